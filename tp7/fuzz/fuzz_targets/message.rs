@@ -0,0 +1,26 @@
+#![no_main]
+
+//! Analyse d'octets quelconques : `DnsMessage::from_bytes` ne doit jamais
+//! paniquer, un message accepté doit se réencoder à l'identique, et la vue
+//! `DnsMessageRef` doit accepter tout ce que l'analyse complète accepte
+
+use libfuzzer_sys::fuzz_target;
+use tp7::{DnsMessage, DnsMessageRef};
+
+fuzz_target!(|data: &[u8]| {
+    let view = DnsMessageRef::parse(data);
+    if let Ok(view) = &view {
+        for record in view.answers().chain(view.authorities()).chain(view.additionals()) {
+            let _ = record.to_string();
+        }
+    }
+
+    if let Ok(message) = DnsMessage::from_bytes(data) {
+        let again = DnsMessage::from_bytes(&message.to_bytes()).expect("message réencodé illisible");
+        assert_eq!(message, again);
+
+        let view = view.expect("message accepté refusé par la vue");
+        assert_eq!(view.to_message().expect("vue illisible"), message);
+        assert_eq!(view.answers().count(), message.answers.len());
+    }
+});
@@ -0,0 +1,17 @@
+#![no_main]
+
+//! Lecture de captures quelconques : ni `read_packets` ni
+//! `extract_messages` ne doivent paniquer, pas plus que l'analyse des
+//! messages extraits
+
+use libfuzzer_sys::fuzz_target;
+use tp7::{DnsMessage, pcap};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(packets) = pcap::read_packets(data) {
+        let (messages, _) = pcap::extract_messages(&packets, &[pcap::DNS_PORT]);
+        for message in messages {
+            let _ = DnsMessage::from_bytes(&message.data);
+        }
+    }
+});
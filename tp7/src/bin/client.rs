@@ -0,0 +1,11 @@
+use std::env;
+
+/// Client DNS seul, mêmes arguments que `tp7 client`
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = tp7::client::run(&args).await {
+        eprintln!("Erreur: {}", e);
+        std::process::exit(1);
+    }
+}
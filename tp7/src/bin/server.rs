@@ -0,0 +1,11 @@
+use std::env;
+
+/// Serveur DNS seul, mêmes options que `tp7 server`
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(e) = tp7::server::run_from_args(&args).await {
+        eprintln!("Erreur: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+use crate::dns_message::{DnsAnswer, DnsMessage, RecordData, RecordType, ResponseCode, labels_to_name, name_labels};
+
+/// TTL des adresses de redirection : court, pour qu'un nom retiré de la
+/// liste ne reste pas bloqué longtemps dans les caches des clients
+const SINKHOLE_TTL: u32 = 60;
+
+/// Noms des fichiers hosts qui désignent la machine elle-même et ne doivent
+/// jamais être bloqués
+const LOCAL_NAMES: [&str; 6] = ["localhost", "localhost.localdomain", "local", "broadcasthost", "ip6-localhost", "ip6-loopback"];
+
+/// Réponse donnée pour un nom bloqué
+#[derive(Debug, Clone, Default)]
+pub struct BlockAction {
+    /// Adresses renvoyées à la place des vraies ; sans adresse de la
+    /// famille demandée, le nom existe mais n'a pas d'enregistrement, et
+    /// sans aucune adresse il n'existe pas (NXDOMAIN)
+    pub sinkhole_v4: Option<Ipv4Addr>,
+    pub sinkhole_v6: Option<Ipv6Addr>,
+}
+
+impl BlockAction {
+    fn is_nxdomain(&self) -> bool {
+        self.sinkhole_v4.is_none() && self.sinkhole_v6.is_none()
+    }
+}
+
+/// Origine d'une règle, pour le journal
+#[derive(Debug, Clone)]
+pub struct Rule {
+    /// Nom bloqué, avec tous ses sous-domaines
+    pub domain: String,
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}. ({}:{})", self.domain, self.file.display(), self.line)
+    }
+}
+
+/// Règles chargées, indexées par nom en minuscules
+#[derive(Default)]
+struct Rules {
+    domains: HashMap<String, Rule>,
+    /// Date de modification de chaque fichier au dernier chargement
+    modified: Vec<Option<SystemTime>>,
+}
+
+/// Listes de blocage au format hosts (`0.0.0.0 pub.example.com`) ou d'un
+/// nom par ligne ; un nom bloque aussi tous ses sous-domaines
+pub struct Blocklist {
+    files: Vec<PathBuf>,
+    action: BlockAction,
+    rules: RwLock<Rules>,
+    blocked: AtomicU64,
+}
+
+impl Blocklist {
+    /// Charge les listes ; un fichier illisible empêche le démarrage
+    pub fn load(files: Vec<PathBuf>, action: BlockAction) -> Result<Self, Box<dyn std::error::Error>> {
+        let rules = load_rules(&files)?;
+        Ok(Self { files, action, rules: RwLock::new(rules), blocked: AtomicU64::new(0) })
+    }
+
+    pub fn action(&self) -> &BlockAction {
+        &self.action
+    }
+
+    /// Nombre de noms bloqués (sans compter leurs sous-domaines)
+    pub fn len(&self) -> usize {
+        self.rules.read().unwrap_or_else(|e| e.into_inner()).domains.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Recharge les listes si l'un des fichiers a changé depuis le dernier
+    /// chargement ; en cas d'erreur, les anciennes règles restent en place.
+    /// Renvoie vrai si les règles ont été remplacées.
+    pub fn reload_if_changed(&self) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let modified: Vec<Option<SystemTime>> = self.files.iter().map(|file| modified(file)).collect();
+        if self.rules.read().unwrap_or_else(|e| e.into_inner()).modified == modified {
+            return Ok(false);
+        }
+
+        let rules = load_rules(&self.files).map_err(|e| e.to_string())?;
+        *self.rules.write().unwrap_or_else(|e| e.into_inner()) = rules;
+        Ok(true)
+    }
+
+    /// Règle qui bloque `name`, ou celle du plus proche parent bloqué
+    pub fn find(&self, name: &str) -> Option<Rule> {
+        let labels = name_labels(name).ok()?;
+        let rules = self.rules.read().unwrap_or_else(|e| e.into_inner());
+        (0..labels.len()).find_map(|start| rules.domains.get(&labels_to_name(&labels[start..]).to_ascii_lowercase()).cloned())
+    }
+
+    /// Réponse à une question portant sur un nom bloqué
+    pub fn answer(&self, query: &DnsMessage) -> DnsMessage {
+        self.blocked.fetch_add(1, Ordering::Relaxed);
+        let question = &query.questions[0];
+
+        let mut response = DnsMessage::new_response(query, vec![]);
+        if self.action.is_nxdomain() {
+            response.header.flags.rcode = ResponseCode::NXDomain;
+            return response;
+        }
+
+        let qtype = RecordType::from(question.qtype);
+        let name = question.name.clone();
+        if matches!(qtype, RecordType::A | RecordType::ANY) && let Some(addr) = self.action.sinkhole_v4 {
+            response.answers.push(DnsAnswer::with_ttl(name.clone(), SINKHOLE_TTL, RecordData::A(addr)));
+        }
+        if matches!(qtype, RecordType::AAAA | RecordType::ANY) && let Some(addr) = self.action.sinkhole_v6 {
+            response.answers.push(DnsAnswer::with_ttl(name, SINKHOLE_TTL, RecordData::AAAA(addr)));
+        }
+        response
+    }
+
+    /// Requêtes bloquées depuis le démarrage
+    pub fn blocked(&self) -> u64 {
+        self.blocked.load(Ordering::Relaxed)
+    }
+}
+
+fn modified(file: &Path) -> Option<SystemTime> {
+    std::fs::metadata(file).and_then(|metadata| metadata.modified()).ok()
+}
+
+fn load_rules(files: &[PathBuf]) -> Result<Rules, Box<dyn std::error::Error>> {
+    let mut rules = Rules::default();
+    for file in files {
+        // La date est relevée avant la lecture : une modification pendant
+        // le chargement sera vue au prochain passage
+        rules.modified.push(modified(file));
+        let text = std::fs::read_to_string(file).map_err(|e| format!("{}: {}", file.display(), e))?;
+
+        let mut ignored = 0;
+        for (index, line) in text.lines().enumerate() {
+            for domain in parse_line(line) {
+                match canonical_domain(domain) {
+                    Some(domain) => {
+                        let rule = Rule { domain: domain.clone(), file: file.clone(), line: index + 1 };
+                        rules.domains.entry(domain).or_insert(rule);
+                    }
+                    None => ignored += 1,
+                }
+            }
+        }
+        if ignored > 0 {
+            println!("Liste de blocage {}: {} nom(s) invalide(s) ignoré(s)", file.display(), ignored);
+        }
+    }
+    Ok(rules)
+}
+
+/// Noms d'une ligne : après l'adresse pour le format hosts, seul sur la
+/// ligne sinon ; `#` commence un commentaire
+fn parse_line(line: &str) -> Vec<&str> {
+    let line = line.split_once('#').map_or(line, |(line, _)| line);
+    let mut fields = line.split_whitespace();
+    let Some(first) = fields.next() else {
+        return vec![];
+    };
+    if first.parse::<IpAddr>().is_ok() {
+        fields.filter(|name| !LOCAL_NAMES.contains(&name.to_ascii_lowercase().as_str())).collect()
+    } else {
+        vec![first]
+    }
+}
+
+/// Forme canonique d'un nom de la liste (`*.` et point final retirés) ;
+/// `None` pour un nom invalide ou la racine
+fn canonical_domain(domain: &str) -> Option<String> {
+    let domain = domain.strip_prefix("*.").unwrap_or(domain);
+    let labels = name_labels(domain).ok().filter(|labels| !labels.is_empty())?;
+    Some(labels_to_name(&labels).to_ascii_lowercase())
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use crate::dns_message::{DnsAnswer, RecordData, ResponseCode};
+
+/// Réponse conservée dans le cache, positive ou négative
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub rcode: ResponseCode,
+    pub answers: Vec<DnsAnswer>,
+    pub authorities: Vec<DnsAnswer>,
+}
+
+struct CacheEntry {
+    response: CachedResponse,
+    stored_at: Instant,
+    expires_at: Instant,
+}
+
+/// Cache des réponses obtenues auprès des serveurs amont.
+///
+/// Les entrées sont lues en parallèle (verrou en lecture) et servies avec
+/// des TTL décrémentés du temps passé dans le cache.
+pub struct DnsCache {
+    entries: RwLock<HashMap<(String, u16), CacheEntry>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Default for DnsCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DnsCache {
+    pub fn new() -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Cherche une réponse encore valide pour (`name`, `qtype`)
+    pub fn get(&self, name: &str, qtype: u16) -> Option<CachedResponse> {
+        let key = (name.to_ascii_lowercase(), qtype);
+        let now = Instant::now();
+
+        let found = {
+            let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+            entries.get(&key).filter(|entry| entry.expires_at > now).map(|entry| {
+                let elapsed = now.duration_since(entry.stored_at).as_secs() as u32;
+                let mut response = entry.response.clone();
+                for record in response.answers.iter_mut().chain(response.authorities.iter_mut()) {
+                    record.ttl = record.ttl.saturating_sub(elapsed);
+                }
+                response
+            })
+        };
+
+        match found {
+            Some(response) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(response)
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                // Purger l'entrée expirée, s'il y en a une
+                let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+                if entries.get(&key).is_some_and(|entry| entry.expires_at <= now) {
+                    entries.remove(&key);
+                }
+                None
+            }
+        }
+    }
+
+    /// Enregistre une réponse si elle peut être mise en cache.
+    ///
+    /// Une réponse positive vit aussi longtemps que son plus petit TTL ; une
+    /// réponse négative (NXDOMAIN ou NODATA) utilise le SOA de la section
+    /// autorité (RFC 2308) et n'est pas conservée sans lui.
+    pub fn insert(&self, name: &str, qtype: u16, response: CachedResponse) {
+        let ttl = match response.rcode {
+            ResponseCode::NoError if !response.answers.is_empty() => {
+                response.answers.iter().map(|record| record.ttl).min()
+            }
+            ResponseCode::NoError | ResponseCode::NXDomain => {
+                response.authorities.iter().find_map(|record| match record.rdata {
+                    RecordData::SOA { minimum, .. } => Some(record.ttl.min(minimum)),
+                    _ => None,
+                })
+            }
+            _ => None,
+        };
+
+        let ttl = match ttl {
+            Some(ttl) if ttl > 0 => ttl,
+            _ => return,
+        };
+
+        let now = Instant::now();
+        let entry = CacheEntry {
+            response,
+            stored_at: now,
+            expires_at: now + Duration::from_secs(ttl as u64),
+        };
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        entries.insert((name.to_ascii_lowercase(), qtype), entry);
+    }
+
+    /// Compteurs (succès, échecs) depuis le démarrage
+    pub fn stats(&self) -> (u64, u64) {
+        (self.hits.load(Ordering::Relaxed), self.misses.load(Ordering::Relaxed))
+    }
+}
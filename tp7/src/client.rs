@@ -1,75 +1,564 @@
-use tokio::net::UdpSocket;
-use std::net::SocketAddr;
-use crate::dns_message::DnsMessage;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::time::{timeout, timeout_at, Duration, Instant};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use crate::dns_message::{DEFAULT_EDNS_PAYLOAD, DnsAnswer, DnsMessage, DnsQuestion, RecordData, RecordType, ResponseCode};
+use crate::dnssec::{Security, Validator};
+use crate::doh::{self, Method};
+use crate::metrics::Transport;
+use crate::reverse;
+use crate::store::soa_serial;
+use crate::tcp;
+use crate::tls::{self, TlsTarget};
+use crate::transfer::{Transfer, decode_transfer};
+
+/// Délai d'attente par défaut du premier essai ; il double à chaque nouvel essai
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Nombre de nouveaux essais par défaut après le premier
+pub const DEFAULT_RETRIES: u32 = 2;
+
+/// Erreurs renvoyées par le client DNS
+#[derive(Debug)]
+pub enum ClientError {
+    /// Erreur réseau (envoi, réception, connexion TCP)
+    Io(std::io::Error),
+    /// Aucun serveur n'a répondu à temps, après tous les essais
+    Timeout { attempts: u32 },
+    /// Réponse illisible
+    Malformed(String),
+    /// Réponse TCP ne correspondant pas à la requête (ID ou question)
+    Mismatch,
+    /// Le serveur a répondu avec un code d'erreur
+    Rcode(ResponseCode),
+    /// Réponse positive mais sans enregistrement du type demandé
+    NoAnswer,
+    /// Nom demandé invalide (label ou nom trop long, échappement incorrect)
+    InvalidName(String),
+    /// Réponse HTTP sans message DNS (état autre que 200, mauvais type)
+    Http(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Io(e) => write!(f, "Erreur réseau: {}", e),
+            ClientError::Timeout { attempts } => write!(f, "Pas de réponse après {} essai(s)", attempts),
+            ClientError::Malformed(e) => write!(f, "Réponse invalide: {}", e),
+            ClientError::Mismatch => write!(f, "La réponse ne correspond pas à la requête"),
+            ClientError::Rcode(rcode) => write!(f, "Réponse {}", rcode),
+            ClientError::NoAnswer => write!(f, "Aucune réponse trouvée"),
+            ClientError::InvalidName(e) => write!(f, "Nom invalide: {}", e),
+            ClientError::Http(e) => write!(f, "Réponse HTTP inattendue: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ClientError {
+    fn from(e: std::io::Error) -> Self {
+        ClientError::Io(e)
+    }
+}
+
+/// Réponse obtenue par un échange, et comment elle l'a été
+pub struct Exchange {
+    pub response: DnsMessage,
+    /// Serveur qui a répondu
+    pub server: SocketAddr,
+    /// Taille de la réponse reçue, en octets
+    pub size: usize,
+    /// Transport par lequel la réponse est arrivée
+    pub transport: Transport,
+}
+
+/// Transport des requêtes du client
+#[derive(Clone)]
+pub enum Protocol {
+    /// UDP, puis TCP si la réponse est tronquée
+    Udp,
+    /// DNS sur TLS (RFC 7858)
+    Tls(TlsTarget),
+    /// DNS sur HTTPS (RFC 8484) à l'adresse `path` du serveur
+    Https { target: TlsTarget, path: String, method: Method },
+}
+
+impl Protocol {
+    /// DNS sur TLS vers un serveur dont le certificat, valable pour
+    /// `server_name`, est signé par une autorité du fichier PEM `ca`
+    pub fn tls(ca: &Path, server_name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Protocol::Tls(TlsTarget::new(ca, server_name, tls::ALPN_DOT)?))
+    }
+
+    /// DNS sur HTTPS, avec les mêmes vérifications du certificat
+    pub fn https(ca: &Path, server_name: &str, path: &str, method: Method) -> Result<Self, Box<dyn std::error::Error>> {
+        let target = TlsTarget::new(ca, server_name, tls::ALPN_HTTP1)?;
+        Ok(Protocol::Https { target, path: path.to_string(), method })
+    }
+}
 
 pub struct DnsClient {
-    socket: UdpSocket,
-    server_addr: SocketAddr,
+    /// Sockets UDP IPv4 et IPv6, ouverts selon les familles des serveurs
+    socket_v4: Option<UdpSocket>,
+    socket_v6: Option<UdpSocket>,
+    /// Serveurs interrogés à tour de rôle, un par essai
+    servers: Vec<SocketAddr>,
+    /// Taille de réponse UDP annoncée au serveur ; `None` désactive EDNS
+    edns_payload_size: Option<u16>,
+    timeout: Duration,
+    retries: u32,
+    /// Affiche le déroulement des échanges (envois, essais, paquets ignorés)
+    verbose: bool,
+    /// Ancres de confiance (DS ou DNSKEY) pour la validation DNSSEC
+    trust_anchors: Vec<DnsAnswer>,
+    protocol: Protocol,
 }
 
 impl DnsClient {
-    pub async fn new(server_addr: SocketAddr) -> Result<Self, Box<dyn std::error::Error>> {
-        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    pub async fn new(server_addr: SocketAddr) -> Result<Self, ClientError> {
+        Self::with_servers(vec![server_addr]).await
+    }
+
+    /// Client interrogeant plusieurs serveurs : chaque nouvel essai passe au
+    /// serveur suivant de la liste
+    pub async fn with_servers(servers: Vec<SocketAddr>) -> Result<Self, ClientError> {
+        if servers.is_empty() {
+            return Err(ClientError::Io(std::io::Error::new(std::io::ErrorKind::InvalidInput, "Aucun serveur DNS")));
+        }
+        
+        // Un socket par famille utilisée : un socket IPv4 ne peut pas
+        // joindre un serveur IPv6, et inversement
+        let mut socket_v4 = None;
+        let mut socket_v6 = None;
+        if let Some(server) = servers.iter().find(|server| server.is_ipv4()) {
+            socket_v4 = Some(bind_udp_for(*server).await?);
+        }
+        if let Some(server) = servers.iter().find(|server| server.is_ipv6()) {
+            socket_v6 = Some(bind_udp_for(*server).await?);
+        }
         
         Ok(Self {
-            socket,
-            server_addr,
+            socket_v4,
+            socket_v6,
+            servers,
+            edns_payload_size: Some(DEFAULT_EDNS_PAYLOAD),
+            timeout: DEFAULT_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+            verbose: true,
+            trust_anchors: Vec::new(),
+            protocol: Protocol::Udp,
         })
     }
 
-    pub async fn resolve(&self, domain: &str) -> Result<String, Box<dyn std::error::Error>> {
-        // Créer une requête DNS
-        let query_id = rand::random::<u16>();
-        let query = DnsMessage::new_query(query_id, domain.to_string());
-        let query_bytes = query.to_bytes();
+    /// Change la taille de réponse UDP annoncée (`None` pour ne pas utiliser EDNS)
+    pub fn set_edns_payload_size(&mut self, size: Option<u16>) {
+        self.edns_payload_size = size;
+    }
+
+    /// Change le délai d'attente du premier essai
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Active ou coupe l'affichage du déroulement des échanges
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
+    /// Ajoute une ancre de confiance : les réponses de sa zone et des zones
+    /// qu'elle délègue pourront être validées
+    pub fn add_trust_anchor(&mut self, anchor: DnsAnswer) {
+        self.trust_anchors.push(anchor);
+    }
+
+    /// Change le transport des requêtes (les transferts de zone restent en TCP)
+    pub fn set_protocol(&mut self, protocol: Protocol) {
+        self.protocol = protocol;
+    }
 
-        // Envoyer la requête
-        self.socket.send_to(&query_bytes, &self.server_addr).await?;
-        println!("Requête envoyée pour: {}", domain);
+    /// Change le nombre de nouveaux essais après le premier
+    pub fn set_retries(&mut self, retries: u32) {
+        self.retries = retries;
+    }
 
-        // Recevoir la réponse
-        let mut buffer = [0u8; 512];
-        let (size, _) = self.socket.recv_from(&mut buffer).await?;
+    pub async fn resolve(&self, domain: &str) -> Result<String, ClientError> {
+        let records = self.lookup(domain, RecordType::A).await?;
         
-        // Parser la réponse
-        let response = DnsMessage::from_bytes(&buffer[..size])?;
+        // Extraire l'adresse IP de la première réponse
+        records
+            .iter()
+            .find_map(|record| match record {
+                RecordData::A(ip) => Some(ip.to_string()),
+                _ => None,
+            })
+            .ok_or(ClientError::NoAnswer)
+    }
+
+    /// Recherche inverse : nom associé à une adresse IPv4 ou IPv6
+    pub async fn reverse(&self, ip: IpAddr) -> Result<String, ClientError> {
+        let records = self.lookup(&reverse::reverse_name(ip), RecordType::PTR).await?;
         
-        if response.header.id != query_id {
-            return Err("ID de réponse invalide".into());
+        records
+            .iter()
+            .find_map(|record| match record {
+                RecordData::PTR(name) => Some(name.clone()),
+                _ => None,
+            })
+            .ok_or(ClientError::NoAnswer)
+    }
+
+    /// Interroge le serveur pour un type donné et renvoie les données typées
+    /// de la section réponse
+    pub async fn lookup(&self, domain: &str, qtype: RecordType) -> Result<Vec<RecordData>, ClientError> {
+        let response = self.query(domain, qtype).await?;
+
+        if response.rcode() != ResponseCode::NoError {
+            return Err(ClientError::Rcode(response.rcode()));
         }
 
         if response.answers.is_empty() {
-            return Err("Aucune réponse trouvée".into());
+            return Err(ClientError::NoAnswer);
         }
 
-        // Extraire l'adresse IP de la première réponse
-        let answer = &response.answers[0];
-        if answer.rdata.len() == 4 {
-            let ip = format!("{}.{}.{}.{}", 
-                answer.rdata[0], answer.rdata[1], 
-                answer.rdata[2], answer.rdata[3]);
-            Ok(ip)
-        } else {
-            Err("Format de réponse invalide".into())
+        Ok(response.answers.into_iter().map(|answer| answer.rdata).collect())
+    }
+
+    /// Envoie une requête et renvoie la réponse complète
+    pub async fn query(&self, domain: &str, qtype: RecordType) -> Result<DnsMessage, ClientError> {
+        let mut builder = DnsMessage::builder().id(rand::random::<u16>()).question(domain, qtype).rd(true);
+        if let Some(size) = self.edns_payload_size {
+            builder = builder.edns(size);
+        }
+        let query = builder.build().map_err(ClientError::InvalidName)?;
+        
+        self.exchange(&query).await
+    }
+
+    /// Comme `query`, avec le bit DO : le serveur joint les RRSIG et NSEC
+    pub async fn query_dnssec(&self, domain: &str, qtype: RecordType) -> Result<DnsMessage, ClientError> {
+        let query = DnsMessage::builder()
+            .id(rand::random::<u16>())
+            .question(domain, qtype)
+            .rd(true)
+            .edns(self.edns_payload_size.unwrap_or(DEFAULT_EDNS_PAYLOAD))
+            .dnssec_ok(true)
+            .build()
+            .map_err(ClientError::InvalidName)?;
+
+        self.exchange(&query).await
+    }
+
+    /// Interroge le serveur avec le bit DO et valide la réponse depuis les
+    /// ancres de confiance : secure, insecure ou bogus
+    pub async fn validate(&self, domain: &str, qtype: RecordType) -> Result<(DnsMessage, Security), ClientError> {
+        let response = self.query_dnssec(domain, qtype).await?;
+        let security = Validator::new(self, &self.trust_anchors).validate(&response).await?;
+        Ok((response, security))
+    }
+
+    /// Met à jour `zone` (RFC 2136) : les modifications ne sont appliquées que
+    /// si tous les prérequis sont satisfaits, sinon le serveur renvoie le
+    /// code du premier prérequis en échec
+    pub async fn update(&self, zone: &str, prerequisites: Vec<DnsAnswer>, updates: Vec<DnsAnswer>) -> Result<(), ClientError> {
+        let mut message = DnsMessage::new_update(rand::random::<u16>(), zone.to_string());
+        message.answers = prerequisites;
+        message.authorities = updates;
+        
+        let response = self.exchange(&message).await?;
+        match response.rcode() {
+            ResponseCode::NoError => Ok(()),
+            rcode => Err(ClientError::Rcode(rcode)),
+        }
+    }
+
+    /// Signale au serveur que `zone` a changé (NOTIFY, RFC 1996)
+    pub async fn notify(&self, zone: &str) -> Result<(), ClientError> {
+        let message = DnsMessage::new_notify(rand::random::<u16>(), zone.to_string());
+        let response = self.exchange(&message).await?;
+        match response.rcode() {
+            ResponseCode::NoError => Ok(()),
+            rcode => Err(ClientError::Rcode(rcode)),
+        }
+    }
+
+    /// Transfère `zone` depuis le premier serveur, en TCP : IXFR à partir de
+    /// la version `current` si elle est fournie, AXFR sinon
+    pub async fn transfer(&self, zone: &str, current: Option<&DnsAnswer>) -> Result<Transfer, ClientError> {
+        let qtype = if current.is_some() { RecordType::IXFR } else { RecordType::AXFR };
+        let mut query = DnsMessage::new_typed_query(rand::random::<u16>(), zone.to_string(), qtype);
+        query.header.flags.rd = false;
+        query.authorities.extend(current.cloned());
+        let known_serial = current.and_then(soa_serial);
+        
+        let server = self.servers[0];
+        let mut stream = match timeout(self.timeout, TcpStream::connect(server)).await {
+            Ok(stream) => stream?,
+            Err(_) => return Err(ClientError::Timeout { attempts: 1 }),
+        };
+        tcp::write_message(&mut stream, &query.to_bytes()).await?;
+        if self.verbose {
+            println!("Transfert {} de {}. demandé à {}", qtype, zone, server);
+        }
+        
+        // La réponse peut s'étaler sur plusieurs messages
+        let mut records = Vec::new();
+        loop {
+            let data = match timeout(self.timeout, tcp::read_message(&mut stream)).await {
+                Ok(result) => result?.ok_or(ClientError::Malformed("Connexion fermée pendant le transfert".to_string()))?,
+                Err(_) => return Err(ClientError::Timeout { attempts: 1 }),
+            };
+            let response = DnsMessage::from_bytes(&data).map_err(|e| ClientError::Malformed(e.to_string()))?;
+            if response.header.id != query.header.id || !response.header.flags.qr {
+                return Err(ClientError::Mismatch);
+            }
+            if response.rcode() != ResponseCode::NoError {
+                return Err(ClientError::Rcode(response.rcode()));
+            }
+            
+            records.extend(response.answers);
+            if let Some(transfer) = decode_transfer(&records, known_serial).map_err(ClientError::Malformed)? {
+                return Ok(transfer);
+            }
+        }
+    }
+
+    /// Envoie `query` avec nouveaux essais et rotation des serveurs. En UDP,
+    /// si la réponse est tronquée (bit TC), la requête est reposée en TCP au
+    /// serveur qui a répondu.
+    pub async fn exchange(&self, query: &DnsMessage) -> Result<DnsMessage, ClientError> {
+        self.exchange_detailed(query).await.map(|exchange| exchange.response)
+    }
+
+    /// Comme `exchange`, avec le détail de l'échange
+    pub async fn exchange_detailed(&self, query: &DnsMessage) -> Result<Exchange, ClientError> {
+        if !matches!(self.protocol, Protocol::Udp) {
+            return self.exchange_encrypted(query).await;
+        }
+        let query_bytes = query.to_bytes();
+        let attempts = self.retries + 1;
+        
+        for attempt in 0..attempts {
+            let server = self.servers[attempt as usize % self.servers.len()];
+            // Attente exponentielle : 1x, 2x, 4x... le délai de base
+            let wait = self.timeout.saturating_mul(1 << attempt.min(16));
+            
+            // Envoyer la requête
+            self.socket_for(server).send_to(&query_bytes, server).await?;
+            if self.verbose {
+                println!("Requête envoyée à {} pour: {} ({})", server, query.questions[0].name, RecordType::from(query.questions[0].qtype));
+            }
+            
+            if let Some((response, size)) = self.receive_udp(query, server, Instant::now() + wait).await? {
+                if response.header.flags.tc {
+                    if self.verbose {
+                        println!("Réponse tronquée, nouvel essai en TCP");
+                    }
+                    return self.exchange_tcp(query, server).await;
+                }
+                return Ok(Exchange { response, server, size, transport: Transport::Udp });
+            }
+            
+            if self.verbose {
+                println!("Pas de réponse de {} après {:?}", server, wait);
+            }
+        }
+        
+        Err(ClientError::Timeout { attempts })
+    }
+
+    /// Socket UDP de la famille de `server`, ouvert à la création du client
+    fn socket_for(&self, server: SocketAddr) -> &UdpSocket {
+        let socket = if server.is_ipv4() { &self.socket_v4 } else { &self.socket_v6 };
+        socket.as_ref().expect("socket ouvert pour chaque famille de serveurs")
+    }
+
+    /// Attend la réponse de `server` jusqu'à `deadline`.
+    ///
+    /// Les paquets venant d'une autre adresse, ou dont l'ID ou la question
+    /// ne correspondent pas, sont ignorés : ce peut être une tentative
+    /// d'empoisonnement ou la réponse tardive à un essai précédent.
+    async fn receive_udp(&self, query: &DnsMessage, server: SocketAddr, deadline: Instant) -> Result<Option<(DnsMessage, usize)>, ClientError> {
+        // Recevoir la réponse, dans la limite annoncée au serveur
+        let size = query.edns.as_ref().map_or(512, |edns| edns.payload_size.max(512));
+        let mut buffer = vec![0u8; size as usize];
+        
+        loop {
+            let (size, from) = match timeout_at(deadline, self.socket_for(server).recv_from(&mut buffer)).await {
+                Ok(result) => result?,
+                Err(_) => return Ok(None),
+            };
+            
+            if from != server {
+                if self.verbose {
+                    println!("Paquet ignoré: provient de {} au lieu de {}", from, server);
+                }
+                continue;
+            }
+            
+            // Parser la réponse
+            let response = match DnsMessage::from_bytes(&buffer[..size]) {
+                Ok(response) => response,
+                Err(e) => {
+                    if self.verbose {
+                        println!("Paquet ignoré: {}", e);
+                    }
+                    continue;
+                }
+            };
+            
+            if !matches_query(query, &response) {
+                if self.verbose {
+                    println!("Paquet ignoré: ID ou question différents de la requête");
+                }
+                continue;
+            }
+            
+            return Ok(Some((response, size)));
+        }
+    }
+
+    /// Échange une requête sur une connexion TCP dédiée (RFC 1035 §4.2.2)
+    pub async fn exchange_tcp(&self, query: &DnsMessage, server: SocketAddr) -> Result<Exchange, ClientError> {
+        let exchange = async {
+            let mut stream = TcpStream::connect(server).await?;
+            tcp::write_message(&mut stream, &query.to_bytes()).await?;
+            tcp::read_message(&mut stream).await
+        };
+        
+        let data = match timeout(self.timeout, exchange).await {
+            Ok(result) => result?.ok_or(ClientError::Malformed("Connexion TCP fermée sans réponse".to_string()))?,
+            Err(_) => return Err(ClientError::Timeout { attempts: 1 }),
+        };
+        let response = DnsMessage::from_bytes(&data).map_err(|e| ClientError::Malformed(e.to_string()))?;
+        
+        if !matches_query(query, &response) {
+            return Err(ClientError::Mismatch);
+        }
+
+        Ok(Exchange { response, server, size: data.len(), transport: Transport::Tcp })
+    }
+
+    /// Échange sur une connexion TLS par essai ; seule l'absence de réponse
+    /// à temps fait passer au serveur suivant
+    async fn exchange_encrypted(&self, query: &DnsMessage) -> Result<Exchange, ClientError> {
+        let attempts = self.retries + 1;
+        for attempt in 0..attempts {
+            let server = self.servers[attempt as usize % self.servers.len()];
+            let wait = self.timeout.saturating_mul(1 << attempt.min(16));
+            if self.verbose {
+                let transport = if matches!(self.protocol, Protocol::Tls(_)) { Transport::Tls } else { Transport::Https };
+                println!("Requête envoyée à {} ({}) pour: {} ({})", server, transport, query.questions[0].name, RecordType::from(query.questions[0].qtype));
+            }
+            
+            match timeout(wait, self.exchange_over_tls(query, server)).await {
+                Ok(result) => return result,
+                Err(_) if self.verbose => println!("Pas de réponse de {} après {:?}", server, wait),
+                Err(_) => {}
+            }
+        }
+        
+        Err(ClientError::Timeout { attempts })
+    }
+
+    async fn exchange_over_tls(&self, query: &DnsMessage, server: SocketAddr) -> Result<Exchange, ClientError> {
+        match &self.protocol {
+            Protocol::Tls(target) => {
+                let mut stream = target.connect(server).await?;
+                tcp::write_message(&mut stream, &query.to_bytes()).await?;
+                let data = tcp::read_message(&mut stream)
+                    .await?
+                    .ok_or(ClientError::Malformed("Connexion TLS fermée sans réponse".to_string()))?;
+                let response = DnsMessage::from_bytes(&data).map_err(|e| ClientError::Malformed(e.to_string()))?;
+                if !matches_query(query, &response) {
+                    return Err(ClientError::Mismatch);
+                }
+                Ok(Exchange { response, server, size: data.len(), transport: Transport::Tls })
+            }
+            Protocol::Https { target, path, method } => {
+                // ID 0 sur le fil, pour que les caches HTTP partagent les
+                // réponses (RFC 8484 §4.1) ; la connexion TLS protège déjà
+                // l'échange
+                let mut wire = query.clone();
+                wire.header.id = 0;
+                let mut stream = target.connect(server).await?;
+                let reply = doh::exchange(&mut stream, target.host(), path, *method, &wire.to_bytes()).await?;
+                if reply.status() != Some(200) {
+                    return Err(ClientError::Http(reply.start_line));
+                }
+                if !reply.is_dns_message() {
+                    return Err(ClientError::Http(format!("type {}", reply.header("Content-Type").unwrap_or("absent"))));
+                }
+                
+                let mut response = DnsMessage::from_bytes(&reply.body).map_err(|e| ClientError::Malformed(e.to_string()))?;
+                if !matches_query(&wire, &response) {
+                    return Err(ClientError::Mismatch);
+                }
+                response.header.id = query.header.id;
+                Ok(Exchange { response, server, size: reply.body.len(), transport: Transport::Https })
+            }
+            Protocol::Udp => unreachable!("UDP ne passe pas par TLS"),
         }
     }
 }
 
-// Fonction utilitaire pour les tests
-pub async fn test_client() -> Result<(), Box<dyn std::error::Error>> {
-    let server_addr = "127.0.0.1:5353".parse()?;
-    let client = DnsClient::new(server_addr).await?;
+/// Socket UDP local, sur un port quelconque, de la même famille que `server`
+pub async fn bind_udp_for(server: SocketAddr) -> std::io::Result<UdpSocket> {
+    let local = match server {
+        SocketAddr::V4(_) => SocketAddr::from(([0, 0, 0, 0], 0)),
+        SocketAddr::V6(_) => SocketAddr::from(([0u16; 8], 0)),
+    };
+    UdpSocket::bind(local).await
+}
+
+/// Vérifie qu'une réponse correspond bien à la requête : même ID, bit QR, et
+/// même question (RFC 5452). Les réponses d'erreur peuvent omettre la question.
+fn matches_query(query: &DnsMessage, response: &DnsMessage) -> bool {
+    if response.header.id != query.header.id || !response.header.flags.qr {
+        return false;
+    }
     
-    println!("=== Test du client DNS ===");
+    if response.questions.is_empty() {
+        return response.rcode() != ResponseCode::NoError;
+    }
     
-    let domains = vec!["example.com", "google.com", "github.com"];
+    let same_question = |a: &DnsQuestion, b: &DnsQuestion| {
+        a.name.eq_ignore_ascii_case(&b.name) && a.qtype == b.qtype && a.qclass == b.qclass
+    };
+    response.questions.len() == query.questions.len()
+        && query.questions.iter().zip(&response.questions).all(|(a, b)| same_question(a, b))
+}
+
+/// Sous-commande `client` (et binaire du même nom) : `<domaine|ip> [serveur...]`,
+/// le serveur local de test par défaut ; une adresse IP donne lieu à une
+/// recherche inverse (PTR)
+pub async fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(domain) = args.first() else {
+        println!("Usage: client <domain|ip> [server...]");
+        return Ok(());
+    };
     
-    for domain in domains {
-        match client.resolve(domain).await {
-            Ok(ip) => println!("{} -> {}", domain, ip),
-            Err(e) => println!("Erreur pour {}: {}", domain, e),
-        }
+    let mut servers = args[1..].iter().map(|addr| addr.parse()).collect::<Result<Vec<_>, _>>()?;
+    if servers.is_empty() {
+        servers.push("127.0.0.1:5353".parse()?);
     }
+    let client = DnsClient::with_servers(servers).await?;
     
+    let result = match domain.parse() {
+        Ok(ip) => client.reverse(ip).await,
+        Err(_) => client.resolve(domain).await,
+    };
+    match result {
+        Ok(answer) => println!("{} -> {}", domain, answer),
+        Err(e) => println!("Erreur: {}", e),
+    }
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,248 @@
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use tokio::time::{Duration, Instant};
+use tp7::client::{DnsClient, Protocol};
+use tp7::dns_message::{CLASS_IN, DEFAULT_EDNS_PAYLOAD, DnsMessage, RecordType, name_labels, parse_class};
+use tp7::doh::{self, Method};
+use tp7::metrics::Transport;
+use tp7::reverse;
+
+/// Serveur interrogé sans `@serveur` : le serveur de test local
+const DEFAULT_SERVER: &str = "127.0.0.1:5353";
+
+/// Port utilisé quand `@serveur` ne précise qu'une adresse
+const DEFAULT_PORT: u16 = 53;
+
+/// Ports de DNS sur TLS (RFC 7858) et de HTTPS
+const DEFAULT_TLS_PORT: u16 = 853;
+const DEFAULT_HTTPS_PORT: u16 = 443;
+
+/// Options de la sous-commande `dig`, dans l'esprit de l'outil du même nom
+struct DigOptions {
+    server: SocketAddr,
+    name: String,
+    qtype: RecordType,
+    qclass: u16,
+    /// Bit RD (`+rec` / `+norec`)
+    recursion: bool,
+    /// Bit CD (`+cd`)
+    checking_disabled: bool,
+    /// Bit DO de l'OPT (`+dnssec`), qui implique EDNS
+    dnssec: bool,
+    /// Taille UDP annoncée ; `None` avec `+noedns`
+    edns_payload_size: Option<u16>,
+    tcp: bool,
+    /// DNS sur TLS (`+tls`)
+    tls: bool,
+    /// DNS sur HTTPS (`+https[=chemin]`, `+https-get[=chemin]`)
+    https: Option<(String, Method)>,
+    /// Autorités acceptées pour le certificat du serveur (`+tls-ca=fichier`)
+    tls_ca: Option<PathBuf>,
+    /// Nom attendu dans le certificat (`+tls-hostname=nom`), l'adresse du
+    /// serveur par défaut
+    tls_hostname: Option<String>,
+    short: bool,
+    timeout: Duration,
+    retries: u32,
+}
+
+impl DigOptions {
+    /// `[@serveur[:port]] [-p port] [-x adresse] [nom] [type] [classe] [+option]...`
+    fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut options = Self {
+            server: DEFAULT_SERVER.parse().expect("adresse par défaut valide"),
+            name: String::new(),
+            qtype: RecordType::NS,
+            qclass: CLASS_IN,
+            recursion: true,
+            checking_disabled: false,
+            dnssec: false,
+            edns_payload_size: Some(DEFAULT_EDNS_PAYLOAD),
+            tcp: false,
+            tls: false,
+            https: None,
+            tls_ca: None,
+            tls_hostname: None,
+            short: false,
+            timeout: Duration::from_secs(2),
+            retries: 2,
+        };
+        let mut server: Option<&str> = None;
+        let mut port: Option<u16> = None;
+        let mut name: Option<String> = None;
+        let mut qtype: Option<RecordType> = None;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = |option: &str| args.next().ok_or_else(|| format!("{} attend une valeur", option));
+
+            if let Some(address) = arg.strip_prefix('@') {
+                server = Some(address);
+            } else if let Some(option) = arg.strip_prefix('+') {
+                options.set_flag(option)?;
+            } else if arg == "-p" {
+                port = Some(value("-p")?.parse().map_err(|_| "port invalide".to_string())?);
+            } else if arg == "-t" {
+                qtype = Some(value("-t")?.parse()?);
+            } else if arg == "-c" {
+                let class = value("-c")?;
+                options.qclass = parse_class(class).ok_or_else(|| format!("classe inconnue: {}", class))?;
+            } else if arg == "-x" {
+                let address = value("-x")?;
+                let ip: IpAddr = address.parse().map_err(|_| format!("adresse invalide: {}", address))?;
+                name = Some(reverse::reverse_name(ip));
+                qtype = qtype.or(Some(RecordType::PTR));
+            } else if arg.starts_with('-') {
+                return Err(format!("option inconnue: {}", arg));
+            } else if let (Some(_), Ok(rtype)) = (&name, arg.parse::<RecordType>()) {
+                // Après le nom, un type ou une classe ; avant, dig accepte
+                // aussi `dig MX example.com`
+                qtype = Some(rtype);
+            } else if let Some(class) = name.as_ref().and(parse_class(arg)) {
+                options.qclass = class;
+            } else if let (None, Ok(rtype)) = (&qtype, arg.parse::<RecordType>()) {
+                qtype = Some(rtype);
+            } else {
+                name = Some(arg.trim_end_matches('.').to_string());
+            }
+        }
+
+        // Sans nom, dig demande les serveurs de la racine
+        if let Some(name) = name {
+            name_labels(&name)?;
+            options.name = name;
+            options.qtype = qtype.unwrap_or(RecordType::A);
+        } else if let Some(rtype) = qtype {
+            options.qtype = rtype;
+        }
+
+        if let Some(server) = server {
+            let default_port = match (&options.https, options.tls) {
+                (Some(_), _) => DEFAULT_HTTPS_PORT,
+                (None, true) => DEFAULT_TLS_PORT,
+                (None, false) => DEFAULT_PORT,
+            };
+            options.server = match server.parse::<SocketAddr>() {
+                Ok(address) => address,
+                Err(_) => {
+                    let ip: IpAddr = server.parse().map_err(|_| format!("serveur invalide: {}", server))?;
+                    SocketAddr::new(ip, default_port)
+                }
+            };
+        }
+        if let Some(port) = port {
+            options.server.set_port(port);
+        }
+        if (options.tls || options.https.is_some()) && options.tls_ca.is_none() {
+            return Err("+tls et +https demandent +tls-ca=<fichier>".to_string());
+        }
+
+        Ok(options)
+    }
+
+    /// Options `+nom`, `+nonom` et `+nom=valeur`
+    fn set_flag(&mut self, option: &str) -> Result<(), String> {
+        let (name, value) = match option.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (option, None),
+        };
+        let number = |value: Option<&str>| {
+            value
+                .and_then(|value| value.parse::<u32>().ok())
+                .ok_or_else(|| format!("+{} attend un nombre", name))
+        };
+        let (enabled, flag) = match name.strip_prefix("no") {
+            Some(flag) => (false, flag),
+            None => (true, name),
+        };
+
+        match flag {
+            "rec" | "recurse" => self.recursion = enabled,
+            "cd" | "cdflag" => self.checking_disabled = enabled,
+            "dnssec" => self.dnssec = enabled,
+            "tcp" | "vc" => self.tcp = enabled,
+            "tls" => self.tls = enabled,
+            "https" | "https-post" => {
+                self.https = enabled.then(|| (value.unwrap_or(doh::DEFAULT_PATH).to_string(), Method::Post));
+            }
+            "https-get" => self.https = enabled.then(|| (value.unwrap_or(doh::DEFAULT_PATH).to_string(), Method::Get)),
+            "tls-ca" => self.tls_ca = Some(PathBuf::from(value.ok_or("+tls-ca attend un fichier")?)),
+            "tls-hostname" => self.tls_hostname = Some(value.ok_or("+tls-hostname attend un nom")?.to_string()),
+            "short" => self.short = enabled,
+            "edns" => self.edns_payload_size = enabled.then_some(DEFAULT_EDNS_PAYLOAD),
+            "bufsize" => self.edns_payload_size = Some(number(value)?.min(u16::MAX as u32) as u16),
+            "time" => self.timeout = Duration::from_secs(number(value)?.max(1) as u64),
+            "tries" => self.retries = number(value)?.max(1) - 1,
+            _ => return Err(format!("option inconnue: +{}", option)),
+        }
+        Ok(())
+    }
+}
+
+/// Sous-commande `dig` : envoie une requête et affiche la réponse complète
+/// (ou seulement les données avec `+short`), avec le temps de réponse ;
+/// une requête restée sans réponse est une erreur
+pub async fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let options = DigOptions::from_args(args)?;
+
+    let mut client = DnsClient::new(options.server).await?;
+    client.set_verbose(false);
+    client.set_timeout(options.timeout);
+    client.set_retries(options.retries);
+    if let Some(ca) = &options.tls_ca {
+        let hostname = options.tls_hostname.clone().unwrap_or_else(|| options.server.ip().to_string());
+        let protocol = match &options.https {
+            Some((path, method)) => Protocol::https(ca, &hostname, path, *method)?,
+            None => Protocol::tls(ca, &hostname)?,
+        };
+        client.set_protocol(protocol);
+    }
+
+    let mut builder = DnsMessage::builder()
+        .id(rand::random::<u16>())
+        .question_class(&options.name, options.qtype, options.qclass)
+        .rd(options.recursion)
+        .cd(options.checking_disabled);
+    if let Some(size) = options.edns_payload_size {
+        builder = builder.edns(size);
+    }
+    // Le bit DO n'existe que dans l'OPT, ajouté au besoin
+    if options.dnssec {
+        builder = builder.dnssec_ok(true);
+    }
+    let query = builder.build()?;
+
+    if !options.short {
+        println!("; <<>> tp7 dig <<>> {}", args.join(" "));
+    }
+
+    let started = Instant::now();
+    let result = if options.tcp && !options.tls && options.https.is_none() {
+        client.exchange_tcp(&query, options.server).await
+    } else {
+        client.exchange_detailed(&query).await
+    };
+    let elapsed = started.elapsed();
+
+    let exchange = result.map_err(|e| format!("Échec de la requête à {}: {}", options.server, e))?;
+
+    if options.short {
+        for answer in &exchange.response.answers {
+            println!("{}", answer.rdata);
+        }
+        return Ok(());
+    }
+
+    if exchange.transport == Transport::Tcp && !options.tcp {
+        println!(";; Truncated, retrying in TCP mode.");
+    }
+    println!(";; Got answer:");
+    println!("{}", exchange.response);
+    println!();
+    println!(";; Query time: {} msec", elapsed.as_millis());
+    let transport = exchange.transport.to_string().to_uppercase();
+    println!(";; SERVER: {}#{}({})", exchange.server.ip(), exchange.server.port(), transport);
+    println!(";; MSG SIZE  rcvd: {}", exchange.size);
+
+    Ok(())
+}
@@ -1,42 +1,246 @@
-#[derive(Debug, Clone)]
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// Nombre maximal de pointeurs de compression suivis pour un même nom
+const MAX_POINTER_JUMPS: usize = 64;
+
+/// Décalage maximal adressable par un pointeur de compression (14 bits)
+const MAX_POINTER_OFFSET: usize = 0x3FFF;
+
+/// Longueur maximale d'un label, en octets (RFC 1035 §2.3.4)
+const MAX_LABEL_LENGTH: usize = 63;
+
+/// Longueur maximale d'un nom sous sa forme binaire, octet nul final compris
+const MAX_NAME_LENGTH: usize = 255;
+
+/// Taille minimale d'un enregistrement dans un message : nom racine, type,
+/// classe, TTL et RDLENGTH
+const MIN_RECORD_LENGTH: usize = 11;
+
+/// Taille UDP annoncée par défaut en EDNS, qui évite la fragmentation IP
+/// (valeur retenue par le « DNS flag day » 2020)
+pub const DEFAULT_EDNS_PAYLOAD: u16 = 1232;
+
+/// Table des suffixes déjà écrits dans un message (labels -> position)
+pub type NameCompression = HashMap<Vec<Vec<u8>>, usize>;
+
+/// Classe Internet, la seule servie
+pub const CLASS_IN: u16 = 1;
+
+/// Classes Chaos et Hesiod, reconnues pour l'affichage seulement
+pub const CLASS_CH: u16 = 3;
+pub const CLASS_HS: u16 = 4;
+
+/// Classe NONE, utilisée par les mises à jour dynamiques (RFC 2136 §1.3)
+pub const CLASS_NONE: u16 = 254;
+
+/// Classe ANY (QCLASS *), utilisée aussi par les mises à jour dynamiques
+pub const CLASS_ANY: u16 = 255;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DnsHeader {
     pub id: u16,
-    pub flags: u16,
+    pub flags: DnsFlags,
     pub question_count: u16,
     pub answer_count: u16,
     pub authority_count: u16,
     pub additional_count: u16,
 }
 
-#[derive(Debug, Clone)]
+/// Deuxième mot de l'en-tête (RFC 1035 §4.1.1, bits AD/CD de la RFC 4035)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DnsFlags {
+    /// Réponse (true) ou requête (false)
+    pub qr: bool,
+    pub opcode: Opcode,
+    /// Réponse faisant autorité
+    pub aa: bool,
+    /// Message tronqué
+    pub tc: bool,
+    /// Récursion demandée
+    pub rd: bool,
+    /// Récursion disponible
+    pub ra: bool,
+    /// Bit réservé, doit valoir 0
+    pub z: bool,
+    /// Données authentifiées
+    pub ad: bool,
+    /// Vérification désactivée
+    pub cd: bool,
+    /// Seuls les 4 bits de poids faible sont dans l'en-tête ; voir
+    /// `DnsMessage::rcode` pour le code étendu par EDNS
+    pub rcode: ResponseCode,
+}
+
+/// Type de requête (champ OPCODE)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Opcode {
+    #[default]
+    Query,
+    Status,
+    Notify,
+    Update,
+    Other(u8),
+}
+
+/// Code de retour (champ RCODE)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResponseCode {
+    #[default]
+    NoError,
+    FormErr,
+    ServFail,
+    NXDomain,
+    NotImp,
+    Refused,
+    /// Le nom existe alors qu'il ne devrait pas (RFC 2136)
+    YXDomain,
+    /// Un RRset existe alors qu'il ne devrait pas (RFC 2136)
+    YXRRSet,
+    /// Un RRset qui devrait exister n'existe pas (RFC 2136)
+    NXRRSet,
+    /// Le serveur ne fait pas autorité pour la zone (RFC 2136)
+    NotAuth,
+    /// Un nom est en dehors de la zone (RFC 2136)
+    NotZone,
+    /// Version EDNS non supportée (code étendu 16, RFC 6891)
+    BadVers,
+    Other(u16),
+}
+
+/// Les noms sont gardés au format de présentation, sans point final : les
+/// octets d'un label qui ne sont pas des caractères imprimables ordinaires y
+/// sont échappés (`\.`, `\DDD`), ce qui permet de retrouver les labels bruts
+/// (voir `name_labels`)
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DnsQuestion {
     pub name: String,
     pub qtype: u16,  // 1 = A record
     pub qclass: u16, // 1 = IN (Internet)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DnsAnswer {
     pub name: String,
     pub rtype: u16,
     pub rclass: u16,
     pub ttl: u32,
-    pub rdlength: u16,
-    pub rdata: Vec<u8>,
+    pub rdata: RecordData,
 }
 
-#[derive(Debug, Clone)]
+/// Types d'enregistrements connus
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RecordType {
+    A,
+    NS,
+    CNAME,
+    SOA,
+    PTR,
+    MX,
+    TXT,
+    AAAA,
+    SRV,
+    OPT,
+    /// Empreinte d'une clé de la zone fille, publiée par la zone parente
+    /// (RFC 4034 §5)
+    DS,
+    /// Signature d'un RRset (RFC 4034 §3)
+    RRSIG,
+    /// Nom suivant de la zone et types présents, pour prouver une absence
+    /// (RFC 4034 §4)
+    NSEC,
+    /// Clé publique de la zone (RFC 4034 §2)
+    DNSKEY,
+    /// Transfert incrémental de zone (RFC 1995)
+    IXFR,
+    /// Transfert complet de zone (RFC 5936)
+    AXFR,
+    ANY,
+    Other(u16),
+}
+
+/// Données typées d'un enregistrement
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordData {
+    A(Ipv4Addr),
+    AAAA(Ipv6Addr),
+    CNAME(String),
+    NS(String),
+    PTR(String),
+    MX { preference: u16, exchange: String },
+    /// Une ou plusieurs chaînes de 255 octets maximum
+    TXT(Vec<Vec<u8>>),
+    SOA {
+        mname: String,
+        rname: String,
+        serial: u32,
+        refresh: u32,
+        retry: u32,
+        expire: u32,
+        minimum: u32,
+    },
+    SRV { priority: u16, weight: u16, port: u16, target: String },
+    DS { key_tag: u16, algorithm: u8, digest_type: u8, digest: Vec<u8> },
+    /// Les dates sont en secondes depuis 1970, modulo 2^32 (RFC 4034 §3.1.5)
+    RRSIG {
+        type_covered: u16,
+        algorithm: u8,
+        /// Labels du propriétaire, sans l'éventuel joker `*` en tête
+        labels: u8,
+        original_ttl: u32,
+        expiration: u32,
+        inception: u32,
+        key_tag: u16,
+        signer: String,
+        signature: Vec<u8>,
+    },
+    /// Types présents au nom, par ordre croissant
+    NSEC { next: String, types: Vec<u16> },
+    DNSKEY { flags: u16, protocol: u8, algorithm: u8, public_key: Vec<u8> },
+    /// Type non géré : les données sont conservées telles quelles
+    Unknown { rtype: u16, data: Vec<u8> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DnsMessage {
     pub header: DnsHeader,
     pub questions: Vec<DnsQuestion>,
     pub answers: Vec<DnsAnswer>,
+    pub authorities: Vec<DnsAnswer>,
+    /// Section additionnelle, sans le pseudo-enregistrement OPT
+    pub additionals: Vec<DnsAnswer>,
+    /// Pseudo-enregistrement OPT de la section additionnelle (RFC 6891)
+    pub edns: Option<Edns>,
+}
+
+/// Paramètres EDNS(0) portés par le pseudo-enregistrement OPT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edns {
+    /// Taille maximale de réponse UDP acceptée par l'émetteur
+    pub payload_size: u16,
+    /// 8 bits de poids fort du code de retour étendu
+    pub extended_rcode: u8,
+    pub version: u8,
+    /// Bit DO : l'émetteur accepte les enregistrements DNSSEC
+    pub dnssec_ok: bool,
+    pub options: Vec<EdnsOption>,
+}
+
+/// Option EDNS (code et données brutes)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdnsOption {
+    pub code: u16,
+    pub data: Vec<u8>,
 }
 
 impl DnsHeader {
     pub fn new(id: u16) -> Self {
         Self {
             id,
-            flags: 0x0100, // Standard query
+            flags: DnsFlags { rd: true, ..DnsFlags::default() }, // Standard query
             question_count: 0,
             answer_count: 0,
             authority_count: 0,
@@ -47,7 +251,7 @@ impl DnsHeader {
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
         bytes.extend_from_slice(&self.id.to_be_bytes());
-        bytes.extend_from_slice(&self.flags.to_be_bytes());
+        bytes.extend_from_slice(&self.flags.to_u16().to_be_bytes());
         bytes.extend_from_slice(&self.question_count.to_be_bytes());
         bytes.extend_from_slice(&self.answer_count.to_be_bytes());
         bytes.extend_from_slice(&self.authority_count.to_be_bytes());
@@ -56,153 +260,969 @@ impl DnsHeader {
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
-        if bytes.len() < 12 {
-            return Err("Header trop court".into());
-        }
+        let header: [u8; 12] = read_field(bytes, &mut 0, bytes.len()).map_err(|_| "Header trop court")?;
         
         Ok(Self {
-            id: u16::from_be_bytes([bytes[0], bytes[1]]),
-            flags: u16::from_be_bytes([bytes[2], bytes[3]]),
-            question_count: u16::from_be_bytes([bytes[4], bytes[5]]),
-            answer_count: u16::from_be_bytes([bytes[6], bytes[7]]),
-            authority_count: u16::from_be_bytes([bytes[8], bytes[9]]),
-            additional_count: u16::from_be_bytes([bytes[10], bytes[11]]),
+            id: u16::from_be_bytes([header[0], header[1]]),
+            flags: DnsFlags::from_u16(u16::from_be_bytes([header[2], header[3]])),
+            question_count: u16::from_be_bytes([header[4], header[5]]),
+            answer_count: u16::from_be_bytes([header[6], header[7]]),
+            authority_count: u16::from_be_bytes([header[8], header[9]]),
+            additional_count: u16::from_be_bytes([header[10], header[11]]),
         })
     }
 }
 
+impl DnsFlags {
+    pub fn to_u16(self) -> u16 {
+        (self.qr as u16) << 15
+            | (self.opcode.to_u8() as u16 & 0x0F) << 11
+            | (self.aa as u16) << 10
+            | (self.tc as u16) << 9
+            | (self.rd as u16) << 8
+            | (self.ra as u16) << 7
+            | (self.z as u16) << 6
+            | (self.ad as u16) << 5
+            | (self.cd as u16) << 4
+            | (self.rcode.to_u16() & 0x0F)
+    }
+
+    pub fn from_u16(value: u16) -> Self {
+        let bit = |position: u16| value & (1 << position) != 0;
+        Self {
+            qr: bit(15),
+            opcode: Opcode::from(((value >> 11) & 0x0F) as u8),
+            aa: bit(10),
+            tc: bit(9),
+            rd: bit(8),
+            ra: bit(7),
+            z: bit(6),
+            ad: bit(5),
+            cd: bit(4),
+            rcode: ResponseCode::from(value & 0x0F),
+        }
+    }
+}
+
+impl fmt::Display for DnsFlags {
+    /// Liste des bits positionnés, à la manière de dig (`qr aa rd`)
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bits = [
+            (self.qr, "qr"), (self.aa, "aa"), (self.tc, "tc"), (self.rd, "rd"),
+            (self.ra, "ra"), (self.ad, "ad"), (self.cd, "cd"),
+        ];
+        let set: Vec<&str> = bits.iter().filter(|(on, _)| *on).map(|(_, name)| *name).collect();
+        write!(f, "{}", set.join(" "))
+    }
+}
+
+impl Opcode {
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Opcode::Query => 0,
+            Opcode::Status => 2,
+            Opcode::Notify => 4,
+            Opcode::Update => 5,
+            Opcode::Other(value) => value,
+        }
+    }
+}
+
+impl From<u8> for Opcode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Opcode::Query,
+            2 => Opcode::Status,
+            4 => Opcode::Notify,
+            5 => Opcode::Update,
+            other => Opcode::Other(other),
+        }
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Opcode::Other(value) => write!(f, "OPCODE{}", value),
+            other => write!(f, "{}", format!("{:?}", other).to_uppercase()),
+        }
+    }
+}
+
+impl ResponseCode {
+    /// Valeur sur 12 bits : les 4 bits de l'en-tête et les 8 bits de l'OPT
+    pub fn to_u16(self) -> u16 {
+        match self {
+            ResponseCode::NoError => 0,
+            ResponseCode::FormErr => 1,
+            ResponseCode::ServFail => 2,
+            ResponseCode::NXDomain => 3,
+            ResponseCode::NotImp => 4,
+            ResponseCode::Refused => 5,
+            ResponseCode::YXDomain => 6,
+            ResponseCode::YXRRSet => 7,
+            ResponseCode::NXRRSet => 8,
+            ResponseCode::NotAuth => 9,
+            ResponseCode::NotZone => 10,
+            ResponseCode::BadVers => 16,
+            ResponseCode::Other(value) => value,
+        }
+    }
+}
+
+impl From<u16> for ResponseCode {
+    fn from(value: u16) -> Self {
+        match value {
+            0 => ResponseCode::NoError,
+            1 => ResponseCode::FormErr,
+            2 => ResponseCode::ServFail,
+            3 => ResponseCode::NXDomain,
+            4 => ResponseCode::NotImp,
+            5 => ResponseCode::Refused,
+            6 => ResponseCode::YXDomain,
+            7 => ResponseCode::YXRRSet,
+            8 => ResponseCode::NXRRSet,
+            9 => ResponseCode::NotAuth,
+            10 => ResponseCode::NotZone,
+            16 => ResponseCode::BadVers,
+            other => ResponseCode::Other(other),
+        }
+    }
+}
+
+impl fmt::Display for ResponseCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResponseCode::Other(value) => write!(f, "RCODE{}", value),
+            other => write!(f, "{}", format!("{:?}", other).to_uppercase()),
+        }
+    }
+}
+
+/// Nom d'une classe (`IN`, `CH`...), ou notation générique `CLASSnnn`
+pub fn class_name(class: u16) -> String {
+    match class {
+        CLASS_IN => "IN".to_string(),
+        CLASS_CH => "CH".to_string(),
+        CLASS_HS => "HS".to_string(),
+        CLASS_NONE => "NONE".to_string(),
+        CLASS_ANY => "ANY".to_string(),
+        other => format!("CLASS{}", other),
+    }
+}
+
+/// Lit un nom de classe, y compris la notation générique de la RFC 3597
+pub fn parse_class(text: &str) -> Option<u16> {
+    let upper = text.to_ascii_uppercase();
+    match upper.as_str() {
+        "IN" => Some(CLASS_IN),
+        "CH" => Some(CLASS_CH),
+        "HS" => Some(CLASS_HS),
+        "NONE" => Some(CLASS_NONE),
+        "ANY" => Some(CLASS_ANY),
+        _ => upper.strip_prefix("CLASS")?.parse().ok(),
+    }
+}
+
 impl DnsQuestion {
-    pub fn new(name: String) -> Self {
+    pub fn with_type(name: String, qtype: RecordType) -> Self {
         Self {
             name,
-            qtype: 1,  // A record
-            qclass: 1, // IN class
+            qtype: qtype.to_u16(),
+            qclass: CLASS_IN,
         }
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
-        
-        // Encoder le nom de domaine
-        for part in self.name.split('.') {
-            bytes.push(part.len() as u8);
-            bytes.extend_from_slice(part.as_bytes());
-        }
-        bytes.push(0); // Null terminator
-        
+    /// Écrit la question à la fin de `bytes`, en compressant le nom
+    /// avec les suffixes déjà présents dans le message
+    pub fn write_to(&self, bytes: &mut Vec<u8>, compression: &mut NameCompression) {
+        encode_name(bytes, &self.name, compression);
         bytes.extend_from_slice(&self.qtype.to_be_bytes());
         bytes.extend_from_slice(&self.qclass.to_be_bytes());
+    }
+
+    pub fn from_bytes(bytes: &[u8], offset: &mut usize) -> Result<Self, Box<dyn std::error::Error>> {
+        let name = decode_name(bytes, offset)?;
         
-        bytes
+        let fields: [u8; 4] = read_field(bytes, offset, bytes.len()).map_err(|_| "Question trop courte")?;
+        let qtype = u16::from_be_bytes([fields[0], fields[1]]);
+        let qclass = u16::from_be_bytes([fields[2], fields[3]]);
+        
+        Ok(Self { name, qtype, qclass })
+    }
+}
+
+impl fmt::Display for DnsQuestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.\t\t{}\t{}", self.name, class_name(self.qclass), RecordType::from(self.qtype))
+    }
+}
+
+impl fmt::Display for DnsAnswer {
+    /// Une ligne de fichier de zone : nom, TTL, classe, type et données
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.\t{}\t{}\t{}\t{}",
+            self.name,
+            self.ttl,
+            class_name(self.rclass),
+            RecordType::from(self.rtype),
+            self.rdata
+        )
+    }
+}
+
+impl DnsAnswer {
+    pub fn with_ttl(name: String, ttl: u32, rdata: RecordData) -> Self {
+        Self {
+            name,
+            rtype: rdata.record_type().to_u16(),
+            rclass: CLASS_IN,
+            ttl,
+            rdata,
+        }
+    }
+
+    /// Enregistrement sans données ni TTL, de classe ANY ou NONE : c'est la
+    /// forme des prérequis et des suppressions de RRset (RFC 2136 §2.4, §2.5)
+    pub fn empty(name: String, rtype: RecordType, rclass: u16) -> Self {
+        Self {
+            name,
+            rtype: rtype.to_u16(),
+            rclass,
+            ttl: 0,
+            rdata: RecordData::Unknown { rtype: rtype.to_u16(), data: vec![] },
+        }
+    }
+
+    /// Indique si l'enregistrement n'a pas de données (RDLENGTH nul)
+    pub fn is_empty(&self) -> bool {
+        matches!(&self.rdata, RecordData::Unknown { data, .. } if data.is_empty())
+    }
+
+    /// Écrit l'enregistrement à la fin de `bytes`, en compressant le nom
+    /// avec les suffixes déjà présents dans le message
+    pub fn write_to(&self, bytes: &mut Vec<u8>, compression: &mut NameCompression) {
+        encode_name(bytes, &self.name, compression);
+        bytes.extend_from_slice(&self.rtype.to_be_bytes());
+        bytes.extend_from_slice(&self.rclass.to_be_bytes());
+        bytes.extend_from_slice(&self.ttl.to_be_bytes());
+        
+        // rdlength n'est connu qu'après l'encodage des données
+        let length_position = bytes.len();
+        bytes.extend_from_slice(&[0, 0]);
+        self.rdata.write_to(bytes, compression);
+        let rdlength = (bytes.len() - length_position - 2) as u16;
+        bytes[length_position..length_position + 2].copy_from_slice(&rdlength.to_be_bytes());
     }
 
     pub fn from_bytes(bytes: &[u8], offset: &mut usize) -> Result<Self, Box<dyn std::error::Error>> {
-        let name = Self::decode_name(bytes, offset)?;
+        let name = decode_name(bytes, offset)?;
+        
+        // type (2) + classe (2) + ttl (4) + rdlength (2)
+        let fields: [u8; 10] = read_field(bytes, offset, bytes.len()).map_err(|_| "Enregistrement trop court")?;
+        let rtype = u16::from_be_bytes([fields[0], fields[1]]);
+        let rclass = u16::from_be_bytes([fields[2], fields[3]]);
+        let ttl = u32::from_be_bytes([fields[4], fields[5], fields[6], fields[7]]);
+        let rdlength = u16::from_be_bytes([fields[8], fields[9]]) as usize;
         
-        if *offset + 4 > bytes.len() {
-            return Err("Question trop courte".into());
+        if *offset + rdlength > bytes.len() {
+            return Err("Données de l'enregistrement tronquées".into());
         }
         
-        let qtype = u16::from_be_bytes([bytes[*offset], bytes[*offset + 1]]);
-        let qclass = u16::from_be_bytes([bytes[*offset + 2], bytes[*offset + 3]]);
-        *offset += 4;
+        // Les mises à jour dynamiques utilisent des enregistrements vides
+        // de classe ANY ou NONE, quel que soit leur type
+        let rdata = if rdlength == 0 && (rclass == CLASS_ANY || rclass == CLASS_NONE) {
+            RecordData::Unknown { rtype, data: vec![] }
+        } else {
+            RecordData::from_bytes(RecordType::from(rtype), bytes, *offset, rdlength)?
+        };
+        *offset += rdlength;
         
-        Ok(Self { name, qtype, qclass })
+        Ok(Self { name, rtype, rclass, ttl, rdata })
     }
+}
 
-    fn decode_name(bytes: &[u8], offset: &mut usize) -> Result<String, Box<dyn std::error::Error>> {
-        let mut name_parts = Vec::new();
+impl RecordType {
+    pub fn to_u16(self) -> u16 {
+        match self {
+            RecordType::A => 1,
+            RecordType::NS => 2,
+            RecordType::CNAME => 5,
+            RecordType::SOA => 6,
+            RecordType::PTR => 12,
+            RecordType::MX => 15,
+            RecordType::TXT => 16,
+            RecordType::AAAA => 28,
+            RecordType::SRV => 33,
+            RecordType::OPT => 41,
+            RecordType::DS => 43,
+            RecordType::RRSIG => 46,
+            RecordType::NSEC => 47,
+            RecordType::DNSKEY => 48,
+            RecordType::IXFR => 251,
+            RecordType::AXFR => 252,
+            RecordType::ANY => 255,
+            RecordType::Other(value) => value,
+        }
+    }
+}
+
+impl From<u16> for RecordType {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => RecordType::A,
+            2 => RecordType::NS,
+            5 => RecordType::CNAME,
+            6 => RecordType::SOA,
+            12 => RecordType::PTR,
+            15 => RecordType::MX,
+            16 => RecordType::TXT,
+            28 => RecordType::AAAA,
+            33 => RecordType::SRV,
+            41 => RecordType::OPT,
+            43 => RecordType::DS,
+            46 => RecordType::RRSIG,
+            47 => RecordType::NSEC,
+            48 => RecordType::DNSKEY,
+            251 => RecordType::IXFR,
+            252 => RecordType::AXFR,
+            255 => RecordType::ANY,
+            other => RecordType::Other(other),
+        }
+    }
+}
+
+impl std::str::FromStr for RecordType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.to_ascii_uppercase();
+        match upper.as_str() {
+            "A" => Ok(RecordType::A),
+            "NS" => Ok(RecordType::NS),
+            "CNAME" => Ok(RecordType::CNAME),
+            "SOA" => Ok(RecordType::SOA),
+            "PTR" => Ok(RecordType::PTR),
+            "MX" => Ok(RecordType::MX),
+            "TXT" => Ok(RecordType::TXT),
+            "AAAA" => Ok(RecordType::AAAA),
+            "SRV" => Ok(RecordType::SRV),
+            "DS" => Ok(RecordType::DS),
+            "RRSIG" => Ok(RecordType::RRSIG),
+            "NSEC" => Ok(RecordType::NSEC),
+            "DNSKEY" => Ok(RecordType::DNSKEY),
+            "IXFR" => Ok(RecordType::IXFR),
+            "AXFR" => Ok(RecordType::AXFR),
+            "ANY" => Ok(RecordType::ANY),
+            // Notation générique de la RFC 3597 (TYPE65 par exemple)
+            _ => upper
+                .strip_prefix("TYPE")
+                .and_then(|number| number.parse::<u16>().ok())
+                .map(RecordType::from)
+                .ok_or_else(|| format!("Type d'enregistrement inconnu: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for RecordType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordType::Other(value) => write!(f, "TYPE{}", value),
+            other => write!(f, "{:?}", other),
+        }
+    }
+}
+
+impl RecordData {
+    pub fn record_type(&self) -> RecordType {
+        match self {
+            RecordData::A(_) => RecordType::A,
+            RecordData::AAAA(_) => RecordType::AAAA,
+            RecordData::CNAME(_) => RecordType::CNAME,
+            RecordData::NS(_) => RecordType::NS,
+            RecordData::PTR(_) => RecordType::PTR,
+            RecordData::MX { .. } => RecordType::MX,
+            RecordData::TXT(_) => RecordType::TXT,
+            RecordData::SOA { .. } => RecordType::SOA,
+            RecordData::SRV { .. } => RecordType::SRV,
+            RecordData::DS { .. } => RecordType::DS,
+            RecordData::RRSIG { .. } => RecordType::RRSIG,
+            RecordData::NSEC { .. } => RecordType::NSEC,
+            RecordData::DNSKEY { .. } => RecordType::DNSKEY,
+            RecordData::Unknown { rtype, .. } => RecordType::from(*rtype),
+        }
+    }
+
+    /// Encode les données à la fin de `bytes`.
+    ///
+    /// Les noms contenus dans les types de la RFC 1035 peuvent être
+    /// compressés ; ceux de SRV, RRSIG et NSEC ne le sont jamais (RFC 2782,
+    /// RFC 4034).
+    pub fn write_to(&self, bytes: &mut Vec<u8>, compression: &mut NameCompression) {
+        match self {
+            RecordData::A(ip) => bytes.extend_from_slice(&ip.octets()),
+            RecordData::AAAA(ip) => bytes.extend_from_slice(&ip.octets()),
+            RecordData::CNAME(name) | RecordData::NS(name) | RecordData::PTR(name) => {
+                encode_name(bytes, name, compression);
+            }
+            RecordData::MX { preference, exchange } => {
+                bytes.extend_from_slice(&preference.to_be_bytes());
+                encode_name(bytes, exchange, compression);
+            }
+            RecordData::TXT(strings) => {
+                // Une chaîne de caractères fait au plus 255 octets (RFC 1035
+                // §3.3) : une chaîne plus longue part en plusieurs morceaux
+                for string in strings {
+                    if string.is_empty() {
+                        bytes.push(0);
+                    }
+                    for chunk in string.chunks(255) {
+                        bytes.push(chunk.len() as u8);
+                        bytes.extend_from_slice(chunk);
+                    }
+                }
+            }
+            RecordData::SOA { mname, rname, serial, refresh, retry, expire, minimum } => {
+                encode_name(bytes, mname, compression);
+                encode_name(bytes, rname, compression);
+                for value in [serial, refresh, retry, expire, minimum] {
+                    bytes.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+            RecordData::SRV { priority, weight, port, target } => {
+                bytes.extend_from_slice(&priority.to_be_bytes());
+                bytes.extend_from_slice(&weight.to_be_bytes());
+                bytes.extend_from_slice(&port.to_be_bytes());
+                encode_name(bytes, target, &mut NameCompression::new());
+            }
+            RecordData::DS { key_tag, algorithm, digest_type, digest } => {
+                bytes.extend_from_slice(&key_tag.to_be_bytes());
+                bytes.extend_from_slice(&[*algorithm, *digest_type]);
+                bytes.extend_from_slice(digest);
+            }
+            RecordData::RRSIG { signature, .. } => {
+                self.write_rrsig_fields(bytes, false);
+                bytes.extend_from_slice(signature);
+            }
+            RecordData::NSEC { next, types } => {
+                encode_name(bytes, next, &mut NameCompression::new());
+                write_type_bitmap(bytes, types);
+            }
+            RecordData::DNSKEY { flags, protocol, algorithm, public_key } => {
+                bytes.extend_from_slice(&flags.to_be_bytes());
+                bytes.extend_from_slice(&[*protocol, *algorithm]);
+                bytes.extend_from_slice(public_key);
+            }
+            RecordData::Unknown { data, .. } => bytes.extend_from_slice(data),
+        }
+    }
+
+    /// Forme canonique des données pour DNSSEC (RFC 4034 §6.2) : noms jamais
+    /// compressés, et en minuscules pour les types qui le demandent
+    pub fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        match self {
+            RecordData::CNAME(name) | RecordData::NS(name) | RecordData::PTR(name) => {
+                bytes.extend(canonical_name(name));
+            }
+            RecordData::MX { preference, exchange } => {
+                bytes.extend_from_slice(&preference.to_be_bytes());
+                bytes.extend(canonical_name(exchange));
+            }
+            RecordData::SOA { mname, rname, serial, refresh, retry, expire, minimum } => {
+                bytes.extend(canonical_name(mname));
+                bytes.extend(canonical_name(rname));
+                for value in [serial, refresh, retry, expire, minimum] {
+                    bytes.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+            RecordData::SRV { priority, weight, port, target } => {
+                bytes.extend_from_slice(&priority.to_be_bytes());
+                bytes.extend_from_slice(&weight.to_be_bytes());
+                bytes.extend_from_slice(&port.to_be_bytes());
+                bytes.extend(canonical_name(target));
+            }
+            RecordData::RRSIG { signature, .. } => {
+                self.write_rrsig_fields(&mut bytes, true);
+                bytes.extend_from_slice(signature);
+            }
+            // Le nom suivant d'un NSEC garde sa casse (RFC 6840 §5.1)
+            other => other.write_to(&mut bytes, &mut NameCompression::new()),
+        }
+        bytes
+    }
+
+    /// Champs d'un RRSIG qui précèdent la signature, c'est-à-dire ceux qui
+    /// sont eux-mêmes signés (RFC 4034 §3.1.8.1)
+    pub fn write_rrsig_fields(&self, bytes: &mut Vec<u8>, canonical: bool) {
+        if let RecordData::RRSIG { type_covered, algorithm, labels, original_ttl, expiration, inception, key_tag, signer, .. } = self {
+            bytes.extend_from_slice(&type_covered.to_be_bytes());
+            bytes.extend_from_slice(&[*algorithm, *labels]);
+            for value in [original_ttl, expiration, inception] {
+                bytes.extend_from_slice(&value.to_be_bytes());
+            }
+            bytes.extend_from_slice(&key_tag.to_be_bytes());
+            if canonical {
+                bytes.extend(canonical_name(signer));
+            } else {
+                encode_name(bytes, signer, &mut NameCompression::new());
+            }
+        }
+    }
+
+    /// Décode les `rdlength` octets situés à `start` dans le message complet
+    /// (nécessaire pour suivre les pointeurs de compression)
+    pub fn from_bytes(rtype: RecordType, bytes: &[u8], start: usize, rdlength: usize) -> Result<Self, Box<dyn std::error::Error>> {
+        let end = start + rdlength;
+        let rdata = bytes.get(start..end).ok_or("Données de l'enregistrement tronquées")?;
+        let mut offset = start;
         
-        while *offset < bytes.len() {
-            let length = bytes[*offset] as usize;
-            *offset += 1;
-            
-            if length == 0 {
-                break;
+        let data = match rtype {
+            RecordType::A => {
+                let octets: [u8; 4] = rdata.try_into().map_err(|_| "Enregistrement A invalide")?;
+                offset = end;
+                RecordData::A(Ipv4Addr::from(octets))
+            }
+            RecordType::AAAA => {
+                let octets: [u8; 16] = rdata.try_into().map_err(|_| "Enregistrement AAAA invalide")?;
+                offset = end;
+                RecordData::AAAA(Ipv6Addr::from(octets))
+            }
+            RecordType::CNAME => RecordData::CNAME(decode_name(bytes, &mut offset)?),
+            RecordType::NS => RecordData::NS(decode_name(bytes, &mut offset)?),
+            RecordType::PTR => RecordData::PTR(decode_name(bytes, &mut offset)?),
+            RecordType::MX => {
+                let preference = read_u16(bytes, &mut offset, end)?;
+                let exchange = decode_name(bytes, &mut offset)?;
+                RecordData::MX { preference, exchange }
+            }
+            RecordType::TXT => {
+                let mut strings = Vec::new();
+                while offset < end {
+                    let [length] = read_field(bytes, &mut offset, end)?;
+                    let string = bytes.get(offset..end).and_then(|rest| rest.get(..length as usize));
+                    strings.push(string.ok_or("Enregistrement TXT invalide")?.to_vec());
+                    offset += length as usize;
+                }
+                RecordData::TXT(strings)
             }
-            
-            if *offset + length > bytes.len() {
-                return Err("Nom de domaine invalide".into());
+            RecordType::SOA => {
+                let mname = decode_name(bytes, &mut offset)?;
+                let rname = decode_name(bytes, &mut offset)?;
+                RecordData::SOA {
+                    mname,
+                    rname,
+                    serial: read_u32(bytes, &mut offset, end)?,
+                    refresh: read_u32(bytes, &mut offset, end)?,
+                    retry: read_u32(bytes, &mut offset, end)?,
+                    expire: read_u32(bytes, &mut offset, end)?,
+                    minimum: read_u32(bytes, &mut offset, end)?,
+                }
             }
-            
-            let part = String::from_utf8(bytes[*offset..*offset + length].to_vec())?;
-            name_parts.push(part);
-            *offset += length;
+            RecordType::SRV => {
+                let priority = read_u16(bytes, &mut offset, end)?;
+                let weight = read_u16(bytes, &mut offset, end)?;
+                let port = read_u16(bytes, &mut offset, end)?;
+                let target = decode_name(bytes, &mut offset)?;
+                RecordData::SRV { priority, weight, port, target }
+            }
+            RecordType::DS => {
+                let key_tag = read_u16(bytes, &mut offset, end)?;
+                let [algorithm, digest_type] = read_field(bytes, &mut offset, end)?;
+                let digest = bytes[offset..end].to_vec();
+                offset = end;
+                RecordData::DS { key_tag, algorithm, digest_type, digest }
+            }
+            RecordType::RRSIG => {
+                let type_covered = read_u16(bytes, &mut offset, end)?;
+                let [algorithm, labels] = read_field(bytes, &mut offset, end)?;
+                let original_ttl = read_u32(bytes, &mut offset, end)?;
+                let expiration = read_u32(bytes, &mut offset, end)?;
+                let inception = read_u32(bytes, &mut offset, end)?;
+                let key_tag = read_u16(bytes, &mut offset, end)?;
+                let signer = decode_name(bytes, &mut offset)?;
+                let signature = bytes.get(offset..end).ok_or("Enregistrement RRSIG invalide")?.to_vec();
+                offset = end;
+                RecordData::RRSIG { type_covered, algorithm, labels, original_ttl, expiration, inception, key_tag, signer, signature }
+            }
+            RecordType::NSEC => {
+                let next = decode_name(bytes, &mut offset)?;
+                let types = read_type_bitmap(bytes.get(offset..end).ok_or("Enregistrement NSEC invalide")?)?;
+                offset = end;
+                RecordData::NSEC { next, types }
+            }
+            RecordType::DNSKEY => {
+                let flags = read_u16(bytes, &mut offset, end)?;
+                let [protocol, algorithm] = read_field(bytes, &mut offset, end)?;
+                let public_key = bytes[offset..end].to_vec();
+                offset = end;
+                RecordData::DNSKEY { flags, protocol, algorithm, public_key }
+            }
+            _ => {
+                offset = end;
+                RecordData::Unknown { rtype: rtype.to_u16(), data: rdata.to_vec() }
+            }
+        };
+        
+        if offset != end {
+            return Err(format!("Longueur invalide pour l'enregistrement {}", rtype).into());
         }
         
-        Ok(name_parts.join("."))
+        Ok(data)
     }
 }
 
-impl DnsAnswer {
-    pub fn new(name: String, ip: [u8; 4]) -> Self {
+impl fmt::Display for RecordData {
+    /// Format de présentation des fichiers de zone
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordData::A(ip) => write!(f, "{}", ip),
+            RecordData::AAAA(ip) => write!(f, "{}", ip),
+            RecordData::CNAME(name) | RecordData::NS(name) | RecordData::PTR(name) => {
+                write!(f, "{}.", name)
+            }
+            RecordData::MX { preference, exchange } => write!(f, "{} {}.", preference, exchange),
+            RecordData::TXT(strings) => {
+                // Les octets hors ASCII imprimable sont échappés en `\DDD`,
+                // que le lecteur de fichiers de zone sait relire
+                let quoted: Vec<String> = strings
+                    .iter()
+                    .map(|string| {
+                        let mut text = String::new();
+                        for &byte in string {
+                            match byte {
+                                b'"' | b'\\' => text.extend(['\\', byte as char]),
+                                0x20..=0x7E => text.push(byte as char),
+                                _ => text.push_str(&format!("\\{:03}", byte)),
+                            }
+                        }
+                        format!("\"{}\"", text)
+                    })
+                    .collect();
+                write!(f, "{}", quoted.join(" "))
+            }
+            RecordData::SOA { mname, rname, serial, refresh, retry, expire, minimum } => write!(
+                f,
+                "{}. {}. {} {} {} {} {}",
+                mname, rname, serial, refresh, retry, expire, minimum
+            ),
+            RecordData::SRV { priority, weight, port, target } => {
+                write!(f, "{} {} {} {}.", priority, weight, port, target)
+            }
+            RecordData::DS { key_tag, algorithm, digest_type, digest } => {
+                let hex: String = digest.iter().map(|byte| format!("{:02X}", byte)).collect();
+                write!(f, "{} {} {} {}", key_tag, algorithm, digest_type, hex)
+            }
+            RecordData::RRSIG { type_covered, algorithm, labels, original_ttl, expiration, inception, key_tag, signer, signature } => {
+                write!(
+                    f,
+                    "{} {} {} {} {} {} {} {}. {}",
+                    RecordType::from(*type_covered),
+                    algorithm,
+                    labels,
+                    original_ttl,
+                    format_timestamp(*expiration),
+                    format_timestamp(*inception),
+                    key_tag,
+                    signer,
+                    base64_encode(signature)
+                )
+            }
+            RecordData::NSEC { next, types } => {
+                write!(f, "{}.", next)?;
+                for rtype in types {
+                    write!(f, " {}", RecordType::from(*rtype))?;
+                }
+                Ok(())
+            }
+            RecordData::DNSKEY { flags, protocol, algorithm, public_key } => {
+                write!(f, "{} {} {} {}", flags, protocol, algorithm, base64_encode(public_key))
+            }
+            RecordData::Unknown { data, .. } => {
+                // Format générique de la RFC 3597
+                let hex: String = data.iter().map(|byte| format!("{:02x}", byte)).collect();
+                write!(f, "\\# {} {}", data.len(), hex)
+            }
+        }
+    }
+}
+
+/// Lit `N` octets à `offset` sans dépasser `end` ni la fin de `bytes`
+fn read_field<const N: usize>(bytes: &[u8], offset: &mut usize, end: usize) -> Result<[u8; N], Box<dyn std::error::Error>> {
+    let field = bytes
+        .get(*offset..end)
+        .and_then(|rest| rest.first_chunk::<N>())
+        .ok_or("Données de l'enregistrement tronquées")?;
+    *offset += N;
+    Ok(*field)
+}
+
+/// Bitmap des types d'un NSEC : par fenêtre de 256 types, le numéro de
+/// fenêtre, la longueur puis les bits des types présents (RFC 4034 §4.1.2)
+fn write_type_bitmap(bytes: &mut Vec<u8>, types: &[u16]) {
+    let mut windows: Vec<(u8, [u8; 32])> = Vec::new();
+    for &rtype in types {
+        let window = (rtype >> 8) as u8;
+        if windows.last().is_none_or(|(last, _)| *last != window) {
+            windows.push((window, [0; 32]));
+        }
+        if let Some((_, bits)) = windows.last_mut() {
+            let low = (rtype & 0xFF) as usize;
+            bits[low / 8] |= 0x80 >> (low % 8);
+        }
+    }
+    for (window, bits) in windows {
+        let length = bits.iter().rposition(|&byte| byte != 0).map_or(0, |last| last + 1);
+        bytes.extend_from_slice(&[window, length as u8]);
+        bytes.extend_from_slice(&bits[..length]);
+    }
+}
+
+fn read_type_bitmap(bitmap: &[u8]) -> Result<Vec<u16>, Box<dyn std::error::Error>> {
+    let mut types = Vec::new();
+    let mut offset = 0;
+    let mut previous_window = None;
+    while offset < bitmap.len() {
+        let [window, length] = read_field(bitmap, &mut offset, bitmap.len())?;
+        let bits = bitmap.get(offset..offset + length as usize).ok_or("Bitmap NSEC tronqué")?;
+        // Fenêtres croissantes et non vides, de 32 octets au plus
+        if length == 0 || length > 32 || previous_window.is_some_and(|previous| previous >= window) {
+            return Err("Bitmap NSEC invalide".into());
+        }
+        previous_window = Some(window);
+        for (index, byte) in bits.iter().enumerate() {
+            for bit in 0..8 {
+                if byte & (0x80 >> bit) != 0 {
+                    types.push((window as u16) << 8 | (index * 8 + bit) as u16);
+                }
+            }
+        }
+        offset += length as usize;
+    }
+    Ok(types)
+}
+
+fn read_u16(bytes: &[u8], offset: &mut usize, end: usize) -> Result<u16, Box<dyn std::error::Error>> {
+    read_field(bytes, offset, end).map(u16::from_be_bytes)
+}
+
+fn read_u32(bytes: &[u8], offset: &mut usize, end: usize) -> Result<u32, Box<dyn std::error::Error>> {
+    read_field(bytes, offset, end).map(u32::from_be_bytes)
+}
+
+impl Edns {
+    pub fn new(payload_size: u16) -> Self {
         Self {
-            name,
-            rtype: 1,    // A record
-            rclass: 1,   // IN class
-            ttl: 300,    // 5 minutes
-            rdlength: 4, // 4 bytes pour IPv4
-            rdata: ip.to_vec(),
+            payload_size,
+            extended_rcode: 0,
+            version: 0,
+            dnssec_ok: false,
+            options: vec![],
         }
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
+    /// Lit un pseudo-enregistrement OPT : la classe porte la taille UDP et le
+    /// TTL le code étendu, la version et le bit DO
+    pub fn from_record(record: &DnsAnswer) -> Result<Self, Box<dyn std::error::Error>> {
+        if !record.name.is_empty() {
+            return Err("OPT avec un nom différent de la racine".into());
+        }
         
-        // Encoder le nom (simplifié)
-        for part in self.name.split('.') {
-            bytes.push(part.len() as u8);
-            bytes.extend_from_slice(part.as_bytes());
+        let data = match &record.rdata {
+            RecordData::Unknown { data, .. } => data,
+            _ => return Err("OPT invalide".into()),
+        };
+        
+        let mut options = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            let code = read_u16(data, &mut offset, data.len())?;
+            let length = read_u16(data, &mut offset, data.len())? as usize;
+            if offset + length > data.len() {
+                return Err("Option EDNS tronquée".into());
+            }
+            options.push(EdnsOption { code, data: data[offset..offset + length].to_vec() });
+            offset += length;
         }
-        bytes.push(0);
         
-        bytes.extend_from_slice(&self.rtype.to_be_bytes());
-        bytes.extend_from_slice(&self.rclass.to_be_bytes());
-        bytes.extend_from_slice(&self.ttl.to_be_bytes());
-        bytes.extend_from_slice(&self.rdlength.to_be_bytes());
-        bytes.extend_from_slice(&self.rdata);
+        Ok(Self {
+            payload_size: record.rclass,
+            extended_rcode: (record.ttl >> 24) as u8,
+            version: (record.ttl >> 16) as u8,
+            dnssec_ok: record.ttl & 0x8000 != 0,
+            options,
+        })
+    }
+
+    pub fn to_record(&self) -> DnsAnswer {
+        let mut data = Vec::new();
+        for option in &self.options {
+            data.extend_from_slice(&option.code.to_be_bytes());
+            data.extend_from_slice(&(option.data.len() as u16).to_be_bytes());
+            data.extend_from_slice(&option.data);
+        }
         
-        bytes
+        DnsAnswer {
+            name: String::new(),
+            rtype: RecordType::OPT.to_u16(),
+            rclass: self.payload_size,
+            ttl: (self.extended_rcode as u32) << 24
+                | (self.version as u32) << 16
+                | (self.dnssec_ok as u32) << 15,
+            rdata: RecordData::Unknown { rtype: RecordType::OPT.to_u16(), data },
+        }
+    }
+}
+
+impl fmt::Display for Edns {
+    /// Pseudo-section OPT à la manière de dig
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "EDNS: version: {}, flags:{}; udp: {}",
+            self.version,
+            if self.dnssec_ok { " do" } else { "" },
+            self.payload_size
+        )?;
+        for option in &self.options {
+            let hex: String = option.data.iter().map(|byte| format!("{:02x}", byte)).collect();
+            write!(f, "\n; OPTION {}: {}", option.code, hex)?;
+        }
+        Ok(())
     }
 }
 
 impl DnsMessage {
-    pub fn new_query(id: u16, domain: String) -> Self {
+    /// Message vide (ID 0, aucun bit) à compléter ; voir `DnsMessageBuilder`
+    pub fn builder() -> DnsMessageBuilder {
+        DnsMessageBuilder::default()
+    }
+
+    pub fn new_typed_query(id: u16, domain: String, qtype: RecordType) -> Self {
         let mut header = DnsHeader::new(id);
         header.question_count = 1;
         
-        let question = DnsQuestion::new(domain);
+        let question = DnsQuestion::with_type(domain, qtype);
         
         Self {
             header,
             questions: vec![question],
             answers: vec![],
+            authorities: vec![],
+            additionals: vec![],
+            edns: None,
         }
     }
 
+    /// Mise à jour dynamique de `zone` (RFC 2136) : la section zone reprend
+    /// le format de la question, les prérequis et les mises à jour sont à
+    /// ajouter dans `answers` et `authorities`
+    pub fn new_update(id: u16, zone: String) -> Self {
+        let mut message = Self::new_typed_query(id, zone, RecordType::SOA);
+        message.header.flags.opcode = Opcode::Update;
+        message.header.flags.rd = false;
+        message
+    }
+
+    /// Annonce d'un changement de `zone` aux serveurs secondaires (RFC 1996)
+    pub fn new_notify(id: u16, zone: String) -> Self {
+        let mut message = Self::new_typed_query(id, zone, RecordType::SOA);
+        message.header.flags.opcode = Opcode::Notify;
+        message.header.flags.aa = true;
+        message.header.flags.rd = false;
+        message
+    }
+
+    /// Prépare la réponse à `query` : même ID, même question, et les bits
+    /// OPCODE/RD/CD recopiés de la requête
     pub fn new_response(query: &DnsMessage, answers: Vec<DnsAnswer>) -> Self {
-        let mut header = query.header.clone();
-        header.flags = 0x8180; // Response, authoritative
-        header.answer_count = answers.len() as u16;
+        let mut response = Self::new_error(&query.header, ResponseCode::NoError);
+        response.questions = query.questions.clone();
+        response.answers = answers;
+        response
+    }
+
+    /// Version tronquée d'une réponse trop grande pour UDP : en-tête et
+    /// question seulement, avec le bit TC pour que le client passe en TCP
+    pub fn truncated(&self) -> Self {
+        let mut header = self.header.clone();
+        header.flags.tc = true;
         
         Self {
             header,
-            questions: query.questions.clone(),
-            answers,
+            questions: self.questions.clone(),
+            answers: vec![],
+            authorities: vec![],
+            additionals: vec![],
+            edns: self.edns.clone(),
+        }
+    }
+
+    /// Réponse sans question, pour une requête qui n'a pas pu être analysée
+    /// au-delà de l'en-tête
+    pub fn new_error(query_header: &DnsHeader, rcode: ResponseCode) -> Self {
+        let mut header = DnsHeader::new(query_header.id);
+        header.flags = DnsFlags {
+            qr: true,
+            opcode: query_header.flags.opcode,
+            rd: query_header.flags.rd,
+            cd: query_header.flags.cd,
+            ..DnsFlags::default()
+        };
+        
+        let mut response = Self {
+            header,
+            questions: vec![],
+            answers: vec![],
+            authorities: vec![],
+            additionals: vec![],
+            edns: None,
+        };
+        response.set_rcode(rcode);
+        response
+    }
+
+    /// Code de retour complet, étendu par l'OPT s'il est présent
+    pub fn rcode(&self) -> ResponseCode {
+        let extended = self.edns.as_ref().map_or(0, |edns| edns.extended_rcode as u16);
+        ResponseCode::from(extended << 4 | self.header.flags.rcode.to_u16())
+    }
+
+    /// Positionne le code de retour ; un code sur plus de 4 bits ajoute un
+    /// OPT au message s'il n'en a pas
+    pub fn set_rcode(&mut self, rcode: ResponseCode) {
+        let value = rcode.to_u16();
+        self.header.flags.rcode = ResponseCode::from(value & 0x0F);
+        
+        if value > 0x0F {
+            self.edns.get_or_insert_with(|| Edns::new(512)).extended_rcode = (value >> 4) as u8;
+        } else if let Some(edns) = &mut self.edns {
+            edns.extended_rcode = 0;
         }
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
+        // Les noms déjà écrits sont partagés par toutes les sections
+        let mut compression = NameCompression::new();
         
-        bytes.extend_from_slice(&self.header.to_bytes());
+        // Les compteurs de l'en-tête suivent toujours le contenu des sections
+        let mut header = self.header.clone();
+        header.question_count = self.questions.len() as u16;
+        header.answer_count = self.answers.len() as u16;
+        header.authority_count = self.authorities.len() as u16;
+        header.additional_count = (self.additionals.len() + self.edns.is_some() as usize) as u16;
+        bytes.extend_from_slice(&header.to_bytes());
         
         for question in &self.questions {
-            bytes.extend_from_slice(&question.to_bytes());
+            question.write_to(&mut bytes, &mut compression);
         }
         
-        for answer in &self.answers {
-            bytes.extend_from_slice(&answer.to_bytes());
+        let opt = self.edns.as_ref().map(Edns::to_record);
+        for record in self.answers.iter().chain(&self.authorities).chain(&self.additionals).chain(&opt) {
+            record.write_to(&mut bytes, &mut compression);
         }
         
         bytes
@@ -218,13 +1238,731 @@ impl DnsMessage {
             questions.push(question);
         }
         
-        // Pour simplifier, on ne parse pas les réponses dans ce TP basique
-        let answers = Vec::new();
+        let answers = Self::parse_records(bytes, &mut offset, header.answer_count)?;
+        let authorities = Self::parse_records(bytes, &mut offset, header.authority_count)?;
+        let mut additionals = Self::parse_records(bytes, &mut offset, header.additional_count)?;
+        
+        // Le pseudo-enregistrement OPT est retiré de la section additionnelle
+        let mut opts = additionals.iter().filter(|record| record.rtype == RecordType::OPT.to_u16());
+        let edns = match (opts.next(), opts.next()) {
+            (None, _) => None,
+            (Some(opt), None) => Some(Edns::from_record(opt)?),
+            (Some(_), Some(_)) => return Err("Plusieurs enregistrements OPT".into()),
+        };
+        additionals.retain(|record| record.rtype != RecordType::OPT.to_u16());
         
         Ok(Self {
             header,
             questions,
             answers,
+            authorities,
+            additionals,
+            edns,
+        })
+    }
+
+    fn parse_records(bytes: &[u8], offset: &mut usize, count: u16) -> Result<Vec<DnsAnswer>, Box<dyn std::error::Error>> {
+        // Le compteur vient du message : on ne réserve pas plus que ce que
+        // les octets reçus peuvent contenir
+        let mut records = Vec::with_capacity((count as usize).min(bytes.len() / MIN_RECORD_LENGTH));
+        for _ in 0..count {
+            records.push(DnsAnswer::from_bytes(bytes, offset)?);
+        }
+        Ok(records)
+    }
+}
+
+impl fmt::Display for DnsMessage {
+    /// Contenu complet du message, à la manière de dig
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = self.header.flags;
+        writeln!(f, ";; ->>HEADER<<- opcode: {}, status: {}, id: {}", flags.opcode, self.rcode(), self.header.id)?;
+
+        // Les sections d'une mise à jour portent d'autres noms (RFC 2136 §2)
+        let update = flags.opcode == Opcode::Update;
+        let names = if update {
+            ["ZONE", "PREREQUISITE", "UPDATE"]
+        } else {
+            ["QUERY", "ANSWER", "AUTHORITY"]
+        };
+        write!(
+            f,
+            ";; flags: {}; {}: {}, {}: {}, {}: {}, ADDITIONAL: {}",
+            flags,
+            names[0], self.questions.len(),
+            names[1], self.answers.len(),
+            names[2], self.authorities.len(),
+            self.additionals.len() + self.edns.is_some() as usize
+        )?;
+
+        if let Some(edns) = &self.edns {
+            write!(f, "\n\n;; OPT PSEUDOSECTION:\n; {}", edns)?;
+        }
+
+        if !self.questions.is_empty() {
+            write!(f, "\n\n;; {} SECTION:", if update { "ZONE" } else { "QUESTION" })?;
+            for question in &self.questions {
+                write!(f, "\n;{}", question)?;
+            }
+        }
+
+        let sections = [
+            (if update { "PREREQUISITE" } else { "ANSWER" }, &self.answers),
+            (if update { "UPDATE" } else { "AUTHORITY" }, &self.authorities),
+            ("ADDITIONAL", &self.additionals),
+        ];
+        for (name, records) in sections {
+            if !records.is_empty() {
+                write!(f, "\n\n;; {} SECTION:", name)?;
+                for record in records {
+                    write!(f, "\n{}", record)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Construction d'un message champ par champ :
+/// `DnsMessage::builder().id(1).question("example.com", RecordType::A).rd(true).build()`.
+///
+/// Les noms sont vérifiés par `build`, qui renvoie la première erreur
+/// rencontrée ; les compteurs de l'en-tête suivent le contenu des sections.
+#[derive(Debug, Clone)]
+pub struct DnsMessageBuilder {
+    message: DnsMessage,
+    rcode: ResponseCode,
+    error: Option<String>,
+}
+
+impl Default for DnsMessageBuilder {
+    fn default() -> Self {
+        let header = DnsHeader { flags: DnsFlags::default(), ..DnsHeader::new(0) };
+        let message = DnsMessage { header, questions: vec![], answers: vec![], authorities: vec![], additionals: vec![], edns: None };
+        Self { message, rcode: ResponseCode::NoError, error: None }
+    }
+}
+
+impl DnsMessageBuilder {
+    pub fn id(mut self, id: u16) -> Self {
+        self.message.header.id = id;
+        self
+    }
+
+    pub fn opcode(mut self, opcode: Opcode) -> Self {
+        self.message.header.flags.opcode = opcode;
+        self
+    }
+
+    /// Code de retour, étendu par un OPT s'il dépasse 4 bits
+    pub fn rcode(mut self, rcode: ResponseCode) -> Self {
+        self.rcode = rcode;
+        self
+    }
+
+    pub fn qr(mut self, qr: bool) -> Self {
+        self.message.header.flags.qr = qr;
+        self
+    }
+
+    pub fn aa(mut self, aa: bool) -> Self {
+        self.message.header.flags.aa = aa;
+        self
+    }
+
+    pub fn tc(mut self, tc: bool) -> Self {
+        self.message.header.flags.tc = tc;
+        self
+    }
+
+    pub fn rd(mut self, rd: bool) -> Self {
+        self.message.header.flags.rd = rd;
+        self
+    }
+
+    pub fn ra(mut self, ra: bool) -> Self {
+        self.message.header.flags.ra = ra;
+        self
+    }
+
+    pub fn ad(mut self, ad: bool) -> Self {
+        self.message.header.flags.ad = ad;
+        self
+    }
+
+    pub fn cd(mut self, cd: bool) -> Self {
+        self.message.header.flags.cd = cd;
+        self
+    }
+
+    /// Question de classe IN
+    pub fn question(self, name: &str, qtype: RecordType) -> Self {
+        self.question_class(name, qtype, CLASS_IN)
+    }
+
+    pub fn question_class(mut self, name: &str, qtype: RecordType, qclass: u16) -> Self {
+        let name = self.checked_name(name);
+        self.message.questions.push(DnsQuestion { name, qtype: qtype.to_u16(), qclass });
+        self
+    }
+
+    pub fn answer(mut self, record: DnsAnswer) -> Self {
+        let record = self.checked_record(record);
+        self.message.answers.push(record);
+        self
+    }
+
+    pub fn authority(mut self, record: DnsAnswer) -> Self {
+        let record = self.checked_record(record);
+        self.message.authorities.push(record);
+        self
+    }
+
+    /// Enregistrement de la section additionnelle ; l'OPT se règle avec
+    /// `edns` et `dnssec_ok`
+    pub fn additional(mut self, record: DnsAnswer) -> Self {
+        if record.rtype == RecordType::OPT.to_u16() {
+            self.error.get_or_insert_with(|| "OPT dans la section additionnelle: utiliser edns()".to_string());
+        }
+        let record = self.checked_record(record);
+        self.message.additionals.push(record);
+        self
+    }
+
+    /// Ajoute un OPT annonçant `payload_size` octets en UDP
+    pub fn edns(mut self, payload_size: u16) -> Self {
+        self.message.edns.get_or_insert_with(|| Edns::new(payload_size)).payload_size = payload_size;
+        self
+    }
+
+    /// Bit DO de l'OPT, ajouté avec la taille par défaut s'il manque
+    pub fn dnssec_ok(mut self, dnssec_ok: bool) -> Self {
+        self.message.edns.get_or_insert_with(|| Edns::new(DEFAULT_EDNS_PAYLOAD)).dnssec_ok = dnssec_ok;
+        self
+    }
+
+    pub fn build(self) -> Result<DnsMessage, String> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        let mut message = self.message;
+        message.set_rcode(self.rcode);
+        message.header.question_count = message.questions.len() as u16;
+        message.header.answer_count = message.answers.len() as u16;
+        message.header.authority_count = message.authorities.len() as u16;
+        message.header.additional_count = (message.additionals.len() + message.edns.is_some() as usize) as u16;
+        Ok(message)
+    }
+
+    /// Nom sous sa forme de présentation habituelle (sans point final) ; un
+    /// nom invalide est gardé tel quel et l'erreur rendue par `build`
+    fn checked_name(&mut self, name: &str) -> String {
+        match name_labels(name) {
+            Ok(labels) => labels_to_name(&labels),
+            Err(e) => {
+                self.error.get_or_insert(e);
+                name.to_string()
+            }
+        }
+    }
+
+    fn checked_record(&mut self, record: DnsAnswer) -> DnsAnswer {
+        let name = self.checked_name(&record.name);
+        DnsAnswer { name, ..record }
+    }
+}
+
+/// Message reçu lu sur place : les octets sont empruntés et non copiés.
+///
+/// `parse` vérifie seulement la structure du message (en-tête, noms et
+/// longueurs des enregistrements), sans rien allouer ; les données d'un
+/// enregistrement sont décodées à la demande par `RecordRef::data`.
+/// `DnsMessage::from_bytes` reste l'analyse complète : tout message qu'elle
+/// accepte est accepté ici, l'inverse n'est pas vrai.
+#[derive(Debug, Clone)]
+pub struct DnsMessageRef<'a> {
+    bytes: &'a [u8],
+    header: DnsHeader,
+    /// Début des questions et de chaque section d'enregistrements, puis fin
+    /// du message
+    sections: [usize; 5],
+}
+
+impl<'a> DnsMessageRef<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let header = DnsHeader::from_bytes(bytes)?;
+        let mut sections = [12; 5];
+        let mut offset = 12;
+
+        for _ in 0..header.question_count {
+            read_labels(bytes, &mut offset, |_| {})?;
+            read_field::<4>(bytes, &mut offset, bytes.len()).map_err(|_| "Question trop courte")?;
+        }
+        let counts = [header.answer_count, header.authority_count, header.additional_count];
+        for (section, count) in counts.into_iter().enumerate() {
+            sections[section + 1] = offset;
+            for _ in 0..count {
+                read_labels(bytes, &mut offset, |_| {})?;
+                let fields: [u8; 10] = read_field(bytes, &mut offset, bytes.len()).map_err(|_| "Enregistrement trop court")?;
+                let rdlength = u16::from_be_bytes([fields[8], fields[9]]) as usize;
+                if offset + rdlength > bytes.len() {
+                    return Err("Données de l'enregistrement tronquées".into());
+                }
+                offset += rdlength;
+            }
+        }
+        sections[4] = offset;
+
+        Ok(Self { bytes, header, sections })
+    }
+
+    pub fn header(&self) -> &DnsHeader {
+        &self.header
+    }
+
+    /// Octets du message, sans ce qui suivrait son dernier enregistrement
+    pub fn bytes(&self) -> &'a [u8] {
+        &self.bytes[..self.sections[4]]
+    }
+
+    pub fn questions(&self) -> Questions<'a> {
+        Questions { bytes: self.bytes, offset: self.sections[0], remaining: self.header.question_count }
+    }
+
+    pub fn answers(&self) -> Records<'a> {
+        Records { bytes: self.bytes, offset: self.sections[1], remaining: self.header.answer_count }
+    }
+
+    pub fn authorities(&self) -> Records<'a> {
+        Records { bytes: self.bytes, offset: self.sections[2], remaining: self.header.authority_count }
+    }
+
+    /// Section additionnelle, OPT compris
+    pub fn additionals(&self) -> Records<'a> {
+        Records { bytes: self.bytes, offset: self.sections[3], remaining: self.header.additional_count }
+    }
+
+    /// Analyse complète, en message possédé
+    pub fn to_message(&self) -> Result<DnsMessage, Box<dyn std::error::Error>> {
+        DnsMessage::from_bytes(self.bytes)
+    }
+}
+
+/// Nom lu dans un message, éventuellement compressé
+#[derive(Debug, Clone, Copy)]
+pub struct NameRef<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> NameRef<'a> {
+    /// Labels bruts, de gauche à droite, sans la racine
+    pub fn labels(&self) -> Vec<&'a [u8]> {
+        let mut labels = Vec::new();
+        // Le nom a été vérifié par `DnsMessageRef::parse`
+        let _ = read_labels(self.bytes, &mut self.offset.clone(), |label| labels.push(label));
+        labels
+    }
+
+    /// Compare au nom de présentation `name`, sans distinction de casse
+    pub fn eq_name(&self, name: &str) -> bool {
+        name_labels(name).is_ok_and(|expected| {
+            let labels = self.labels();
+            labels.len() == expected.len() && labels.iter().zip(&expected).all(|(a, b)| a.eq_ignore_ascii_case(b))
+        })
+    }
+}
+
+impl fmt::Display for NameRef<'_> {
+    /// Forme de présentation, avec le point final
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.", labels_to_name(&self.labels()))
+    }
+}
+
+/// Question lue dans un message
+#[derive(Debug, Clone, Copy)]
+pub struct QuestionRef<'a> {
+    pub name: NameRef<'a>,
+    pub qtype: u16,
+    pub qclass: u16,
+}
+
+impl fmt::Display for QuestionRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\t\t{}\t{}", self.name, class_name(self.qclass), RecordType::from(self.qtype))
+    }
+}
+
+/// Enregistrement lu dans un message, données non décodées
+#[derive(Debug, Clone, Copy)]
+pub struct RecordRef<'a> {
+    pub name: NameRef<'a>,
+    pub rtype: u16,
+    pub rclass: u16,
+    pub ttl: u32,
+    /// Données brutes ; les noms qu'elles contiennent peuvent pointer
+    /// ailleurs dans le message
+    pub rdata: &'a [u8],
+    bytes: &'a [u8],
+    rdata_offset: usize,
+}
+
+impl RecordRef<'_> {
+    /// Données décodées, comme par `DnsMessage::from_bytes`
+    pub fn data(&self) -> Result<RecordData, Box<dyn std::error::Error>> {
+        if self.rdata.is_empty() && (self.rclass == CLASS_ANY || self.rclass == CLASS_NONE) {
+            return Ok(RecordData::Unknown { rtype: self.rtype, data: vec![] });
+        }
+        RecordData::from_bytes(RecordType::from(self.rtype), self.bytes, self.rdata_offset, self.rdata.len())
+    }
+
+    pub fn to_answer(&self) -> Result<DnsAnswer, Box<dyn std::error::Error>> {
+        let name = labels_to_name(&self.name.labels());
+        Ok(DnsAnswer { name, rtype: self.rtype, rclass: self.rclass, ttl: self.ttl, rdata: self.data()? })
+    }
+}
+
+impl fmt::Display for RecordRef<'_> {
+    /// Une ligne de fichier de zone ; des données illisibles sont écrites
+    /// sous la forme générique `\# longueur hexadécimal` (RFC 3597 §5)
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\t{}\t{}\t{}\t", self.name, self.ttl, class_name(self.rclass), RecordType::from(self.rtype))?;
+        match self.data() {
+            Ok(data) => write!(f, "{}", data),
+            Err(_) => {
+                write!(f, "\\# {}", self.rdata.len())?;
+                if !self.rdata.is_empty() {
+                    write!(f, " ")?;
+                }
+                self.rdata.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+            }
+        }
+    }
+}
+
+/// Questions d'un `DnsMessageRef`
+pub struct Questions<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    remaining: u16,
+}
+
+impl<'a> Iterator for Questions<'a> {
+    type Item = QuestionRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.remaining = self.remaining.checked_sub(1)?;
+        let name = NameRef { bytes: self.bytes, offset: self.offset };
+        read_labels(self.bytes, &mut self.offset, |_| {}).ok()?;
+        let fields: [u8; 4] = read_field(self.bytes, &mut self.offset, self.bytes.len()).ok()?;
+        Some(QuestionRef {
+            name,
+            qtype: u16::from_be_bytes([fields[0], fields[1]]),
+            qclass: u16::from_be_bytes([fields[2], fields[3]]),
+        })
+    }
+}
+
+/// Enregistrements d'une section d'un `DnsMessageRef`
+pub struct Records<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    remaining: u16,
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = RecordRef<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.remaining = self.remaining.checked_sub(1)?;
+        let name = NameRef { bytes: self.bytes, offset: self.offset };
+        read_labels(self.bytes, &mut self.offset, |_| {}).ok()?;
+        let fields: [u8; 10] = read_field(self.bytes, &mut self.offset, self.bytes.len()).ok()?;
+        let rdlength = u16::from_be_bytes([fields[8], fields[9]]) as usize;
+        let rdata_offset = self.offset;
+        let rdata = self.bytes.get(rdata_offset..rdata_offset + rdlength)?;
+        self.offset += rdlength;
+        Some(RecordRef {
+            name,
+            rtype: u16::from_be_bytes([fields[0], fields[1]]),
+            rclass: u16::from_be_bytes([fields[2], fields[3]]),
+            ttl: u32::from_be_bytes([fields[4], fields[5], fields[6], fields[7]]),
+            rdata,
+            bytes: self.bytes,
+            rdata_offset,
         })
     }
-}
\ No newline at end of file
+}
+
+/// Labels bruts d'un nom au format de présentation, où `\X` désigne le
+/// caractère X et `\DDD` l'octet de valeur décimale DDD ; un point final est
+/// accepté. Les limites de longueur de la RFC 1035 §2.3.4 sont vérifiées.
+pub fn name_labels(name: &str) -> Result<Vec<Vec<u8>>, String> {
+    if name.is_empty() || name == "." {
+        return Ok(vec![]);
+    }
+
+    let mut labels = Vec::new();
+    let mut label = Vec::new();
+    let mut chars = name.chars();
+    let mut buffer = [0u8; 4];
+
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                if label.is_empty() {
+                    return Err(format!("label vide dans {}", name));
+                }
+                labels.push(std::mem::take(&mut label));
+            }
+            '\\' => {
+                let escaped = chars.next().ok_or_else(|| format!("échappement incomplet dans {}", name))?;
+                match escaped.to_digit(10) {
+                    Some(hundreds) => {
+                        let tens = chars.next().and_then(|c| c.to_digit(10));
+                        let units = chars.next().and_then(|c| c.to_digit(10));
+                        let value = match (tens, units) {
+                            (Some(tens), Some(units)) => u8::try_from(hundreds * 100 + tens * 10 + units).ok(),
+                            _ => None,
+                        };
+                        label.push(value.ok_or_else(|| format!("échappement invalide dans {}", name))?);
+                    }
+                    None => label.extend_from_slice(escaped.encode_utf8(&mut buffer).as_bytes()),
+                }
+            }
+            c => label.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes()),
+        }
+    }
+    if !label.is_empty() {
+        labels.push(label);
+    }
+
+    if labels.iter().any(|label| label.len() > MAX_LABEL_LENGTH) {
+        return Err(format!("label de plus de {} octets dans {}", MAX_LABEL_LENGTH, name));
+    }
+    if labels.iter().map(|label| label.len() + 1).sum::<usize>() + 1 > MAX_NAME_LENGTH {
+        return Err(format!("nom de plus de {} octets: {}", MAX_NAME_LENGTH, name));
+    }
+    Ok(labels)
+}
+
+/// Forme de présentation de labels bruts, sans point final : les octets
+/// qui ne sont pas des caractères imprimables ordinaires sont échappés, de
+/// sorte que `name_labels` redonne exactement les mêmes labels
+pub fn labels_to_name<L: AsRef<[u8]>>(labels: &[L]) -> String {
+    let mut name = String::new();
+    for (index, label) in labels.iter().enumerate() {
+        if index > 0 {
+            name.push('.');
+        }
+        for &byte in label.as_ref() {
+            match byte {
+                b'.' | b'\\' | b'"' | b'(' | b')' | b';' | b'@' | b'$' => name.extend(['\\', byte as char]),
+                0x21..=0x7E => name.push(byte as char),
+                _ => name.push_str(&format!("\\{:03}", byte)),
+            }
+        }
+    }
+    name
+}
+
+/// Encode un nom de domaine (RFC 1035 §4.1.4).
+///
+/// Si un suffixe du nom a déjà été écrit dans le message, il est remplacé
+/// par un pointeur vers sa première occurrence ; sinon chaque nouveau suffixe
+/// est enregistré dans `compression` pour les noms suivants.
+///
+/// Les noms sont validés à leur entrée (fichiers de zone, client, messages
+/// reçus) ; un nom invalide qui arriverait jusqu'ici est écrit comme la
+/// racine plutôt que de produire un message illisible.
+pub fn encode_name(bytes: &mut Vec<u8>, name: &str, compression: &mut NameCompression) {
+    let labels = name_labels(name).unwrap_or_default();
+    
+    for i in 0..labels.len() {
+        let suffix = &labels[i..];
+        
+        if let Some(&position) = compression.get(suffix) {
+            let pointer = 0xC000 | position as u16;
+            bytes.extend_from_slice(&pointer.to_be_bytes());
+            return;
+        }
+        
+        if bytes.len() <= MAX_POINTER_OFFSET {
+            compression.insert(suffix.to_vec(), bytes.len());
+        }
+        
+        bytes.push(labels[i].len() as u8);
+        bytes.extend_from_slice(&labels[i]);
+    }
+    
+    bytes.push(0); // Null terminator
+}
+
+/// Décode un nom de domaine à partir de `offset`, en suivant les pointeurs
+/// de compression.
+///
+/// `offset` est positionné juste après le nom tel qu'il apparaît dans le
+/// message (c'est-à-dire après le premier pointeur rencontré). Les pointeurs
+/// doivent désigner une position antérieure, ce qui rend les boucles
+/// impossibles ; le nombre de sauts est en plus borné. Les labels peuvent
+/// contenir n'importe quel octet : ils sont échappés dans le nom renvoyé.
+pub fn decode_name(bytes: &[u8], offset: &mut usize) -> Result<String, Box<dyn std::error::Error>> {
+    let mut labels: Vec<&[u8]> = Vec::new();
+    read_labels(bytes, offset, |label| labels.push(label))?;
+    Ok(labels_to_name(&labels))
+}
+
+/// Parcourt le nom situé à `offset` comme `decode_name`, en passant ses
+/// labels bruts à `visit` au lieu de les copier
+fn read_labels<'a>(bytes: &'a [u8], offset: &mut usize, mut visit: impl FnMut(&'a [u8])) -> Result<(), Box<dyn std::error::Error>> {
+    let mut name_length = 1; // octet nul final
+    let mut position = *offset;
+    let mut jumps = 0;
+    let mut end_offset = None;
+    
+    loop {
+        let length = *bytes.get(position).ok_or("Nom de domaine tronqué")? as usize;
+        
+        match length & 0xC0 {
+            0x00 => {
+                position += 1;
+                
+                if length == 0 {
+                    break;
+                }
+                
+                let label = bytes.get(position..position + length).ok_or("Nom de domaine invalide")?;
+                name_length += length + 1;
+                if name_length > MAX_NAME_LENGTH {
+                    return Err("Nom de domaine trop long".into());
+                }
+                
+                visit(label);
+                position += length;
+            }
+            0xC0 => {
+                let low = *bytes.get(position + 1).ok_or("Pointeur de compression tronqué")?;
+                let target = ((length & 0x3F) << 8) | low as usize;
+                
+                if target >= position {
+                    return Err("Pointeur de compression invalide".into());
+                }
+                
+                jumps += 1;
+                if jumps > MAX_POINTER_JUMPS {
+                    return Err("Trop de pointeurs de compression".into());
+                }
+                
+                // Le nom se termine dans le message au premier pointeur
+                end_offset.get_or_insert(position + 2);
+                position = target;
+            }
+            _ => return Err("Type de label inconnu".into()),
+        }
+    }
+    
+    *offset = end_offset.unwrap_or(position);
+    Ok(())
+}
+
+/// Forme canonique d'un nom pour DNSSEC (RFC 4034 §6.2) : format binaire,
+/// sans compression, lettres ASCII en minuscules
+pub fn canonical_name(name: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for label in name_labels(name).unwrap_or_default() {
+        bytes.push(label.len() as u8);
+        bytes.extend(label.to_ascii_lowercase());
+    }
+    bytes.push(0);
+    bytes
+}
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Base64 avec remplissage (RFC 4648 §4), format des clés et signatures
+/// dans les fichiers de zone
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let value = chunk.iter().enumerate().fold(0u32, |value, (index, &byte)| value | (byte as u32) << (16 - 8 * index));
+        for index in 0..4 {
+            if index <= chunk.len() {
+                text.push(BASE64_ALPHABET[(value >> (18 - 6 * index) & 0x3F) as usize] as char);
+            } else {
+                text.push('=');
+            }
+        }
+    }
+    text
+}
+
+/// Décode du base64 ; les blancs sont ignorés, un fichier de zone pouvant
+/// couper une clé sur plusieurs lignes
+pub fn base64_decode(text: &str) -> Result<Vec<u8>, String> {
+    let symbols: Vec<u8> = text.bytes().filter(|byte| !byte.is_ascii_whitespace()).collect();
+    let data = symbols.strip_suffix(b"==").or_else(|| symbols.strip_suffix(b"=")).unwrap_or(&symbols);
+    if !symbols.len().is_multiple_of(4) || data.len() % 4 == 1 {
+        return Err(format!("base64 invalide: {}", text));
+    }
+
+    let mut bytes = Vec::with_capacity(data.len() * 3 / 4);
+    for chunk in data.chunks(4) {
+        let mut value = 0u32;
+        for (index, symbol) in chunk.iter().enumerate() {
+            let digit = BASE64_ALPHABET
+                .iter()
+                .position(|candidate| candidate == symbol)
+                .ok_or_else(|| format!("base64 invalide: {}", text))?;
+            value |= (digit as u32) << (18 - 6 * index);
+        }
+        bytes.extend_from_slice(&value.to_be_bytes()[1..chunk.len()]);
+    }
+    Ok(bytes)
+}
+
+/// Date d'un RRSIG au format de présentation AAAAMMJJHHMMSS (UTC)
+pub fn format_timestamp(seconds: u32) -> String {
+    let days = (seconds / 86400) as i64;
+    let time = seconds % 86400;
+
+    // Jours depuis 1970 vers date civile (algorithme de H. Hinnant)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!("{:04}{:02}{:02}{:02}{:02}{:02}", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
+
+/// Lit une date de RRSIG, au format AAAAMMJJHHMMSS ou en secondes
+pub fn parse_timestamp(text: &str) -> Option<u32> {
+    if text.len() != 14 {
+        return text.parse().ok();
+    }
+    if !text.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let field = |range: std::ops::Range<usize>| text[range].parse::<i64>().ok();
+    let (year, month, day) = (field(0..4)?, field(4..6)?, field(6..8)?);
+    let (hour, minute, second) = (field(8..10)?, field(10..12)?, field(12..14)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    // Date civile vers jours depuis 1970 (même algorithme)
+    let year = year - (month <= 2) as i64;
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    // Les dates sont comptées modulo 2^32 (RFC 4034 §3.1.5)
+    Some((days * 86400 + hour * 3600 + minute * 60 + second) as u32)
+}
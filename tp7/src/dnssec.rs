@@ -0,0 +1,905 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use ring::digest;
+use ring::rand::SystemRandom;
+use ring::signature::{self, EcdsaKeyPair, Ed25519KeyPair, KeyPair, UnparsedPublicKey};
+use crate::client::{ClientError, DnsClient};
+use crate::dns_message::{
+    DnsAnswer, DnsMessage, RecordData, RecordType, ResponseCode, base64_decode, base64_encode, canonical_name,
+    labels_to_name, name_labels,
+};
+use crate::store::{RecordStore, serial_newer};
+use crate::zone::{self, is_subdomain};
+
+/// Drapeau ZONE d'un DNSKEY : la clé signe des données de zone
+const FLAG_ZONE: u16 = 0x0100;
+
+/// Drapeau SEP d'un DNSKEY : clé de signature de clés (KSK), désignée par
+/// le DS de la zone parente
+const FLAG_SEP: u16 = 0x0001;
+
+/// Seule valeur admise du champ protocole d'un DNSKEY (RFC 4034 §2.1.2)
+const DNSKEY_PROTOCOL: u8 = 3;
+
+const DNSKEY_TTL: u32 = 3600;
+
+/// Empreinte SHA-256 des DS (RFC 4509), la seule produite et vérifiée
+const DIGEST_SHA256: u8 = 2;
+
+/// Durée de validité des signatures produites
+pub const SIGNATURE_VALIDITY: u32 = 30 * 86400;
+
+/// Les signatures prennent effet un peu avant leur création, pour les
+/// clients dont l'horloge retarde
+const INCEPTION_MARGIN: u32 = 3600;
+
+/// Algorithmes de signature gérés (registre IANA des algorithmes DNSSEC)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// ECDSA sur la courbe P-256 avec SHA-256 (RFC 6605)
+    EcdsaP256Sha256,
+    /// Ed25519 (RFC 8080)
+    Ed25519,
+}
+
+impl Algorithm {
+    pub fn number(self) -> u8 {
+        match self {
+            Algorithm::EcdsaP256Sha256 => 13,
+            Algorithm::Ed25519 => 15,
+        }
+    }
+
+    fn from_number(number: u8) -> Option<Self> {
+        match number {
+            13 => Some(Algorithm::EcdsaP256Sha256),
+            15 => Some(Algorithm::Ed25519),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_ascii_lowercase().as_str() {
+            "p256" | "ecdsap256sha256" | "13" => Ok(Algorithm::EcdsaP256Sha256),
+            "ed25519" | "15" => Ok(Algorithm::Ed25519),
+            _ => Err(format!("Algorithme inconnu: {} (ed25519 ou p256)", text)),
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Algorithm::EcdsaP256Sha256 => write!(f, "ECDSAP256SHA256"),
+            Algorithm::Ed25519 => write!(f, "ED25519"),
+        }
+    }
+}
+
+enum KeyPairKind {
+    EcdsaP256(EcdsaKeyPair),
+    Ed25519(Ed25519KeyPair),
+}
+
+/// Clé privée d'une zone, avec l'enregistrement DNSKEY qui la publie
+pub struct SigningKey {
+    pub dnskey: DnsAnswer,
+    algorithm: Algorithm,
+    key_pair: KeyPairKind,
+}
+
+impl SigningKey {
+    /// Génère une clé pour `zone` et renvoie le contenu de son fichier : le
+    /// DNSKEY au format des fichiers de zone, puis la clé privée PKCS#8 en
+    /// base64. Une KSK porte le drapeau SEP.
+    pub fn generate(zone: &str, algorithm: Algorithm, ksk: bool) -> Result<String, Box<dyn std::error::Error>> {
+        let rng = SystemRandom::new();
+        let pkcs8 = match algorithm {
+            Algorithm::EcdsaP256Sha256 => EcdsaKeyPair::generate_pkcs8(&signature::ECDSA_P256_SHA256_FIXED_SIGNING, &rng),
+            Algorithm::Ed25519 => Ed25519KeyPair::generate_pkcs8(&rng),
+        }
+        .map_err(|_| "Génération de la clé impossible")?;
+
+        let flags = if ksk { FLAG_ZONE | FLAG_SEP } else { FLAG_ZONE };
+        let key = Self::from_pkcs8(zone, algorithm, flags, pkcs8.as_ref())?;
+        Ok(format!(
+            "; Clé DNSSEC de {}. ({}, tag {}{})\n{}\nPrivateKey: {}\n",
+            zone,
+            algorithm,
+            key.key_tag(),
+            if ksk { ", KSK" } else { "" },
+            key.dnskey,
+            base64_encode(pkcs8.as_ref())
+        ))
+    }
+
+    /// Lit un fichier produit par `generate`
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let error = |message: String| format!("{}: {}", path.display(), message);
+        let text = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+
+        let mut dnskey = None;
+        let mut private_key = None;
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with(';')) {
+            match line.strip_prefix("PrivateKey:") {
+                Some(key) => private_key = Some(base64_decode(key).map_err(error)?),
+                None => dnskey = Some(zone::parse_record_line(line, "").map_err(error)?),
+            }
+        }
+
+        let dnskey = dnskey.ok_or_else(|| error("enregistrement DNSKEY manquant".to_string()))?;
+        let private_key = private_key.ok_or_else(|| error("clé privée manquante".to_string()))?;
+        let RecordData::DNSKEY { flags, algorithm, .. } = dnskey.rdata else {
+            return Err(error(format!("DNSKEY attendu, {} trouvé", dnskey.rdata.record_type())).into());
+        };
+        let algorithm = Algorithm::from_number(algorithm).ok_or_else(|| error(format!("algorithme {} non supporté", algorithm)))?;
+
+        let key = Self::from_pkcs8(&dnskey.name, algorithm, flags, &private_key).map_err(error)?;
+        if key.dnskey.rdata != dnskey.rdata {
+            return Err(error("la clé privée ne correspond pas au DNSKEY".to_string()).into());
+        }
+        Ok(Self { dnskey, ..key })
+    }
+
+    fn from_pkcs8(zone: &str, algorithm: Algorithm, flags: u16, pkcs8: &[u8]) -> Result<Self, String> {
+        let (key_pair, public_key) = match algorithm {
+            Algorithm::EcdsaP256Sha256 => {
+                let key_pair = EcdsaKeyPair::from_pkcs8(&signature::ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8, &SystemRandom::new())
+                    .map_err(|e| format!("clé privée invalide: {}", e))?;
+                // ring donne le point non compressé (0x04, X, Y) ; le DNSKEY
+                // ne garde que X et Y (RFC 6605 §4)
+                let public_key = key_pair.public_key().as_ref()[1..].to_vec();
+                (KeyPairKind::EcdsaP256(key_pair), public_key)
+            }
+            Algorithm::Ed25519 => {
+                let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8).map_err(|e| format!("clé privée invalide: {}", e))?;
+                let public_key = key_pair.public_key().as_ref().to_vec();
+                (KeyPairKind::Ed25519(key_pair), public_key)
+            }
+        };
+
+        let rdata = RecordData::DNSKEY { flags, protocol: DNSKEY_PROTOCOL, algorithm: algorithm.number(), public_key };
+        Ok(Self { dnskey: DnsAnswer::with_ttl(zone.to_string(), DNSKEY_TTL, rdata), algorithm, key_pair })
+    }
+
+    /// Sommet de la zone de la clé
+    pub fn zone(&self) -> &str {
+        &self.dnskey.name
+    }
+
+    pub fn key_tag(&self) -> u16 {
+        key_tag(&self.dnskey.rdata)
+    }
+
+    /// Clé de signature de clés (drapeau SEP)
+    pub fn is_ksk(&self) -> bool {
+        matches!(self.dnskey.rdata, RecordData::DNSKEY { flags, .. } if flags & FLAG_SEP != 0)
+    }
+
+    /// Signe un RRset (enregistrements de même nom et de même type), pour
+    /// la période d'`inception` à `expiration`
+    pub fn sign(&self, rrset: &[&DnsAnswer], inception: u32, expiration: u32) -> Result<DnsAnswer, String> {
+        let first = rrset.first().ok_or("RRset vide")?;
+        let rrsig = |signature: Vec<u8>| RecordData::RRSIG {
+            type_covered: first.rtype,
+            algorithm: self.algorithm.number(),
+            labels: signature_labels(&first.name),
+            original_ttl: first.ttl,
+            expiration,
+            inception,
+            key_tag: self.key_tag(),
+            signer: self.zone().to_string(),
+            signature,
+        };
+
+        let data = signed_data(&rrsig(vec![]), rrset);
+        let signature = match &self.key_pair {
+            KeyPairKind::EcdsaP256(key_pair) => {
+                key_pair.sign(&SystemRandom::new(), &data).map_err(|_| "Signature impossible")?.as_ref().to_vec()
+            }
+            KeyPairKind::Ed25519(key_pair) => key_pair.sign(&data).as_ref().to_vec(),
+        };
+        Ok(DnsAnswer::with_ttl(first.name.clone(), first.ttl, rrsig(signature)))
+    }
+}
+
+/// Identifiant d'une clé (RFC 4034 annexe B), repris par ses RRSIG et DS
+pub fn key_tag(dnskey: &RecordData) -> u16 {
+    let bytes = dnskey.to_canonical_bytes();
+    let mut sum: u32 = 0;
+    for (index, &byte) in bytes.iter().enumerate() {
+        sum += if index % 2 == 0 { (byte as u32) << 8 } else { byte as u32 };
+    }
+    sum += (sum >> 16) & 0xFFFF;
+    sum as u16
+}
+
+/// DS désignant `dnskey`, à publier dans la zone parente ou à donner comme
+/// ancre de confiance
+pub fn ds_for(dnskey: &DnsAnswer) -> DnsAnswer {
+    let mut data = canonical_name(&dnskey.name);
+    data.extend(dnskey.rdata.to_canonical_bytes());
+    let algorithm = match dnskey.rdata {
+        RecordData::DNSKEY { algorithm, .. } => algorithm,
+        _ => 0,
+    };
+    let rdata = RecordData::DS {
+        key_tag: key_tag(&dnskey.rdata),
+        algorithm,
+        digest_type: DIGEST_SHA256,
+        digest: digest::digest(&digest::SHA256, &data).as_ref().to_vec(),
+    };
+    DnsAnswer::with_ttl(dnskey.name.clone(), dnskey.ttl, rdata)
+}
+
+/// Indique si `ds` désigne `dnskey`
+pub fn ds_matches(ds: &DnsAnswer, dnskey: &DnsAnswer) -> bool {
+    ds.name.eq_ignore_ascii_case(&dnskey.name) && ds_for(dnskey).rdata == ds.rdata
+}
+
+/// Nombre de labels d'un propriétaire pour son RRSIG, sans le joker
+fn signature_labels(name: &str) -> u8 {
+    let labels = name_labels(name).unwrap_or_default();
+    let wildcard = labels.first().is_some_and(|label| label == b"*");
+    (labels.len() - wildcard as usize) as u8
+}
+
+/// Propriétaire couvert par une signature : pour une réponse synthétisée
+/// par un joker, le RRSIG compte moins de labels que le nom, qui redevient
+/// `*.<labels de droite>` (RFC 4035 §5.3.2)
+fn signature_owner(name: &str, labels: u8) -> Vec<u8> {
+    let mut owner = name_labels(name).unwrap_or_default();
+    if owner.len() > labels as usize {
+        owner.drain(..owner.len() - labels as usize);
+        owner.insert(0, b"*".to_vec());
+    }
+    canonical_name(&labels_to_name(&owner))
+}
+
+/// Données couvertes par une signature (RFC 4034 §3.1.8.1) : les champs du
+/// RRSIG sans la signature, puis chaque enregistrement sous forme canonique,
+/// dans l'ordre de leurs données
+fn signed_data(rrsig: &RecordData, rrset: &[&DnsAnswer]) -> Vec<u8> {
+    let (RecordData::RRSIG { labels, original_ttl, .. }, Some(first)) = (rrsig, rrset.first()) else {
+        return vec![];
+    };
+
+    let mut data = Vec::new();
+    rrsig.write_rrsig_fields(&mut data, true);
+
+    let owner = signature_owner(&first.name, *labels);
+    let mut rdatas: Vec<Vec<u8>> = rrset.iter().map(|record| record.rdata.to_canonical_bytes()).collect();
+    rdatas.sort();
+    rdatas.dedup();
+    for rdata in rdatas {
+        data.extend_from_slice(&owner);
+        data.extend_from_slice(&first.rtype.to_be_bytes());
+        data.extend_from_slice(&first.rclass.to_be_bytes());
+        data.extend_from_slice(&original_ttl.to_be_bytes());
+        data.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        data.extend(rdata);
+    }
+    data
+}
+
+/// Vérifie la signature `rrsig` du RRset avec la clé `dnskey`, à la date
+/// `now` (RFC 4035 §5.3)
+pub fn verify(rrset: &[&DnsAnswer], rrsig: &DnsAnswer, dnskey: &DnsAnswer, now: u32) -> Result<(), String> {
+    let RecordData::RRSIG { type_covered, algorithm, labels, expiration, inception, key_tag: tag, signer, signature, .. } = &rrsig.rdata else {
+        return Err("RRSIG attendu".to_string());
+    };
+    let RecordData::DNSKEY { flags, protocol, algorithm: key_algorithm, public_key } = &dnskey.rdata else {
+        return Err("DNSKEY attendu".to_string());
+    };
+    let first = rrset.first().ok_or("RRset vide")?;
+
+    if *type_covered != first.rtype || !rrsig.name.eq_ignore_ascii_case(&first.name) {
+        return Err("la signature porte sur un autre RRset".to_string());
+    }
+    if !signer.eq_ignore_ascii_case(&dnskey.name) || !is_subdomain(&first.name, signer) {
+        return Err(format!("signataire {}. incohérent", signer));
+    }
+    if flags & FLAG_ZONE == 0 || *protocol != DNSKEY_PROTOCOL || algorithm != key_algorithm || *tag != key_tag(&dnskey.rdata) {
+        return Err(format!("la clé {} ne correspond pas à la signature", key_tag(&dnskey.rdata)));
+    }
+    if *labels as usize > name_labels(&first.name).unwrap_or_default().len() {
+        return Err("nombre de labels invalide".to_string());
+    }
+    // Dates comparées en arithmétique de la RFC 1982 (RFC 4034 §3.1.5)
+    if serial_newer(*inception, now) {
+        return Err("signature pas encore valide".to_string());
+    }
+    if serial_newer(now, *expiration) {
+        return Err("signature expirée".to_string());
+    }
+
+    let data = signed_data(&rrsig.rdata, rrset);
+    let verified = match Algorithm::from_number(*algorithm) {
+        Some(Algorithm::Ed25519) => UnparsedPublicKey::new(&signature::ED25519, public_key).verify(&data, signature),
+        Some(Algorithm::EcdsaP256Sha256) => {
+            let mut point = vec![0x04];
+            point.extend_from_slice(public_key);
+            UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point).verify(&data, signature)
+        }
+        None => return Err(format!("algorithme {} non supporté", algorithm)),
+    };
+    verified.map_err(|_| "signature invalide".to_string())
+}
+
+/// Vérifie qu'au moins une des signatures du RRset est valide avec l'une
+/// des clés ; l'erreur rapportée est celle de la dernière tentative
+pub fn verify_rrset(rrset: &[&DnsAnswer], rrsigs: &[&DnsAnswer], keys: &[DnsAnswer], now: u32) -> Result<(), String> {
+    let mut last_error = "aucune signature".to_string();
+    for rrsig in rrsigs {
+        let RecordData::RRSIG { key_tag: tag, .. } = rrsig.rdata else {
+            continue;
+        };
+        for key in keys.iter().filter(|key| key_tag(&key.rdata) == tag) {
+            match verify(rrset, rrsig, key, now) {
+                Ok(()) => return Ok(()),
+                Err(e) => last_error = e,
+            }
+        }
+    }
+    Err(last_error)
+}
+
+/// Labels en minuscules, du plus à droite au plus à gauche : leur ordre est
+/// l'ordre canonique des noms (RFC 4034 §6.1)
+fn canonical_key(name: &str) -> Vec<Vec<u8>> {
+    let mut labels = name_labels(name).unwrap_or_default();
+    for label in &mut labels {
+        label.make_ascii_lowercase();
+    }
+    labels.reverse();
+    labels
+}
+
+/// Comparaison de deux noms dans l'ordre canonique
+pub fn canonical_cmp(a: &str, b: &str) -> Ordering {
+    canonical_key(a).cmp(&canonical_key(b))
+}
+
+/// Indique si le NSEC couvre `name`, c'est-à-dire si `name` se trouve
+/// strictement entre son propriétaire et le nom suivant ; le dernier NSEC
+/// de la zone couvre tout ce qui suit son propriétaire
+pub fn nsec_covers(nsec: &DnsAnswer, name: &str) -> bool {
+    let RecordData::NSEC { next, .. } = &nsec.rdata else {
+        return false;
+    };
+    let after_owner = canonical_cmp(name, &nsec.name) == Ordering::Greater;
+    let before_next = canonical_cmp(name, next) == Ordering::Less;
+    if canonical_cmp(&nsec.name, next) == Ordering::Less {
+        after_owner && before_next
+    } else {
+        after_owner || before_next
+    }
+}
+
+fn nsec_has_type(nsec: &DnsAnswer, rtype: RecordType) -> bool {
+    matches!(&nsec.rdata, RecordData::NSEC { types, .. } if types.contains(&rtype.to_u16()))
+}
+
+/// Plus long ancêtre commun à deux noms
+fn common_ancestor(a: &str, b: &str) -> String {
+    let common = canonical_key(a).iter().zip(canonical_key(b)).take_while(|(x, y)| **x == *y).count();
+    let labels = name_labels(a).unwrap_or_default();
+    labels_to_name(&labels[labels.len() - common..])
+}
+
+fn wildcard_of(encloser: &str) -> String {
+    if encloser.is_empty() { "*".to_string() } else { format!("*.{}", encloser) }
+}
+
+/// Vérifie que des NSEC authentifiés prouvent que `name` n'existe pas : un
+/// NSEC couvre le nom, et un NSEC couvre le joker de son plus proche ancêtre
+/// existant (RFC 4035 §5.4)
+pub fn proves_nxdomain(nsecs: &[DnsAnswer], name: &str) -> Result<(), String> {
+    let covering = nsecs
+        .iter()
+        .find(|nsec| nsec_covers(nsec, name))
+        .ok_or_else(|| format!("aucun NSEC ne couvre {}.", name))?;
+    let wildcard = wildcard_of(&closest_encloser(covering, name));
+    if nsecs.iter().any(|nsec| nsec_covers(nsec, &wildcard)) {
+        Ok(())
+    } else {
+        Err(format!("aucun NSEC n'exclut le joker {}.", wildcard))
+    }
+}
+
+/// Plus proche ancêtre existant de `name`, déduit du NSEC qui le couvre
+fn closest_encloser(covering: &DnsAnswer, name: &str) -> String {
+    let from_owner = common_ancestor(name, &covering.name);
+    let from_next = match &covering.rdata {
+        RecordData::NSEC { next, .. } => common_ancestor(name, next),
+        _ => String::new(),
+    };
+    if canonical_key(&from_next).len() > canonical_key(&from_owner).len() { from_next } else { from_owner }
+}
+
+/// Vérifie que des NSEC authentifiés prouvent que `name` n'a pas
+/// d'enregistrement `qtype` : NSEC du nom sans le type, nœud intermédiaire
+/// vide, ou joker sans le type (RFC 4035 §5.4)
+pub fn proves_nodata(nsecs: &[DnsAnswer], name: &str, qtype: RecordType) -> Result<(), String> {
+    let lacks_type = |nsec: &DnsAnswer| !nsec_has_type(nsec, qtype) && !nsec_has_type(nsec, RecordType::CNAME);
+
+    if let Some(nsec) = nsecs.iter().find(|nsec| nsec.name.eq_ignore_ascii_case(name)) {
+        return if lacks_type(nsec) { Ok(()) } else { Err(format!("le NSEC de {}. annonce le type {}", name, qtype)) };
+    }
+
+    // Nœud intermédiaire vide : le nom suivant est l'un de ses descendants
+    let covering = nsecs.iter().find(|nsec| nsec_covers(nsec, name));
+    if let Some(RecordData::NSEC { next, .. }) = covering.map(|nsec| &nsec.rdata)
+        && is_subdomain(next, name)
+    {
+        return Ok(());
+    }
+
+    let covering = covering.ok_or_else(|| format!("aucun NSEC pour {}.", name))?;
+    let wildcard = wildcard_of(&closest_encloser(covering, name));
+    match nsecs.iter().find(|nsec| nsec.name.eq_ignore_ascii_case(&wildcard)) {
+        Some(nsec) if lacks_type(nsec) => Ok(()),
+        _ => Err(format!("absence de {} pour {}. non prouvée", qtype, name)),
+    }
+}
+
+fn in_zone(store: &RecordStore, origin: &str, record: &DnsAnswer) -> bool {
+    store.zone_of(record).is_some_and(|zone| zone.eq_ignore_ascii_case(origin))
+}
+
+/// Signe la zone `origin` avec `keys` : les DNSKEY sont publiés au sommet,
+/// la chaîne NSEC est reconstruite et chaque RRset est signé, sauf aux
+/// délégations où seuls DS et NSEC font autorité. Les anciennes signatures
+/// sont remplacées. Renvoie le nombre de signatures produites.
+///
+/// Les KSK signent le RRset DNSKEY et les autres clés tout le reste ; une
+/// clé seule fait les deux.
+pub fn sign_zone(store: &mut RecordStore, origin: &str, keys: &[SigningKey], now: u32) -> Result<usize, String> {
+    for record in store.zone_records(origin) {
+        if matches!(record.rdata.record_type(), RecordType::RRSIG | RecordType::NSEC) {
+            store.remove(&record.name, |existing| existing.rdata == record.rdata);
+        }
+    }
+    for key in keys {
+        store.add(key.dnskey.clone());
+    }
+
+    // Délégations : NS hors du sommet, et zones filles servies ici
+    let records = store.zone_records(origin);
+    let mut cuts: Vec<String> = records
+        .iter()
+        .filter(|record| record.rdata.record_type() == RecordType::NS && !record.name.eq_ignore_ascii_case(origin))
+        .map(|record| record.name.clone())
+        .collect();
+    cuts.extend(store.origins().iter().filter(|child| {
+        let parent = child.split_once('.').map_or("", |(_, parent)| parent);
+        !child.eq_ignore_ascii_case(origin) && store.find_zone(parent).is_some_and(|zone| zone.eq_ignore_ascii_case(origin))
+    }).cloned());
+    let is_cut = |name: &str| cuts.iter().any(|cut| cut.eq_ignore_ascii_case(name));
+    // Ce qui est sous une délégation (colle) n'appartient pas à la zone
+    let occluded = |name: &str| cuts.iter().any(|cut| is_subdomain(name, cut) && !cut.eq_ignore_ascii_case(name));
+
+    let mut names: BTreeMap<Vec<Vec<u8>>, (String, Vec<&DnsAnswer>)> = BTreeMap::new();
+    for record in records.iter().filter(|record| !occluded(&record.name)) {
+        names.entry(canonical_key(&record.name)).or_insert_with(|| (record.name.clone(), vec![])).1.push(record);
+    }
+    for cut in &cuts {
+        names.entry(canonical_key(cut)).or_insert_with(|| (cut.clone(), vec![]));
+    }
+
+    let nsec_ttl = store.negative_soa(origin).map_or(DNSKEY_TTL, |soa| soa.ttl);
+    let inception = now.wrapping_sub(INCEPTION_MARGIN);
+    let expiration = now.wrapping_add(SIGNATURE_VALIDITY);
+    let (ksks, zsks): (Vec<&SigningKey>, Vec<&SigningKey>) = keys.iter().partition(|key| key.is_ksk());
+    let key_signers = if ksks.is_empty() { &zsks } else { &ksks };
+    let data_signers = if zsks.is_empty() { &ksks } else { &zsks };
+
+    let entries: Vec<&(String, Vec<&DnsAnswer>)> = names.values().collect();
+    let mut added = Vec::new();
+    for (index, (name, owned)) in entries.iter().enumerate() {
+        let next = &entries[(index + 1) % entries.len()].0;
+        let cut = is_cut(name);
+
+        let mut types: Vec<u16> = owned.iter().map(|record| record.rtype).collect();
+        if cut {
+            types.push(RecordType::NS.to_u16());
+        }
+        types.extend([RecordType::RRSIG.to_u16(), RecordType::NSEC.to_u16()]);
+        types.sort_unstable();
+        types.dedup();
+        let nsec = DnsAnswer::with_ttl(name.clone(), nsec_ttl, RecordData::NSEC { next: next.clone(), types });
+
+        let mut rrsets: BTreeMap<u16, Vec<&DnsAnswer>> = BTreeMap::new();
+        for record in owned.iter().filter(|record| !cut || record.rdata.record_type() == RecordType::DS) {
+            rrsets.entry(record.rtype).or_default().push(record);
+        }
+        rrsets.insert(RecordType::NSEC.to_u16(), vec![&nsec]);
+        for (rtype, rrset) in rrsets {
+            let signers = if rtype == RecordType::DNSKEY.to_u16() { key_signers } else { data_signers };
+            for key in signers {
+                added.push(key.sign(&rrset, inception, expiration)?);
+            }
+        }
+        added.push(nsec);
+    }
+
+    let signatures = added.iter().filter(|record| record.rdata.record_type() == RecordType::RRSIG).count();
+    for record in added {
+        store.add(record);
+    }
+    Ok(signatures)
+}
+
+/// Signatures de la zone `origin` couvrant le type `rtype`, parmi `records`
+pub fn signatures(store: &RecordStore, origin: &str, records: &[DnsAnswer], rtype: RecordType) -> Vec<DnsAnswer> {
+    records
+        .iter()
+        .filter(|record| matches!(record.rdata, RecordData::RRSIG { type_covered, .. } if type_covered == rtype.to_u16()))
+        .filter(|record| in_zone(store, origin, record))
+        .cloned()
+        .collect()
+}
+
+/// NSEC de la zone `origin` au nom `name`, ou à défaut celui qui le couvre,
+/// avec ses signatures
+fn nsec_for(store: &RecordStore, origin: &str, name: &str) -> Vec<DnsAnswer> {
+    let is_nsec = |record: &&DnsAnswer| record.rdata.record_type() == RecordType::NSEC && in_zone(store, origin, record);
+    let nsec = store
+        .get(name)
+        .iter()
+        .find(is_nsec)
+        .or_else(|| store.records().filter(is_nsec).find(|nsec| nsec_covers(nsec, name)));
+    match nsec {
+        Some(nsec) => {
+            let mut proof = vec![nsec.clone()];
+            proof.extend(signatures(store, origin, store.get(&nsec.name), RecordType::NSEC));
+            proof
+        }
+        None => vec![],
+    }
+}
+
+/// NSEC à joindre à une réponse de la zone `origin` pour `name` : celui du
+/// nom (ou qui le couvre) et, si le nom n'existe pas, celui du joker de son
+/// plus proche ancêtre. Ils servent aux réponses négatives comme aux
+/// réponses synthétisées par un joker (RFC 4035 §3.1.3).
+pub fn denial(store: &RecordStore, origin: &str, name: &str) -> Vec<DnsAnswer> {
+    let mut proof = nsec_for(store, origin, name);
+    if !store.name_exists(name) {
+        for record in nsec_for(store, origin, &store.source_of_synthesis(name, origin)) {
+            if !proof.contains(&record) {
+                proof.push(record);
+            }
+        }
+    }
+    proof
+}
+
+/// Date courante au format des RRSIG
+pub fn now() -> u32 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs() as u32)
+}
+
+/// Ancres de confiance : DS ou DNSKEY au format des fichiers de zone, un par
+/// ligne (la sortie de `keygen` convient)
+pub fn load_trust_anchors(path: &Path) -> Result<Vec<DnsAnswer>, Box<dyn std::error::Error>> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut anchors = Vec::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with(';')) {
+        let record = zone::parse_record_line(line, "").map_err(|e| format!("{}: {}", path.display(), e))?;
+        if matches!(record.rdata.record_type(), RecordType::DS | RecordType::DNSKEY) {
+            anchors.push(record);
+        }
+    }
+    if anchors.is_empty() {
+        return Err(format!("{}: aucun DS ni DNSKEY", path.display()).into());
+    }
+    Ok(anchors)
+}
+
+/// Résultat de la validation d'une réponse (RFC 4033 §5)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Security {
+    /// Chaîne de signatures complète depuis une ancre de confiance
+    Secure,
+    /// Nom hors des ancres, ou sous une délégation prouvée non signée
+    Insecure,
+    /// Signature ou preuve manquante ou invalide
+    Bogus(String),
+}
+
+impl fmt::Display for Security {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Security::Secure => write!(f, "secure"),
+            Security::Insecure => write!(f, "insecure"),
+            Security::Bogus(reason) => write!(f, "bogus ({})", reason),
+        }
+    }
+}
+
+/// Ce qui interrompt une validation
+enum Failure {
+    Insecure,
+    Bogus(String),
+    Client(ClientError),
+}
+
+impl From<ClientError> for Failure {
+    fn from(e: ClientError) -> Self {
+        Failure::Client(e)
+    }
+}
+
+type Check<T> = Result<T, Failure>;
+
+fn bogus<T>(reason: String) -> Check<T> {
+    Err(Failure::Bogus(reason))
+}
+
+/// RRset de `records` au nom et au type donnés, avec ses signatures
+fn rrset_with_signatures<'a>(records: &'a [DnsAnswer], name: &str, rtype: u16) -> (Vec<&'a DnsAnswer>, Vec<&'a DnsAnswer>) {
+    let same_name = |record: &&DnsAnswer| record.name.eq_ignore_ascii_case(name);
+    let rrset = records.iter().filter(same_name).filter(|record| record.rtype == rtype).collect();
+    let rrsigs = records
+        .iter()
+        .filter(same_name)
+        .filter(|record| matches!(record.rdata, RecordData::RRSIG { type_covered, .. } if type_covered == rtype))
+        .collect();
+    (rrset, rrsigs)
+}
+
+/// Validation des réponses par le client : les clés de chaque zone sont
+/// authentifiées de proche en proche depuis les ancres de confiance, en
+/// suivant les DS des zones parentes (RFC 4035 §5)
+pub struct Validator<'a> {
+    client: &'a DnsClient,
+    anchors: &'a [DnsAnswer],
+    /// Clés authentifiées par zone ; `None` pour une zone non signée
+    keys: HashMap<String, Option<Vec<DnsAnswer>>>,
+    now: u32,
+}
+
+impl<'a> Validator<'a> {
+    pub fn new(client: &'a DnsClient, anchors: &'a [DnsAnswer]) -> Self {
+        Self { client, anchors, keys: HashMap::new(), now: now() }
+    }
+
+    /// Valide une réponse obtenue avec le bit DO
+    pub async fn validate(&mut self, response: &DnsMessage) -> Result<Security, ClientError> {
+        match self.check_response(response).await {
+            Ok(()) => Ok(Security::Secure),
+            Err(Failure::Insecure) => Ok(Security::Insecure),
+            Err(Failure::Bogus(reason)) => Ok(Security::Bogus(reason)),
+            Err(Failure::Client(e)) => Err(e),
+        }
+    }
+
+    fn under_anchor(&self, name: &str) -> bool {
+        self.anchors.iter().any(|anchor| is_subdomain(name, &anchor.name))
+    }
+
+    async fn check_response(&mut self, response: &DnsMessage) -> Check<()> {
+        let question = match response.questions.first() {
+            Some(question) => question,
+            None => return bogus("réponse sans question".to_string()),
+        };
+        if !self.under_anchor(&question.name) {
+            return Err(Failure::Insecure);
+        }
+        let rcode = response.rcode();
+        if rcode != ResponseCode::NoError && rcode != ResponseCode::NXDomain {
+            return bogus(format!("réponse {}", rcode));
+        }
+        let qtype = RecordType::from(question.qtype);
+
+        // Chaque RRset de la réponse doit être signé par sa zone ; ceux
+        // synthétisés par un joker demandent en plus une preuve
+        let mut synthesized = Vec::new();
+        let mut seen: Vec<(String, u16)> = Vec::new();
+        for record in response.answers.iter().filter(|record| record.rdata.record_type() != RecordType::RRSIG) {
+            let key = (record.name.to_ascii_lowercase(), record.rtype);
+            if seen.contains(&key) {
+                continue;
+            }
+            seen.push(key);
+
+            let (rrset, rrsigs) = rrset_with_signatures(&response.answers, &record.name, record.rtype);
+            if rrsigs.is_empty() {
+                return self.prove_insecure(&record.name).await;
+            }
+            self.verify_signed(&rrset, &rrsigs).await?;
+            let labels = name_labels(&record.name).unwrap_or_default().len();
+            if rrsigs.iter().any(|rrsig| matches!(rrsig.rdata, RecordData::RRSIG { labels: signed, .. } if (signed as usize) < labels)) {
+                synthesized.push(record.name.clone());
+            }
+        }
+
+        // Dernier nom de la chaîne de CNAME, que ANY et CNAME ne suivent pas
+        let mut name = question.name.clone();
+        let follows_cname = !matches!(qtype, RecordType::ANY | RecordType::CNAME);
+        for record in response.answers.iter().filter(|_| follows_cname) {
+            if let RecordData::CNAME(target) = &record.rdata
+                && record.name.eq_ignore_ascii_case(&name)
+            {
+                name = target.clone();
+            }
+        }
+        let answered = response.answers.iter().any(|record| {
+            record.name.eq_ignore_ascii_case(&name) && (qtype == RecordType::ANY || record.rtype == qtype.to_u16())
+        });
+
+        let negative = rcode == ResponseCode::NXDomain || !answered;
+        if !negative && synthesized.is_empty() {
+            return Ok(());
+        }
+
+        let nsecs = self.authenticated_nsecs(response).await?;
+        if nsecs.is_empty() {
+            return self.prove_insecure(&name).await;
+        }
+        let proof = if rcode == ResponseCode::NXDomain {
+            proves_nxdomain(&nsecs, &name)
+        } else if !answered {
+            proves_nodata(&nsecs, &name, qtype)
+        } else {
+            Ok(())
+        };
+        proof.map_err(Failure::Bogus)?;
+
+        // Réponse issue d'un joker : le nom demandé lui-même n'existe pas
+        for owner in synthesized {
+            if !nsecs.iter().any(|nsec| nsec_covers(nsec, &owner)) {
+                return bogus(format!("joker utilisé pour {}. sans preuve que le nom n'existe pas", owner));
+            }
+        }
+        Ok(())
+    }
+
+    /// NSEC de la section autorité dont la signature est valide
+    async fn authenticated_nsecs(&mut self, response: &DnsMessage) -> Check<Vec<DnsAnswer>> {
+        let mut nsecs = Vec::new();
+        for nsec in response.authorities.iter().filter(|record| record.rdata.record_type() == RecordType::NSEC) {
+            let (rrset, rrsigs) = rrset_with_signatures(&response.authorities, &nsec.name, nsec.rtype);
+            if rrsigs.is_empty() {
+                return bogus(format!("NSEC de {}. non signé", nsec.name));
+            }
+            self.verify_signed(&rrset, &rrsigs).await?;
+            nsecs.push(nsec.clone());
+        }
+        Ok(nsecs)
+    }
+
+    /// Vérifie un RRset avec les clés authentifiées de son signataire
+    async fn verify_signed(&mut self, rrset: &[&DnsAnswer], rrsigs: &[&DnsAnswer]) -> Check<()> {
+        let signer = match rrsigs.first().map(|rrsig| &rrsig.rdata) {
+            Some(RecordData::RRSIG { signer, .. }) => signer.clone(),
+            _ => return bogus("RRset non signé".to_string()),
+        };
+        let owner = rrset.first().map_or("", |record| record.name.as_str());
+        if !is_subdomain(owner, &signer) {
+            return bogus(format!("{}. signé par {}., qui n'en est pas un ancêtre", owner, signer));
+        }
+
+        let keys = self.zone_keys(&signer).await?;
+        verify_rrset(rrset, rrsigs, &keys, self.now)
+            .map_err(|e| Failure::Bogus(format!("{}. {}: {}", owner, RecordType::from(rrset[0].rtype), e)))
+    }
+
+    /// DNSKEY authentifiés de `zone`
+    async fn zone_keys(&mut self, zone: &str) -> Check<Vec<DnsAnswer>> {
+        let key = zone.to_ascii_lowercase();
+        if let Some(keys) = self.keys.get(&key) {
+            return keys.clone().ok_or(Failure::Insecure);
+        }
+
+        let result = Box::pin(self.fetch_zone_keys(zone)).await;
+        match &result {
+            Ok(keys) => {
+                self.keys.insert(key, Some(keys.clone()));
+            }
+            Err(Failure::Insecure) => {
+                self.keys.insert(key, None);
+            }
+            Err(_) => {}
+        }
+        result
+    }
+
+    /// Authentifie les DNSKEY de `zone` par une ancre de confiance ou par le
+    /// DS de la zone parente
+    async fn fetch_zone_keys(&mut self, zone: &str) -> Check<Vec<DnsAnswer>> {
+        let anchors: Vec<DnsAnswer> = self.anchors.iter().filter(|anchor| anchor.name.eq_ignore_ascii_case(zone)).cloned().collect();
+        let entry_points = if !anchors.is_empty() {
+            anchors
+        } else if self.under_anchor(zone) {
+            self.delegation_signers(zone).await?
+        } else {
+            return Err(Failure::Insecure);
+        };
+
+        let response = self.query(zone, RecordType::DNSKEY).await?;
+        let (dnskeys, rrsigs) = rrset_with_signatures(&response.answers, zone, RecordType::DNSKEY.to_u16());
+        let trusted: Vec<DnsAnswer> = dnskeys
+            .iter()
+            .filter(|dnskey| {
+                entry_points.iter().any(|entry| match entry.rdata {
+                    RecordData::DS { .. } => ds_matches(entry, dnskey),
+                    _ => entry.rdata == dnskey.rdata,
+                })
+            })
+            .map(|dnskey| (*dnskey).clone())
+            .collect();
+        if trusted.is_empty() {
+            return bogus(format!("aucun DNSKEY de {}. ne correspond à son DS ou à l'ancre", zone));
+        }
+
+        verify_rrset(&dnskeys, &rrsigs, &trusted, self.now).map_err(|e| Failure::Bogus(format!("DNSKEY de {}.: {}", zone, e)))?;
+        Ok(dnskeys.into_iter().cloned().collect())
+    }
+
+    /// DS authentifiés de `zone`, signés par sa zone parente ; sans DS, la
+    /// zone n'est pas signée si un NSEC de la parente l'atteste
+    async fn delegation_signers(&mut self, zone: &str) -> Check<Vec<DnsAnswer>> {
+        let response = self.query(zone, RecordType::DS).await?;
+        let (ds, rrsigs) = rrset_with_signatures(&response.answers, zone, RecordType::DS.to_u16());
+        if !ds.is_empty() {
+            if rrsigs.is_empty() {
+                return bogus(format!("DS de {}. non signé", zone));
+            }
+            self.verify_signed(&ds, &rrsigs).await?;
+            return Ok(ds.into_iter().cloned().collect());
+        }
+
+        let nsecs = self.authenticated_nsecs(&response).await?;
+        match nsecs.iter().find(|nsec| nsec.name.eq_ignore_ascii_case(zone)) {
+            Some(nsec) if is_unsigned_delegation(nsec) => Err(Failure::Insecure),
+            _ => bogus(format!("absence de DS pour {}. non prouvée", zone)),
+        }
+    }
+
+    /// Cherche entre l'ancre et `name` une délégation prouvée non signée ;
+    /// sans elle, des données non signées sont fausses
+    async fn prove_insecure(&mut self, name: &str) -> Check<()> {
+        let labels = name_labels(name).unwrap_or_default();
+        let anchor_depth = self
+            .anchors
+            .iter()
+            .filter(|anchor| is_subdomain(name, &anchor.name))
+            .map(|anchor| name_labels(&anchor.name).unwrap_or_default().len())
+            .max()
+            .unwrap_or(0);
+
+        for depth in anchor_depth + 1..=labels.len() {
+            let candidate = labels_to_name(&labels[labels.len() - depth..]);
+            let response = self.query(&candidate, RecordType::DS).await?;
+            let nsecs = self.authenticated_nsecs(&response).await?;
+            if nsecs.iter().any(|nsec| nsec.name.eq_ignore_ascii_case(&candidate) && is_unsigned_delegation(nsec)) {
+                return Err(Failure::Insecure);
+            }
+        }
+        bogus(format!("données de {}. non signées dans une zone signée", name))
+    }
+
+    async fn query(&self, name: &str, qtype: RecordType) -> Check<DnsMessage> {
+        let response = self.client.query_dnssec(name, qtype).await?;
+        match response.rcode() {
+            ResponseCode::NoError | ResponseCode::NXDomain => Ok(response),
+            rcode => bogus(format!("{}. {}: réponse {}", name, qtype, rcode)),
+        }
+    }
+}
+
+/// NSEC d'une délégation sans DS : NS présent, ni DS ni SOA
+fn is_unsigned_delegation(nsec: &DnsAnswer) -> bool {
+    nsec_has_type(nsec, RecordType::NS) && !nsec_has_type(nsec, RecordType::DS) && !nsec_has_type(nsec, RecordType::SOA)
+}
@@ -0,0 +1,203 @@
+use std::fmt;
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use crate::dns_message::{base64_decode, base64_encode};
+
+/// Chemin des requêtes DoH par défaut (RFC 8484 §6 laisse le choix au
+/// serveur ; c'est celui de l'exemple de la RFC)
+pub const DEFAULT_PATH: &str = "/dns-query";
+
+/// Type des messages DNS transportés en HTTP (RFC 8484 §6)
+const CONTENT_TYPE: &str = "application/dns-message";
+
+/// Taille maximale des en-têtes d'un message HTTP
+const MAX_HTTP_HEADER: usize = 8 * 1024;
+
+/// Taille maximale d'un corps : celle d'un message DNS
+const MAX_HTTP_BODY: usize = u16::MAX as usize;
+
+/// Forme de la requête DoH (RFC 8484 §4.1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    /// Message en base64url dans le paramètre `dns` de l'URL
+    Get,
+    /// Message brut dans le corps
+    Post,
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Method::Get => write!(f, "GET"),
+            Method::Post => write!(f, "POST"),
+        }
+    }
+}
+
+/// Message HTTP/1.1 : ligne de début, en-têtes et corps
+pub struct HttpMessage {
+    pub start_line: String,
+    headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpMessage {
+    /// Valeur d'un en-tête (nom sans distinction de casse)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(header, _)| header.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+
+    /// Code d'état d'une réponse
+    pub fn status(&self) -> Option<u16> {
+        self.start_line.split_whitespace().nth(1)?.parse().ok()
+    }
+
+    /// Indique si le message porte un message DNS
+    pub fn is_dns_message(&self) -> bool {
+        self.header("Content-Type")
+            .is_some_and(|value| value.split(';').next().unwrap_or("").trim().eq_ignore_ascii_case(CONTENT_TYPE))
+    }
+
+    /// L'émetteur fermera la connexion après ce message
+    pub fn closes_connection(&self) -> bool {
+        self.header("Connection").is_some_and(|value| value.eq_ignore_ascii_case("close"))
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Lit un message HTTP/1.1 dont le corps est délimité par `Content-Length`.
+///
+/// `buffer` garde ce qui a été lu au-delà du message, début du suivant sur
+/// la même connexion. Renvoie `None` si la connexion se ferme proprement
+/// entre deux messages.
+pub async fn read_message<S: AsyncRead + Unpin>(stream: &mut S, buffer: &mut Vec<u8>) -> io::Result<Option<HttpMessage>> {
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position;
+        }
+        if buffer.len() > MAX_HTTP_HEADER {
+            return Err(invalid("en-têtes HTTP trop longs"));
+        }
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return if buffer.is_empty() { Ok(None) } else { Err(io::ErrorKind::UnexpectedEof.into()) };
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).into_owned();
+    buffer.drain(..header_end + 4);
+    let mut lines = head.split("\r\n");
+    let start_line = lines.next().unwrap_or("").to_string();
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+    let mut message = HttpMessage { start_line, headers, body: Vec::new() };
+
+    if message.header("Transfer-Encoding").is_some() {
+        return Err(invalid("Transfer-Encoding non supporté"));
+    }
+    let length = match message.header("Content-Length") {
+        Some(length) => length.parse::<usize>().map_err(|_| invalid("Content-Length invalide"))?,
+        None => 0,
+    };
+    if length > MAX_HTTP_BODY {
+        return Err(invalid("corps HTTP trop long"));
+    }
+    while buffer.len() < length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    }
+    message.body = buffer.drain(..length).collect();
+    Ok(Some(message))
+}
+
+/// Message DNS d'une requête DoH, ou l'état HTTP qui la refuse
+pub fn query_from_request(request: &HttpMessage, path: &str) -> Result<Vec<u8>, &'static str> {
+    let mut parts = request.start_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (target_path, query) = target.split_once('?').unwrap_or((target, ""));
+    if target_path != path {
+        return Err("404 Not Found");
+    }
+
+    match method {
+        "GET" => {
+            let dns = query
+                .split('&')
+                .find_map(|parameter| parameter.strip_prefix("dns="))
+                .ok_or("400 Bad Request")?;
+            base64url_decode(dns).map_err(|_| "400 Bad Request")
+        }
+        "POST" if request.is_dns_message() => Ok(request.body.clone()),
+        "POST" => Err("415 Unsupported Media Type"),
+        _ => Err("405 Method Not Allowed"),
+    }
+}
+
+/// Écrit une réponse HTTP ; un corps non vide est un message DNS, qui peut
+/// rester en cache `max_age` secondes (RFC 8484 §5.1)
+pub async fn write_response<S: AsyncWrite + Unpin>(stream: &mut S, status: &str, body: &[u8], max_age: Option<u32>) -> io::Result<()> {
+    let mut head = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n", status, body.len());
+    if !body.is_empty() {
+        head.push_str(&format!("Content-Type: {}\r\n", CONTENT_TYPE));
+    }
+    if let Some(max_age) = max_age {
+        head.push_str(&format!("Cache-Control: max-age={}\r\n", max_age));
+    }
+    head.push_str("\r\n");
+
+    let mut response = head.into_bytes();
+    response.extend_from_slice(body);
+    stream.write_all(&response).await?;
+    stream.flush().await
+}
+
+/// Envoie une requête DoH sur une connexion établie et lit la réponse ; la
+/// connexion est fermée par le serveur ensuite
+pub async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    host: &str,
+    path: &str,
+    method: Method,
+    query: &[u8],
+) -> io::Result<HttpMessage> {
+    let mut request = match method {
+        Method::Get => format!("GET {}?dns={} HTTP/1.1\r\n", path, base64url_encode(query)),
+        Method::Post => format!("POST {} HTTP/1.1\r\nContent-Type: {}\r\nContent-Length: {}\r\n", path, CONTENT_TYPE, query.len()),
+    };
+    request.push_str(&format!("Host: {}\r\nAccept: {}\r\nConnection: close\r\n\r\n", host, CONTENT_TYPE));
+
+    let mut bytes = request.into_bytes();
+    if method == Method::Post {
+        bytes.extend_from_slice(query);
+    }
+    stream.write_all(&bytes).await?;
+    stream.flush().await?;
+
+    read_message(stream, &mut Vec::new()).await?.ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+}
+
+/// Base64 de l'URL (RFC 4648 §5), sans remplissage comme l'exige RFC 8484 §6
+fn base64url_encode(bytes: &[u8]) -> String {
+    base64_encode(bytes).trim_end_matches('=').replace('+', "-").replace('/', "_")
+}
+
+fn base64url_decode(text: &str) -> Result<Vec<u8>, String> {
+    if text.contains(['+', '/', '=']) {
+        return Err(format!("base64url invalide: {}", text));
+    }
+    let mut standard = text.replace('-', "+").replace('_', "/");
+    while !standard.len().is_multiple_of(4) {
+        standard.push('=');
+    }
+    base64_decode(&standard)
+}
@@ -0,0 +1,44 @@
+pub mod acl;
+pub mod blocklist;
+pub mod cache;
+pub mod client;
+pub mod dns_message;
+pub mod dnssec;
+pub mod doh;
+pub mod metrics;
+pub mod pcap;
+pub mod ratelimit;
+pub mod resolver;
+pub mod reverse;
+pub mod server;
+pub mod store;
+pub mod tcp;
+pub mod tls;
+pub mod transfer;
+pub mod update;
+pub mod zone;
+
+pub use dns_message::{
+    CLASS_ANY,
+    CLASS_IN,
+    CLASS_NONE,
+    DnsAnswer,
+    DnsFlags,
+    DnsHeader,
+    DnsMessage,
+    DnsMessageBuilder,
+    DnsMessageRef,
+    DnsQuestion,
+    Edns,
+    NameRef,
+    Opcode,
+    QuestionRef,
+    RecordData,
+    RecordRef,
+    RecordType,
+    ResponseCode,
+};
+pub use client::{ClientError, DnsClient, Protocol};
+pub use resolver::{Hosts, ResolvConf, StubResolver};
+pub use server::{DnsServer, ServerConfig};
+pub use zone::Zone;
@@ -1,8 +1,9 @@
-mod dns_message;
-mod client;
-mod server;
-
 use std::env;
+use tp7::dns_message::{CLASS_ANY, DnsAnswer, DnsHeader, DnsMessage, RecordType};
+use tp7::{client, dnssec, pcap, resolver, server, tls, zone};
+
+mod dig;
+mod scenarios;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -10,46 +11,320 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     if args.len() < 2 {
         println!("Usage:");
-        println!("  {} server          - Démarrer le serveur DNS", args[0]);
-        println!("  {} client <domain> - Résoudre un domaine", args[0]);
-        println!("  {} test            - Tester client et serveur", args[0]);
+        println!("  {} server [--bind <addr>]... [--forward <addr>]... [--query-log <fichier>] [--metrics <addr>]", args[0]);
+        println!("         [--blocklist <fichier>]... [--sinkhole <adresse>]... [--dnssec-key <fichier>]...");
+        println!("         [--tls-cert <pem> --tls-key <pem> [--dot <addr>] [--doh <addr>]] [--pcap <fichier>] [zone...]");
+        println!("      Démarrer le serveur DNS");
+        println!("  {} client <domain|ip> [server...]", args[0]);
+        println!("      Résoudre un domaine, ou une adresse en recherche inverse");
+        println!("      (serveur par défaut: 127.0.0.1:5353)");
+        println!("  {} resolve <nom> [--resolv-conf <fichier>] [--hosts <fichier>]", args[0]);
+        println!("      Toutes les adresses d'un nom, comme la libc (par défaut /etc/resolv.conf et /etc/hosts)");
+        println!("  {} dig [@serveur[:port]] [-x adresse] [nom] [type] [classe] [+option]...", args[0]);
+        println!("      Requête détaillée à la manière de dig (+tcp, +short, +[no]rec, +cd, +dnssec...)");
+        println!("      ou chiffrée (+tls, +https[=chemin], +https-get, +tls-ca=<pem>, +tls-hostname=<nom>)");
+        println!("  {} pcap <fichier> [--port <port>]...", args[0]);
+        println!("      Afficher les messages DNS d'une capture pcap ou pcapng (port 53 par défaut)");
+        println!("  {} update <zone> add <enregistrement> | delete <nom> [type] [--server <addr>]", args[0]);
+        println!("      Mise à jour dynamique (serveur par défaut: 127.0.0.1:5353)");
+        println!("  {} keygen <zone> [ed25519|p256] [--ksk] <fichier>", args[0]);
+        println!("      Générer une clé DNSSEC et afficher son DS");
+        println!("  {} validate <nom> [type] --anchor <fichier> [--server <addr>]", args[0]);
+        println!("      Résoudre avec validation DNSSEC depuis une ancre de confiance (DS ou DNSKEY)");
+        println!("  {} certgen <cert.pem> <cle.pem> [nom|adresse]...", args[0]);
+        println!("      Générer un certificat autosigné pour DoT et DoH (par défaut localhost et 127.0.0.1)");
+        println!("  {} test", args[0]);
+        println!("      Tester client, serveur et serveur récursif");
+        println!("  {} loadtest [requêtes] [parallèles]", args[0]);
+        println!("      Test de charge sur un serveur local (127.0.0.1:5355)");
         return Ok(());
     }
 
     match args[1].as_str() {
         "server" => {
-            server::test_server().await?;
+            if let Err(e) = server::run_from_args(&args[2..]).await {
+                eprintln!("Erreur: {}", e);
+                std::process::exit(1);
+            }
         },
-        "client" => {
-            if args.len() < 3 {
-                println!("Usage: {} client <domain>", args[0]);
+        "client" => client::run(&args[2..]).await?,
+        "resolve" => {
+            let mut resolv_conf = resolver::RESOLV_CONF.to_string();
+            let mut hosts = resolver::HOSTS.to_string();
+            let mut rest = Vec::new();
+            let mut options = args[2..].iter();
+            while let Some(arg) = options.next() {
+                match arg.as_str() {
+                    "--resolv-conf" => resolv_conf = options.next().ok_or("--resolv-conf attend un fichier")?.clone(),
+                    "--hosts" => hosts = options.next().ok_or("--hosts attend un fichier")?.clone(),
+                    _ => rest.push(arg.as_str()),
+                }
+            }
+            let [name] = rest.as_slice() else {
+                println!("Usage: {} resolve <nom> [--resolv-conf <fichier>] [--hosts <fichier>]", args[0]);
                 return Ok(());
+            };
+            
+            let resolver = resolver::StubResolver::from_files(std::path::Path::new(&resolv_conf), std::path::Path::new(&hosts)).await?;
+            match resolver.lookup_ip(name).await {
+                Ok(addresses) => {
+                    for address in addresses {
+                        println!("{}", address);
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Erreur: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        },
+        "dig" => {
+            if let Err(e) = dig::run(&args[2..]).await {
+                eprintln!("Erreur: {}", e);
+                std::process::exit(1);
+            }
+        },
+        "pcap" => {
+            if let Err(e) = run_pcap(&args[2..]) {
+                eprintln!("Erreur: {}", e);
+                std::process::exit(1);
+            }
+        },
+        "update" => {
+            // Les noms relatifs sont complétés par la zone, comme dans un
+            // fichier de zone ; --server peut apparaître n'importe où
+            let mut server: std::net::SocketAddr = "127.0.0.1:5353".parse()?;
+            let mut rest = Vec::new();
+            let mut options = args[2..].iter();
+            while let Some(arg) = options.next() {
+                match arg.as_str() {
+                    "--server" => server = options.next().ok_or("--server attend une adresse")?.parse()?,
+                    _ => rest.push(arg.as_str()),
+                }
+            }
+            
+            let (zone, action, operands) = match rest.as_slice() {
+                [zone, action, operands @ ..] if !operands.is_empty() => (*zone, *action, operands),
+                _ => {
+                    println!("Usage: {} update <zone> add <enregistrement> | delete <nom> [type] [--server <addr>]", args[0]);
+                    return Ok(());
+                }
+            };
+            let zone = zone.strip_suffix('.').unwrap_or(zone);
+            
+            let update = match (action, operands) {
+                ("add", record) => zone::parse_record_line(&record.join(" "), zone)?,
+                ("delete", [name]) => DnsAnswer::empty(zone::absolute_name(name, Some(zone))?, RecordType::ANY, CLASS_ANY),
+                ("delete", [name, rtype]) => DnsAnswer::empty(zone::absolute_name(name, Some(zone))?, rtype.parse()?, CLASS_ANY),
+                _ => {
+                    println!("Action inconnue: {} (add ou delete)", action);
+                    return Ok(());
+                }
+            };
+            
+            let client = client::DnsClient::new(server).await?;
+            match client.update(zone, vec![], vec![update]).await {
+                Ok(()) => println!("Mise à jour de {}. acceptée", zone),
+                Err(e) => println!("Erreur: {}", e),
+            }
+        },
+        "keygen" => {
+            let mut algorithm = dnssec::Algorithm::Ed25519;
+            let mut ksk = false;
+            let mut rest = Vec::new();
+            for arg in &args[2..] {
+                match arg.as_str() {
+                    "--ksk" => ksk = true,
+                    "ed25519" | "p256" => algorithm = arg.parse()?,
+                    _ => rest.push(arg.as_str()),
+                }
             }
+            let [zone, file] = rest.as_slice() else {
+                println!("Usage: {} keygen <zone> [ed25519|p256] [--ksk] <fichier>", args[0]);
+                return Ok(());
+            };
             
-            let domain = &args[2];
-            let server_addr = "127.0.0.1:5353".parse()?;
-            let client = client::DnsClient::new(server_addr).await?;
+            let zone = zone.strip_suffix('.').unwrap_or(zone);
+            std::fs::write(file, dnssec::SigningKey::generate(zone, algorithm, ksk)?)?;
+            let key = dnssec::SigningKey::load(std::path::Path::new(file))?;
+            println!("Clé {} de {}. écrite dans {}", key.key_tag(), zone, file);
+            println!("{}", key.dnskey);
+            println!("{}", dnssec::ds_for(&key.dnskey));
+        },
+        "validate" => {
+            let mut server: std::net::SocketAddr = "127.0.0.1:5353".parse()?;
+            let mut anchor = None;
+            let mut rest = Vec::new();
+            let mut options = args[2..].iter();
+            while let Some(arg) = options.next() {
+                match arg.as_str() {
+                    "--server" => server = options.next().ok_or("--server attend une adresse")?.parse()?,
+                    "--anchor" => anchor = Some(options.next().ok_or("--anchor attend un fichier")?),
+                    _ => rest.push(arg.as_str()),
+                }
+            }
+            let (Some(anchor), [name, qtype @ ..]) = (anchor, rest.as_slice()) else {
+                println!("Usage: {} validate <nom> [type] --anchor <fichier> [--server <addr>]", args[0]);
+                return Ok(());
+            };
+            let qtype = qtype.first().map_or(Ok(RecordType::A), |qtype| qtype.parse())?;
             
-            match client.resolve(domain).await {
-                Ok(ip) => println!("{} -> {}", domain, ip),
+            let mut client = client::DnsClient::new(server).await?;
+            client.set_verbose(false);
+            for record in dnssec::load_trust_anchors(std::path::Path::new(anchor))? {
+                client.add_trust_anchor(record);
+            }
+            let name = name.strip_suffix('.').unwrap_or(name);
+            match client.validate(name, qtype).await {
+                Ok((response, security)) => {
+                    println!("{}. {}: {}", name, qtype, response.rcode());
+                    for record in &response.answers {
+                        println!("  {}", record);
+                    }
+                    println!("Validation: {}", security);
+                }
                 Err(e) => println!("Erreur: {}", e),
             }
         },
+        "certgen" => {
+            let [cert, key, names @ ..] = &args[2..] else {
+                println!("Usage: {} certgen <cert.pem> <cle.pem> [nom|adresse]...", args[0]);
+                return Ok(());
+            };
+            let names = if names.is_empty() { vec!["localhost".to_string(), "127.0.0.1".to_string()] } else { names.to_vec() };
+            tls::generate_self_signed(&names, std::path::Path::new(cert), std::path::Path::new(key))?;
+            println!("Certificat pour {} écrit dans {}, clé dans {}", names.join(", "), cert, key);
+        },
         "test" => {
             println!("Mode test - démarrage du serveur en arrière-plan...");
             
-            // Démarrer le serveur en arrière-plan
-            tokio::spawn(async {
-                if let Err(e) = server::test_server().await {
+            // Journal des requêtes du serveur principal, repris à zéro
+            let query_log = env::temp_dir().join("tp7-queries.jsonl");
+            let _ = std::fs::remove_file(&query_log);
+            
+            // Démarrer le serveur en arrière-plan, en IPv4 et en IPv6
+            // (qui accepte les mises à jour et les transferts de la zone de
+            // test en local, annonce les changements au serveur secondaire et
+            // publie ses compteurs)
+            let log_arg = query_log.display().to_string();
+            tokio::spawn(async move {
+                let args = [
+                    "--bind", "127.0.0.1:5353", "--bind", "[::1]:5353",
+                    "--allow-update", ".=127.0.0.1", "--allow-transfer", ".=127.0.0.1",
+                    "--notify", "127.0.0.1:5356",
+                    "--metrics", "127.0.0.1:9153", "--query-log", &log_arg,
+                ]
+                .map(String::from);
+                if let Err(e) = server::run_from_args(&args).await {
                     eprintln!("Erreur serveur: {}", e);
                 }
             });
             
-            // Attendre que le serveur démarre
+            // Et un serveur récursif qui lui transmet toutes les requêtes en IPv6
+            tokio::spawn(async {
+                let args = ["--bind", "127.0.0.1:5354", "--bind", "[::1]:5354", "--forward", "[::1]:5353"].map(String::from);
+                if let Err(e) = server::run_from_args(&args).await {
+                    eprintln!("Erreur serveur récursif: {}", e);
+                }
+            });
+            
+            // Attendre que les serveurs démarrent
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            
+            // Tester le client, directement puis via le serveur récursif
+            // (deux fois, la seconde en IPv6 et servie par le cache)
+            scenarios::test_client("127.0.0.1:5353".parse()?).await?;
+            scenarios::test_client("127.0.0.1:5354".parse()?).await?;
+            scenarios::test_client("[::1]:5354".parse()?).await?;
+            scenarios::test_dual_stack("127.0.0.1:5353".parse()?, "[::1]:5353".parse()?).await?;
+            scenarios::test_resolver("[::1]:5353".parse()?, &env::temp_dir().join("tp7-resolver")).await?;
+            scenarios::test_update("127.0.0.1:5353".parse()?).await?;
+            
+            // Un serveur secondaire recopie la zone de test par transfert
+            tokio::spawn(async {
+                let args = ["--bind", "127.0.0.1:5356", "--secondary", ".=127.0.0.1:5353", "--allow-transfer", ".=127.0.0.1"]
+                    .map(String::from);
+                if let Err(e) = server::run_from_args(&args).await {
+                    eprintln!("Erreur serveur secondaire: {}", e);
+                }
+            });
+            tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
+            scenarios::test_transfer("127.0.0.1:5353".parse()?, "127.0.0.1:5356".parse()?).await?;
+            scenarios::test_metrics("127.0.0.1:9153".parse()?, &query_log).await?;
+            
+            // Un serveur limité à 20 réponses/s, inondé depuis un seul socket
+            tokio::spawn(async {
+                let args = ["--bind", "127.0.0.1:5357", "--rate-limit", "20", "--rate-slip", "2"].map(String::from);
+                if let Err(e) = server::run_from_args(&args).await {
+                    eprintln!("Erreur serveur limité: {}", e);
+                }
+            });
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            scenarios::test_rate_limit("127.0.0.1:5357".parse()?, 200, 2).await?;
+            
+            // Un serveur filtrant, avec une liste au format hosts et une
+            // liste d'un nom par ligne dans le même fichier
+            let blocklist = env::temp_dir().join("tp7-blocklist.txt");
+            std::fs::write(&blocklist, "# Liste de test\n0.0.0.0 ads.example.com localhost\ntracker.example.com\n")?;
+            let list_arg = blocklist.display().to_string();
+            tokio::spawn(async move {
+                let args = ["--bind", "127.0.0.1:5358", "--blocklist", &list_arg, "--sinkhole", "0.0.0.0"].map(String::from);
+                if let Err(e) = server::run_from_args(&args).await {
+                    eprintln!("Erreur serveur filtrant: {}", e);
+                }
+            });
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            scenarios::test_blocklist("127.0.0.1:5358".parse()?, &blocklist).await?;
+            
+            // Un serveur qui signe com, example.com et sa délégation sans
+            // DS unsigned.com, avec des clés générées pour l'occasion
+            let dnssec_dir = env::temp_dir().join("tp7-dnssec");
+            let dnssec_args = scenarios::write_test_zones(&dnssec_dir, "127.0.0.1:5359".parse()?)?;
+            tokio::spawn(async move {
+                if let Err(e) = server::run_from_args(&dnssec_args).await {
+                    eprintln!("Erreur serveur signé: {}", e);
+                }
+            });
+            tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
+            scenarios::test_dnssec("127.0.0.1:5359".parse()?, &dnssec_dir).await?;
+            
+            // Un serveur joignable aussi en DNS sur TLS et en DNS sur HTTPS,
+            // avec un certificat autosigné qui sert d'autorité au client ; il
+            // capture tous ses messages, relus ensuite
+            let tls_dir = env::temp_dir().join("tp7-tls");
+            std::fs::create_dir_all(&tls_dir)?;
+            let (cert, key) = (tls_dir.join("cert.pem"), tls_dir.join("key.pem"));
+            tls::generate_self_signed(&["localhost".to_string(), "127.0.0.1".to_string()], &cert, &key)?;
+            let capture = tls_dir.join("capture.pcap");
+            let (cert_arg, key_arg) = (cert.display().to_string(), key.display().to_string());
+            let capture_arg = capture.display().to_string();
+            tokio::spawn(async move {
+                let args = [
+                    "--bind", "127.0.0.1:5360", "--tls-cert", &cert_arg, "--tls-key", &key_arg,
+                    "--dot", "127.0.0.1:8853", "--doh", "127.0.0.1:8443", "--pcap", &capture_arg,
+                ]
+                .map(String::from);
+                if let Err(e) = server::run_from_args(&args).await {
+                    eprintln!("Erreur serveur chiffré: {}", e);
+                }
+            });
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+            scenarios::test_transports("127.0.0.1:5360".parse()?, "127.0.0.1:8853".parse()?, "127.0.0.1:8443".parse()?, &cert).await?;
+            scenarios::test_pcap(&capture, &[5360, 8853, 8443])?;
+        },
+        "loadtest" => {
+            let total = args.get(2).map(|n| n.parse()).transpose()?.unwrap_or(5000);
+            let concurrency = args.get(3).map(|n| n.parse()).transpose()?.unwrap_or(256);
+            
+            tokio::spawn(async {
+                let args = ["--bind", "127.0.0.1:5355"].map(String::from);
+                if let Err(e) = server::run_from_args(&args).await {
+                    eprintln!("Erreur serveur: {}", e);
+                }
+            });
             tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
             
-            // Tester le client
-            client::test_client().await?;
+            scenarios::load_test("127.0.0.1:5355".parse()?, total, concurrency).await?;
         },
         _ => {
             println!("Commande inconnue: {}", args[1]);
@@ -57,4 +332,83 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Sous-commande `pcap` : `<fichier> [--port <port>]...`. Affiche chaque
+/// message DNS de la capture, en signalant ceux que `DnsMessage::from_bytes`
+/// refuse ; une erreur est rendue s'il y en a.
+fn run_pcap(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut path = None;
+    let mut ports = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => ports.push(args.next().ok_or("--port attend un numéro de port")?.parse::<u16>()?),
+            option if option.starts_with("--") => return Err(format!("option inconnue: {}", option).into()),
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(format!("argument en trop: {}", arg).into()),
+        }
+    }
+    let Some(path) = path else {
+        println!("Usage: pcap <fichier> [--port <port>]...");
+        return Ok(());
+    };
+    if ports.is_empty() {
+        ports.push(pcap::DNS_PORT);
+    }
+
+    let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    let packets = pcap::read_packets(&bytes)?;
+    let (messages, ignored) = pcap::extract_messages(&packets, &ports);
+
+    // Temps relatif au premier paquet, comme dans Wireshark
+    let start = packets.first().map_or(std::time::Duration::ZERO, |packet| packet.timestamp);
+    let mut failures = 0;
+    for (index, message) in messages.iter().enumerate() {
+        let time = message.timestamp.saturating_sub(start);
+        println!(
+            ";; #{} {}.{:06} {} -> {} ({}, {} octets)",
+            index + 1,
+            time.as_secs(),
+            time.subsec_micros(),
+            message.source,
+            message.destination,
+            message.transport,
+            message.data.len()
+        );
+        match DnsMessage::from_bytes(&message.data) {
+            Ok(decoded) => println!("{}", decoded),
+            Err(e) => {
+                failures += 1;
+                match DnsHeader::from_bytes(&message.data) {
+                    Ok(header) => println!(";; ÉCHEC de l'analyse (ID {}): {}", header.id, e),
+                    Err(_) => println!(";; ÉCHEC de l'analyse: {}", e),
+                }
+                print!("{}", hex_dump(&message.data));
+            }
+        }
+        println!();
+    }
+
+    println!(
+        ";; {} paquet(s), {} message(s) DNS, {} échec(s) d'analyse, {} paquet(s) ignoré(s)",
+        packets.len(),
+        messages.len(),
+        failures,
+        ignored
+    );
+    if failures > 0 {
+        return Err(format!("{} message(s) illisible(s) dans {}", failures, path).into());
+    }
+    Ok(())
+}
+
+/// Octets par lignes de 16, avec leur position
+fn hex_dump(bytes: &[u8]) -> String {
+    let mut dump = String::new();
+    for (line, chunk) in bytes.chunks(16).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+        dump.push_str(&format!(";;   {:04x}  {}\n", line * 16, hex.join(" ")));
+    }
+    dump
+}
@@ -0,0 +1,293 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use crate::dns_message::{DnsMessage, RecordType, class_name};
+
+/// Bornes (en secondes) de l'histogramme des temps de réponse
+const LATENCY_BUCKETS: [f64; 8] = [0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0];
+
+/// Nombre de clients suivis individuellement ; au-delà, ils sont comptés
+/// ensemble pour que la taille de la page reste bornée
+const MAX_TRACKED_CLIENTS: usize = 1024;
+
+/// Taille maximale de l'en-tête d'une requête HTTP
+const MAX_HTTP_REQUEST: usize = 8 * 1024;
+
+/// Délai laissé à un client HTTP pour envoyer sa requête
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Transport par lequel une requête est arrivée
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    Udp,
+    Tcp,
+    /// DNS sur TLS (RFC 7858)
+    Tls,
+    /// DNS sur HTTPS (RFC 8484)
+    Https,
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transport::Udp => write!(f, "udp"),
+            Transport::Tcp => write!(f, "tcp"),
+            Transport::Tls => write!(f, "tls"),
+            Transport::Https => write!(f, "https"),
+        }
+    }
+}
+
+/// Ce qu'il faut retenir d'une requête traitée
+pub struct QueryEvent<'a> {
+    pub client: SocketAddr,
+    pub transport: Transport,
+    /// Réponse envoyée (le premier message pour un transfert) : elle
+    /// reprend l'ID et la question de la requête
+    pub response: &'a DnsMessage,
+    pub latency: Duration,
+    pub cache_hit: bool,
+}
+
+/// Journal des requêtes, une ligne JSON par requête
+pub struct QueryLog {
+    file: Mutex<File>,
+}
+
+impl QueryLog {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file: Mutex::new(file) })
+    }
+
+    /// Ajoute une ligne au journal ; chaque ligne est écrite d'un bloc pour
+    /// ne pas se mélanger à celles des autres tâches
+    pub fn write(&self, event: &QueryEvent) -> io::Result<()> {
+        let mut line = event_to_json(event);
+        line.push('\n');
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.write_all(line.as_bytes())
+    }
+}
+
+fn event_to_json(event: &QueryEvent) -> String {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let question = event.response.questions.first();
+    let text = |value: Option<String>| value.map_or("null".to_string(), |value| json_string(&value));
+
+    format!(
+        "{{\"time\":{}.{:03},\"client\":{},\"transport\":\"{}\",\"id\":{},\"name\":{},\"type\":{},\"class\":{},\
+         \"rcode\":{},\"answers\":{},\"latency_us\":{},\"cache_hit\":{}}}",
+        time.as_secs(),
+        time.subsec_millis(),
+        json_string(&event.client.to_string()),
+        event.transport,
+        event.response.header.id,
+        text(question.map(|question| format!("{}.", question.name))),
+        text(question.map(|question| RecordType::from(question.qtype).to_string())),
+        text(question.map(|question| class_name(question.qclass))),
+        json_string(&event.response.rcode().to_string()),
+        event.response.answers.len(),
+        event.latency.as_micros(),
+        event.cache_hit
+    )
+}
+
+/// Chaîne JSON entre guillemets (RFC 8259 §7)
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[derive(Default)]
+struct Counters {
+    queries: u64,
+    tcp: u64,
+    by_transport: BTreeMap<String, u64>,
+    cache_hits: u64,
+    by_rcode: BTreeMap<String, u64>,
+    by_qtype: BTreeMap<String, u64>,
+    by_client: BTreeMap<IpAddr, u64>,
+    /// Requêtes des clients au-delà de `MAX_TRACKED_CLIENTS`
+    other_clients: u64,
+    /// Nombre de requêtes sous chaque borne de `LATENCY_BUCKETS`
+    latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    latency_sum: f64,
+}
+
+/// Compteurs du serveur depuis son démarrage
+#[derive(Default)]
+pub struct Metrics {
+    counters: Mutex<Counters>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, event: &QueryEvent) {
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        counters.queries += 1;
+        counters.tcp += (event.transport == Transport::Tcp) as u64;
+        *counters.by_transport.entry(event.transport.to_string()).or_default() += 1;
+        counters.cache_hits += event.cache_hit as u64;
+
+        *counters.by_rcode.entry(event.response.rcode().to_string()).or_default() += 1;
+        let qtype = match event.response.questions.first() {
+            Some(question) => RecordType::from(question.qtype).to_string(),
+            None => "NONE".to_string(),
+        };
+        *counters.by_qtype.entry(qtype).or_default() += 1;
+
+        let ip = event.client.ip();
+        if counters.by_client.contains_key(&ip) || counters.by_client.len() < MAX_TRACKED_CLIENTS {
+            *counters.by_client.entry(ip).or_default() += 1;
+        } else {
+            counters.other_clients += 1;
+        }
+
+        let seconds = event.latency.as_secs_f64();
+        counters.latency_sum += seconds;
+        for (bucket, bound) in counters.latency_buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+    }
+
+    /// Page au format texte de Prometheus ; `extra` ajoute des compteurs
+    /// tenus ailleurs (nom, description, valeur)
+    pub fn render(&self, extra: &[(&str, &str, u64)]) -> String {
+        let counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        let mut page = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+            page.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+            for (labels, value) in samples {
+                page.push_str(&format!("{}{} {}\n", name, labels, value));
+            }
+        };
+        let labelled = |label: &str, values: Vec<(String, u64)>| -> Vec<(String, String)> {
+            values
+                .into_iter()
+                .map(|(key, value)| (format!("{{{}=\"{}\"}}", label, escape_label_value(&key)), value.to_string()))
+                .collect()
+        };
+
+        metric("tp7_queries_total", "counter", "Requêtes traitées", vec![(String::new(), counters.queries.to_string())]);
+        metric("tp7_tcp_queries_total", "counter", "Requêtes reçues en TCP", vec![(String::new(), counters.tcp.to_string())]);
+        metric(
+            "tp7_queries_by_transport_total",
+            "counter",
+            "Requêtes par transport (udp, tcp, tls, https)",
+            labelled("transport", counters.by_transport.iter().map(|(transport, n)| (transport.clone(), *n)).collect()),
+        );
+        metric(
+            "tp7_responses_total",
+            "counter",
+            "Réponses par code de retour",
+            labelled("rcode", counters.by_rcode.iter().map(|(rcode, n)| (rcode.clone(), *n)).collect()),
+        );
+        metric(
+            "tp7_queries_by_type_total",
+            "counter",
+            "Requêtes par type demandé",
+            labelled("qtype", counters.by_qtype.iter().map(|(qtype, n)| (qtype.clone(), *n)).collect()),
+        );
+        let mut clients: Vec<(String, u64)> = counters.by_client.iter().map(|(ip, n)| (ip.to_string(), *n)).collect();
+        if counters.other_clients > 0 {
+            clients.push(("other".to_string(), counters.other_clients));
+        }
+        metric("tp7_queries_by_client_total", "counter", "Requêtes par adresse de client", labelled("client", clients));
+        metric(
+            "tp7_cache_answers_total",
+            "counter",
+            "Réponses servies depuis le cache",
+            vec![(String::new(), counters.cache_hits.to_string())],
+        );
+        for (name, help, value) in extra {
+            metric(name, "counter", help, vec![(String::new(), value.to_string())]);
+        }
+
+        let mut histogram: Vec<(String, String)> = LATENCY_BUCKETS
+            .iter()
+            .zip(counters.latency_buckets)
+            .map(|(bound, count)| (format!("_bucket{{le=\"{}\"}}", bound), count.to_string()))
+            .collect();
+        histogram.push(("_bucket{le=\"+Inf\"}".to_string(), counters.queries.to_string()));
+        histogram.push(("_sum".to_string(), format!("{:.6}", counters.latency_sum)));
+        histogram.push(("_count".to_string(), counters.queries.to_string()));
+        metric("tp7_query_duration_seconds", "histogram", "Temps de préparation des réponses", histogram);
+
+        page
+    }
+}
+
+/// Valeur d'étiquette Prometheus : `\`, `"` et les retours à la ligne sont
+/// échappés
+fn escape_label_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Répond à une connexion HTTP : `GET /metrics` renvoie la page produite
+/// par `page`, tout le reste une erreur. Une seule requête par connexion.
+pub async fn serve_http(mut stream: TcpStream, page: impl FnOnce() -> String) -> io::Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = match timeout(HTTP_TIMEOUT, stream.read(&mut buffer)).await {
+            Ok(read) => read?,
+            Err(_) => return Ok(()),
+        };
+        if read == 0 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buffer[..read]);
+        if request.len() > MAX_HTTP_REQUEST {
+            return write_http(&mut stream, "431 Request Header Fields Too Large", "").await;
+        }
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut first_line = request.lines().next().unwrap_or("").split_whitespace();
+    let method = first_line.next().unwrap_or("");
+    let path = first_line.next().unwrap_or("");
+    let path = path.split_once('?').map_or(path, |(path, _)| path);
+
+    match (method, path) {
+        ("GET", "/metrics") => write_http(&mut stream, "200 OK", &page()).await,
+        ("GET", _) => write_http(&mut stream, "404 Not Found", "").await,
+        _ => write_http(&mut stream, "405 Method Not Allowed", "").await,
+    }
+}
+
+async fn write_http(stream: &mut TcpStream, status: &str, body: &str) -> io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
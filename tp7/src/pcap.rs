@@ -0,0 +1,639 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::metrics::Transport;
+
+/// Port DNS, extrait par défaut des captures
+pub const DNS_PORT: u16 = 53;
+
+/// Nombres magiques de pcap (horodatage en microsecondes ou en
+/// nanosecondes), du bloc d'en-tête de section pcapng et de son ordre
+/// d'octets
+const PCAP_MAGIC_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_MAGIC_NANOS: u32 = 0xa1b2_3c4d;
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER_MAGIC: u32 = 0x1a2b_3c4d;
+
+/// Blocs pcapng qui décrivent une interface ou contiennent un paquet
+const PCAPNG_INTERFACE: u32 = 1;
+const PCAPNG_OBSOLETE_PACKET: u32 = 2;
+const PCAPNG_SIMPLE_PACKET: u32 = 3;
+const PCAPNG_ENHANCED_PACKET: u32 = 6;
+
+/// Option `if_tsresol` d'une interface pcapng (microsecondes par défaut)
+const PCAPNG_IF_TSRESOL: u16 = 9;
+
+/// Types de lien reconnus (LINKTYPE_*)
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LOOP: u32 = 108;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+
+/// En-têtes d'extension IPv6 sautés pour atteindre UDP ou TCP
+const IPV6_HOP_BY_HOP: u8 = 0;
+const IPV6_ROUTING: u8 = 43;
+const IPV6_FRAGMENT: u8 = 44;
+const IPV6_DESTINATION: u8 = 60;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_RST: u8 = 0x04;
+const TCP_PSH_ACK: u8 = 0x18;
+
+/// Longueur de capture annoncée par les fichiers écrits (celle de tcpdump)
+const SNAPLEN: u32 = 262_144;
+
+/// Taille maximale des segments TCP écrits : un grand message est découpé
+/// comme sur un vrai lien Ethernet
+const CAPTURE_MSS: usize = 1460;
+
+/// Paquet lu dans une capture
+#[derive(Debug, Clone)]
+pub struct Packet<'a> {
+    /// Instant de capture, depuis l'époque Unix
+    pub timestamp: Duration,
+    /// Type de lien de l'interface (LINKTYPE_*)
+    pub link_type: u32,
+    /// Octets capturés, éventuellement moins que le paquet d'origine
+    pub data: &'a [u8],
+}
+
+/// Message DNS trouvé dans une capture, qui n'est pas forcément valide
+#[derive(Debug, Clone)]
+pub struct CapturedMessage {
+    pub timestamp: Duration,
+    pub source: SocketAddr,
+    pub destination: SocketAddr,
+    /// `Udp` ou `Tcp`, d'après le paquet
+    pub transport: Transport,
+    /// Message sans sa longueur sur deux octets en TCP
+    pub data: Vec<u8>,
+}
+
+/// Lecture des entiers dans l'ordre d'octets de la capture
+#[derive(Debug, Clone, Copy)]
+struct ByteOrder {
+    big_endian: bool,
+}
+
+impl ByteOrder {
+    fn u16(self, bytes: &[u8], at: usize) -> Result<u16, String> {
+        let field = bytes.get(at..at + 2).ok_or("Capture tronquée")?;
+        let field = [field[0], field[1]];
+        Ok(if self.big_endian { u16::from_be_bytes(field) } else { u16::from_le_bytes(field) })
+    }
+
+    fn u32(self, bytes: &[u8], at: usize) -> Result<u32, String> {
+        let field = bytes.get(at..at + 4).ok_or("Capture tronquée")?;
+        let field = [field[0], field[1], field[2], field[3]];
+        Ok(if self.big_endian { u32::from_be_bytes(field) } else { u32::from_le_bytes(field) })
+    }
+}
+
+/// Paquets d'un fichier pcap ou pcapng, reconnu à son nombre magique
+pub fn read_packets(bytes: &[u8]) -> Result<Vec<Packet<'_>>, String> {
+    // Le type du bloc d'en-tête de section pcapng se lit pareil dans les
+    // deux ordres d'octets
+    match (ByteOrder { big_endian: false }).u32(bytes, 0) {
+        Ok(PCAPNG_SECTION_HEADER) => read_pcapng(bytes),
+        Ok(_) => read_pcap(bytes),
+        Err(_) => Err("Fichier trop court pour une capture".to_string()),
+    }
+}
+
+/// Format pcap classique : un en-tête de 24 octets, puis chaque paquet
+/// précédé de son horodatage et de ses longueurs
+fn read_pcap(bytes: &[u8]) -> Result<Vec<Packet<'_>>, String> {
+    let little = ByteOrder { big_endian: false };
+    let (order, nanos) = match little.u32(bytes, 0)? {
+        PCAP_MAGIC_MICROS => (little, false),
+        PCAP_MAGIC_NANOS => (little, true),
+        magic if magic == PCAP_MAGIC_MICROS.swap_bytes() => (ByteOrder { big_endian: true }, false),
+        magic if magic == PCAP_MAGIC_NANOS.swap_bytes() => (ByteOrder { big_endian: true }, true),
+        _ => return Err("Format de capture inconnu (ni pcap ni pcapng)".to_string()),
+    };
+    // Les bits de poids fort du type de lien décrivent la somme de contrôle
+    // des trames, sans intérêt ici
+    let link_type = order.u32(bytes, 20).map_err(|_| "En-tête pcap tronqué")? & 0xffff;
+
+    let mut packets = Vec::new();
+    let mut offset = 24;
+    while offset < bytes.len() {
+        let seconds = order.u32(bytes, offset)?;
+        let fraction = order.u32(bytes, offset + 4)?;
+        let length = order.u32(bytes, offset + 8)? as usize;
+        let data = bytes
+            .get(offset + 16..offset + 16 + length)
+            .ok_or_else(|| format!("Paquet {} tronqué", packets.len() + 1))?;
+        let fraction = if nanos { Duration::from_nanos(fraction as u64) } else { Duration::from_micros(fraction as u64) };
+        packets.push(Packet { timestamp: Duration::from_secs(seconds as u64) + fraction, link_type, data });
+        offset += 16 + length;
+    }
+    Ok(packets)
+}
+
+/// Interface d'une section pcapng
+struct Interface {
+    link_type: u32,
+    /// Unités d'horodatage par seconde
+    ticks_per_second: u64,
+}
+
+/// Format pcapng : une suite de blocs, chacun encadré par sa longueur ;
+/// les paquets désignent l'interface qui les a capturés, décrite plus tôt
+/// dans la même section
+fn read_pcapng(bytes: &[u8]) -> Result<Vec<Packet<'_>>, String> {
+    let mut packets = Vec::new();
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let mut order = ByteOrder { big_endian: false };
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        // Chaque section annonce son ordre d'octets
+        if order.u32(bytes, offset)? == PCAPNG_SECTION_HEADER {
+            let magic = order.u32(bytes, offset + 8)?;
+            if magic == PCAPNG_BYTE_ORDER_MAGIC.swap_bytes() {
+                order.big_endian = !order.big_endian;
+            } else if magic != PCAPNG_BYTE_ORDER_MAGIC {
+                return Err("Section pcapng d'ordre d'octets inconnu".to_string());
+            }
+            interfaces.clear();
+        }
+        let block_type = order.u32(bytes, offset)?;
+        let length = order.u32(bytes, offset + 4)? as usize;
+        if length < 12 || !length.is_multiple_of(4) {
+            return Err(format!("Bloc pcapng de longueur invalide à l'octet {}", offset));
+        }
+        let body = bytes.get(offset + 8..offset + length - 4).ok_or("Bloc pcapng tronqué")?;
+
+        let interface = |id: u32| {
+            let interface = interfaces.get(id as usize).ok_or(format!("Interface pcapng {} inconnue", id))?;
+            Ok::<_, String>((interface.link_type, interface.ticks_per_second))
+        };
+        match block_type {
+            PCAPNG_INTERFACE => {
+                let link_type = order.u16(body, 0)? as u32;
+                let ticks_per_second = timestamp_resolution(body, order)?;
+                interfaces.push(Interface { link_type, ticks_per_second });
+            }
+            PCAPNG_ENHANCED_PACKET | PCAPNG_OBSOLETE_PACKET => {
+                // L'ancien bloc de paquet n'a que deux octets d'interface,
+                // suivis du nombre de paquets perdus
+                let id = if block_type == PCAPNG_ENHANCED_PACKET { order.u32(body, 0)? } else { order.u16(body, 0)? as u32 };
+                let (link_type, ticks_per_second) = interface(id)?;
+                let ticks = (order.u32(body, 4)? as u64) << 32 | order.u32(body, 8)? as u64;
+                let captured = order.u32(body, 12)? as usize;
+                let data = body.get(20..20 + captured).ok_or("Paquet pcapng tronqué")?;
+                packets.push(Packet { timestamp: ticks_to_duration(ticks, ticks_per_second), link_type, data });
+            }
+            PCAPNG_SIMPLE_PACKET => {
+                // Sans horodatage, capturé par la première interface ; les
+                // octets de bourrage sont retirés d'après la longueur d'origine
+                let (link_type, _) = interface(0)?;
+                let original = order.u32(body, 0)? as usize;
+                let data = body.get(4..).ok_or("Paquet pcapng tronqué")?;
+                let data = &data[..original.min(data.len())];
+                packets.push(Packet { timestamp: Duration::ZERO, link_type, data });
+            }
+            // Statistiques, résolution de noms, commentaires...
+            _ => {}
+        }
+        offset += length;
+    }
+    Ok(packets)
+}
+
+/// Option `if_tsresol` d'une interface : une puissance de 10 négative, ou
+/// de 2 si le bit de poids fort est mis
+fn timestamp_resolution(body: &[u8], order: ByteOrder) -> Result<u64, String> {
+    let mut offset = 8;
+    while offset + 4 <= body.len() {
+        let code = order.u16(body, offset)?;
+        let length = order.u16(body, offset + 2)? as usize;
+        if code == 0 {
+            break;
+        }
+        if code == PCAPNG_IF_TSRESOL && length == 1 {
+            let resolution = *body.get(offset + 4).ok_or("Option pcapng tronquée")?;
+            let exponent = (resolution & 0x7f) as u32;
+            let base: u64 = if resolution & 0x80 == 0 { 10 } else { 2 };
+            return base.checked_pow(exponent).ok_or_else(|| format!("Résolution d'horodatage invalide: {}", resolution));
+        }
+        offset += 4 + length.div_ceil(4) * 4;
+    }
+    Ok(1_000_000)
+}
+
+fn ticks_to_duration(ticks: u64, ticks_per_second: u64) -> Duration {
+    let nanos = (ticks % ticks_per_second) as u128 * 1_000_000_000 / ticks_per_second as u128;
+    Duration::new(ticks / ticks_per_second, nanos as u32)
+}
+
+/// Partie UDP ou TCP d'un paquet
+struct Segment<'a> {
+    source: SocketAddr,
+    destination: SocketAddr,
+    protocol: u8,
+    payload: &'a [u8],
+    /// Numéro de séquence et drapeaux d'un segment TCP
+    sequence: u32,
+    flags: u8,
+}
+
+/// Paquet IP porté par une trame, d'après son type de lien
+fn ip_packet(link_type: u32, data: &[u8]) -> Option<&[u8]> {
+    let ethertype = |at: usize| data.get(at..at + 2).map(|field| u16::from_be_bytes([field[0], field[1]]));
+    let (ethertype, offset) = match link_type {
+        // La famille d'adresses des interfaces de bouclage dépend du
+        // système : la version IP suffit
+        LINKTYPE_NULL | LINKTYPE_LOOP => return data.get(4..),
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => return Some(data),
+        LINKTYPE_LINUX_SLL => (ethertype(14)?, 16),
+        LINKTYPE_LINUX_SLL2 => (ethertype(0)?, 20),
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            while matches!(ethertype(offset)?, ETHERTYPE_VLAN | ETHERTYPE_QINQ) {
+                offset += 4;
+            }
+            (ethertype(offset)?, offset + 2)
+        }
+        _ => return None,
+    };
+    matches!(ethertype, ETHERTYPE_IPV4 | ETHERTYPE_IPV6).then(|| data.get(offset..)).flatten()
+}
+
+/// Segment UDP ou TCP d'un paquet IPv4 ou IPv6 ; les fragments sont ignorés
+fn transport_segment(packet: &[u8]) -> Option<Segment<'_>> {
+    let (source, destination, protocol, payload) = match packet.first()? >> 4 {
+        4 => {
+            let header_length = ((packet[0] & 0x0f) as usize) * 4;
+            let total_length = u16::from_be_bytes([*packet.get(2)?, *packet.get(3)?]) as usize;
+            let fragment = u16::from_be_bytes([*packet.get(6)?, *packet.get(7)?]);
+            if fragment & 0x3fff != 0 || header_length < 20 {
+                return None;
+            }
+            let source = Ipv4Addr::from(<[u8; 4]>::try_from(packet.get(12..16)?).ok()?);
+            let destination = Ipv4Addr::from(<[u8; 4]>::try_from(packet.get(16..20)?).ok()?);
+            // La trame peut être bourrée au-delà du paquet
+            let payload = packet.get(header_length..total_length.min(packet.len()))?;
+            (IpAddr::V4(source), IpAddr::V4(destination), packet[9], payload)
+        }
+        6 => {
+            let payload_length = u16::from_be_bytes([*packet.get(4)?, *packet.get(5)?]) as usize;
+            let source = Ipv6Addr::from(<[u8; 16]>::try_from(packet.get(8..24)?).ok()?);
+            let destination = Ipv6Addr::from(<[u8; 16]>::try_from(packet.get(24..40)?).ok()?);
+            let mut next_header = packet[6];
+            let mut payload = packet.get(40..(40 + payload_length).min(packet.len()))?;
+            while matches!(next_header, IPV6_HOP_BY_HOP | IPV6_ROUTING | IPV6_DESTINATION) {
+                let length = (*payload.get(1)? as usize + 1) * 8;
+                next_header = payload[0];
+                payload = payload.get(length..)?;
+            }
+            if next_header == IPV6_FRAGMENT {
+                return None;
+            }
+            (IpAddr::V6(source), IpAddr::V6(destination), next_header, payload)
+        }
+        _ => return None,
+    };
+
+    let port = |at: usize| payload.get(at..at + 2).map(|field| u16::from_be_bytes([field[0], field[1]]));
+    let (source_port, destination_port) = (port(0)?, port(2)?);
+    let (payload, sequence, flags) = match protocol {
+        IPPROTO_UDP => {
+            // Un en-tête UDP tronqué n'a pas de données
+            if payload.len() < 8 {
+                return None;
+            }
+            let length = (port(4)? as usize).clamp(8, payload.len());
+            (&payload[8..length], 0, 0)
+        }
+        IPPROTO_TCP => {
+            let header_length = ((*payload.get(12)? >> 4) as usize) * 4;
+            let sequence = u32::from_be_bytes(payload.get(4..8)?.try_into().ok()?);
+            (payload.get(header_length..)?, sequence, *payload.get(13)?)
+        }
+        _ => return None,
+    };
+    Some(Segment {
+        source: SocketAddr::new(source, source_port),
+        destination: SocketAddr::new(destination, destination_port),
+        protocol,
+        payload,
+        sequence,
+        flags,
+    })
+}
+
+/// Sens d'une connexion TCP en cours de réassemblage
+struct TcpStream {
+    /// Numéro de séquence du prochain octet attendu
+    next_sequence: u32,
+    /// Octets reçus qui ne forment pas encore un message complet
+    buffer: Vec<u8>,
+}
+
+/// Messages DNS des paquets UDP et TCP dont un des ports est dans `ports`,
+/// dans l'ordre de la capture, avec le nombre de paquets ignorés (autres
+/// protocoles ou ports, fragments, liens inconnus).
+///
+/// Les flux TCP sont réassemblés d'après les numéros de séquence : les
+/// retransmissions sont écartées, et un trou dans le flux fait repartir du
+/// segment suivant, quitte à mal découper les messages qui suivent.
+pub fn extract_messages(packets: &[Packet], ports: &[u16]) -> (Vec<CapturedMessage>, usize) {
+    let mut messages = Vec::new();
+    let mut ignored = 0;
+    let mut streams: HashMap<(SocketAddr, SocketAddr), TcpStream> = HashMap::new();
+
+    for packet in packets {
+        let segment = ip_packet(packet.link_type, packet.data).and_then(transport_segment);
+        let Some(segment) = segment.filter(|segment| {
+            ports.contains(&segment.source.port()) || ports.contains(&segment.destination.port())
+        }) else {
+            ignored += 1;
+            continue;
+        };
+        let message = |transport, data: Vec<u8>| CapturedMessage {
+            timestamp: packet.timestamp,
+            source: segment.source,
+            destination: segment.destination,
+            transport,
+            data,
+        };
+
+        if segment.protocol == IPPROTO_UDP {
+            messages.push(message(Transport::Udp, segment.payload.to_vec()));
+            continue;
+        }
+
+        let key = (segment.source, segment.destination);
+        if segment.flags & TCP_SYN != 0 {
+            streams.insert(key, TcpStream { next_sequence: segment.sequence.wrapping_add(1), buffer: Vec::new() });
+        } else if !segment.payload.is_empty() {
+            // Une connexion commencée avant la capture part du premier segment vu
+            let stream = streams
+                .entry(key)
+                .or_insert_with(|| TcpStream { next_sequence: segment.sequence, buffer: Vec::new() });
+            let offset = segment.sequence.wrapping_sub(stream.next_sequence) as i32;
+            let fresh = if offset > 0 {
+                stream.buffer.clear();
+                segment.payload
+            } else {
+                segment.payload.get(offset.unsigned_abs() as usize..).unwrap_or(&[])
+            };
+            stream.buffer.extend_from_slice(fresh);
+            let end = segment.sequence.wrapping_add(segment.payload.len() as u32);
+            if end.wrapping_sub(stream.next_sequence) as i32 > 0 {
+                stream.next_sequence = end;
+            }
+
+            while let [high, low, rest @ ..] = stream.buffer.as_slice() {
+                let length = u16::from_be_bytes([*high, *low]) as usize;
+                if rest.len() < length {
+                    break;
+                }
+                let data = rest[..length].to_vec();
+                stream.buffer.drain(..2 + length);
+                messages.push(message(Transport::Tcp, data));
+            }
+        }
+        if segment.flags & (TCP_FIN | TCP_RST) != 0 {
+            streams.remove(&key);
+        }
+    }
+    (messages, ignored)
+}
+
+/// Capture au format pcap où le serveur écrit chaque message reçu ou envoyé.
+///
+/// Les paquets sont reconstitués sans en-tête de lien (LINKTYPE_RAW) : un
+/// datagramme UDP par message, ou pour les autres transports des segments
+/// TCP où le message est précédé de sa longueur. Les messages de DoT et de
+/// DoH sont écrits en clair, sur les adresses de leur connexion.
+pub struct PcapWriter<W: Write = File> {
+    state: Mutex<CaptureState<W>>,
+}
+
+struct CaptureState<W> {
+    writer: W,
+    /// Prochain numéro de séquence de chaque sens des connexions TCP
+    sequences: HashMap<(SocketAddr, SocketAddr), u32>,
+}
+
+impl PcapWriter {
+    /// Crée (ou vide) le fichier et écrit l'en-tête
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Self::new(File::create(path)?)
+    }
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Capture écrite dans `writer`, en commençant par l'en-tête
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(&PCAP_MAGIC_MICROS.to_le_bytes());
+        header.extend_from_slice(&2u16.to_le_bytes());
+        header.extend_from_slice(&4u16.to_le_bytes());
+        header.extend_from_slice(&[0; 8]);
+        header.extend_from_slice(&SNAPLEN.to_le_bytes());
+        header.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+        writer.write_all(&header)?;
+        Ok(Self { state: Mutex::new(CaptureState { writer, sequences: HashMap::new() }) })
+    }
+
+    /// Rend la destination de la capture, par exemple les octets écrits
+    /// dans un `Vec<u8>`
+    pub fn into_inner(self) -> W {
+        self.state.into_inner().unwrap_or_else(|e| e.into_inner()).writer
+    }
+
+    /// Ajoute un message DNS de `source` à `destination`. Chaque paquet est
+    /// écrit d'un bloc, pour ne pas se mélanger à ceux des autres tâches.
+    pub fn write_message(&self, transport: Transport, source: SocketAddr, destination: SocketAddr, message: &[u8]) -> io::Result<()> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        let mut packets = Vec::new();
+        if transport == Transport::Udp {
+            let mut datagram = Vec::with_capacity(8 + message.len());
+            datagram.extend_from_slice(&source.port().to_be_bytes());
+            datagram.extend_from_slice(&destination.port().to_be_bytes());
+            datagram.extend_from_slice(&segment_length(8 + message.len())?.to_be_bytes());
+            datagram.extend_from_slice(&[0, 0]);
+            datagram.extend_from_slice(message);
+            packets.push(build_ip_packet(source.ip(), destination.ip(), IPPROTO_UDP, datagram, 6)?);
+        } else {
+            let mut framed = Vec::with_capacity(2 + message.len());
+            framed.extend_from_slice(&segment_length(message.len())?.to_be_bytes());
+            framed.extend_from_slice(message);
+
+            // L'accusé de réception reprend ce que l'autre sens a envoyé
+            let acknowledged = state.sequences.get(&(destination, source)).copied().unwrap_or(0);
+            let sequence = state.sequences.entry((source, destination)).or_insert(1);
+            for chunk in framed.chunks(CAPTURE_MSS) {
+                let mut segment = Vec::with_capacity(20 + chunk.len());
+                segment.extend_from_slice(&source.port().to_be_bytes());
+                segment.extend_from_slice(&destination.port().to_be_bytes());
+                segment.extend_from_slice(&sequence.to_be_bytes());
+                segment.extend_from_slice(&acknowledged.to_be_bytes());
+                segment.extend_from_slice(&[5 << 4, TCP_PSH_ACK, 0xff, 0xff, 0, 0, 0, 0]);
+                segment.extend_from_slice(chunk);
+                packets.push(build_ip_packet(source.ip(), destination.ip(), IPPROTO_TCP, segment, 16)?);
+                *sequence = sequence.wrapping_add(chunk.len() as u32);
+            }
+        }
+
+        let mut records = Vec::new();
+        for packet in packets {
+            records.extend_from_slice(&(timestamp.as_secs() as u32).to_le_bytes());
+            records.extend_from_slice(&timestamp.subsec_micros().to_le_bytes());
+            records.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            records.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            records.extend_from_slice(&packet);
+        }
+        state.writer.write_all(&records)
+    }
+
+    /// Oublie les numéros de séquence d'une connexion TCP fermée
+    pub fn close_connection(&self, client: SocketAddr, server: SocketAddr) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.sequences.remove(&(client, server));
+        state.sequences.remove(&(server, client));
+    }
+}
+
+fn segment_length(length: usize) -> io::Result<u16> {
+    u16::try_from(length).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Message trop grand pour la capture"))
+}
+
+/// Paquet IP autour d'un segment UDP ou TCP, dont la somme de contrôle (à
+/// l'octet `checksum_at`) est calculée ; une adresse IPv4 face à une
+/// adresse IPv6 est convertie en adresse IPv4 mappée
+fn build_ip_packet(source: IpAddr, destination: IpAddr, protocol: u8, mut segment: Vec<u8>, checksum_at: usize) -> io::Result<Vec<u8>> {
+    let (source, destination) = match (source, destination) {
+        (IpAddr::V4(source), IpAddr::V6(destination)) => (IpAddr::V6(source.to_ipv6_mapped()), IpAddr::V6(destination)),
+        (IpAddr::V6(source), IpAddr::V4(destination)) => (IpAddr::V6(source), IpAddr::V6(destination.to_ipv6_mapped())),
+        addresses => addresses,
+    };
+    let length = segment.len();
+
+    let mut pseudo_header = Vec::with_capacity(40);
+    let mut packet = Vec::with_capacity(40 + length);
+    match (source, destination) {
+        (IpAddr::V4(source), IpAddr::V4(destination)) => {
+            pseudo_header.extend_from_slice(&source.octets());
+            pseudo_header.extend_from_slice(&destination.octets());
+            pseudo_header.extend_from_slice(&[0, protocol]);
+            pseudo_header.extend_from_slice(&segment_length(length)?.to_be_bytes());
+
+            packet.extend_from_slice(&[0x45, 0]);
+            packet.extend_from_slice(&segment_length(20 + length)?.to_be_bytes());
+            // Identifiant nul, ne pas fragmenter, TTL 64
+            packet.extend_from_slice(&[0, 0, 0x40, 0, 64, protocol, 0, 0]);
+            packet.extend_from_slice(&source.octets());
+            packet.extend_from_slice(&destination.octets());
+            let checksum = internet_checksum(&[&packet]);
+            packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+        }
+        (IpAddr::V6(source), IpAddr::V6(destination)) => {
+            pseudo_header.extend_from_slice(&source.octets());
+            pseudo_header.extend_from_slice(&destination.octets());
+            pseudo_header.extend_from_slice(&(length as u32).to_be_bytes());
+            pseudo_header.extend_from_slice(&[0, 0, 0, protocol]);
+
+            packet.extend_from_slice(&[0x60, 0, 0, 0]);
+            packet.extend_from_slice(&segment_length(length)?.to_be_bytes());
+            packet.extend_from_slice(&[protocol, 64]);
+            packet.extend_from_slice(&source.octets());
+            packet.extend_from_slice(&destination.octets());
+        }
+        _ => unreachable!("familles d'adresses accordées plus haut"),
+    }
+
+    // Une somme nulle s'écrit 0xffff en UDP, où 0 veut dire « absente »
+    let checksum = match internet_checksum(&[&pseudo_header, &segment]) {
+        0 if protocol == IPPROTO_UDP => 0xffff,
+        checksum => checksum,
+    };
+    segment[checksum_at..checksum_at + 2].copy_from_slice(&checksum.to_be_bytes());
+    packet.extend_from_slice(&segment);
+    Ok(packet)
+}
+
+/// Somme de contrôle d'Internet (RFC 1071) de morceaux de longueur paire,
+/// sauf le dernier
+fn internet_checksum(chunks: &[&[u8]]) -> u16 {
+    let mut sum: u32 = 0;
+    for chunk in chunks {
+        for pair in chunk.chunks(2) {
+            sum += u16::from_be_bytes([pair[0], pair.get(1).copied().unwrap_or(0)]) as u32;
+        }
+    }
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Recopie des paquets en pcapng, une section et une interface en
+/// nanosecondes, pour relire la même capture dans l'autre format
+pub fn to_pcapng(packets: &[Packet]) -> Vec<u8> {
+    let block = |block_type: u32, body: &[u8]| {
+        let length = 12 + body.len().div_ceil(4) * 4;
+        let mut block = Vec::with_capacity(length);
+        block.extend_from_slice(&block_type.to_le_bytes());
+        block.extend_from_slice(&(length as u32).to_le_bytes());
+        block.extend_from_slice(body);
+        block.resize(length - 4, 0);
+        block.extend_from_slice(&(length as u32).to_le_bytes());
+        block
+    };
+
+    let mut section = Vec::new();
+    section.extend_from_slice(&PCAPNG_BYTE_ORDER_MAGIC.to_le_bytes());
+    section.extend_from_slice(&1u16.to_le_bytes());
+    section.extend_from_slice(&0u16.to_le_bytes());
+    section.extend_from_slice(&u64::MAX.to_le_bytes());
+    let mut bytes = block(PCAPNG_SECTION_HEADER, &section);
+
+    let link_type = packets.first().map_or(LINKTYPE_RAW, |packet| packet.link_type);
+    let mut interface = Vec::new();
+    interface.extend_from_slice(&(link_type as u16).to_le_bytes());
+    interface.extend_from_slice(&0u16.to_le_bytes());
+    interface.extend_from_slice(&SNAPLEN.to_le_bytes());
+    // if_tsresol = 9, puis la fin des options
+    interface.extend_from_slice(&PCAPNG_IF_TSRESOL.to_le_bytes());
+    interface.extend_from_slice(&1u16.to_le_bytes());
+    interface.extend_from_slice(&[9, 0, 0, 0]);
+    interface.extend_from_slice(&[0; 4]);
+    bytes.extend(block(PCAPNG_INTERFACE, &interface));
+
+    for packet in packets {
+        let nanos = packet.timestamp.as_nanos() as u64;
+        let mut body = Vec::new();
+        body.extend_from_slice(&0u32.to_le_bytes());
+        body.extend_from_slice(&((nanos >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(nanos as u32).to_le_bytes());
+        body.extend_from_slice(&(packet.data.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(packet.data.len() as u32).to_le_bytes());
+        body.extend_from_slice(packet.data);
+        bytes.extend(block(PCAPNG_ENHANCED_PACKET, &body));
+    }
+    bytes
+}
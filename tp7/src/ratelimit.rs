@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::time::Instant;
+use crate::acl::AddressRange;
+
+/// Les clients sont regroupés par réseau : une seule adresse par /24 en
+/// IPv4, par /56 en IPv6 (taille courante d'un accès résidentiel)
+const IPV4_PREFIX_LEN: u32 = 24;
+const IPV6_PREFIX_LEN: u32 = 56;
+
+/// Au-delà de ce nombre de réseaux suivis, ceux dont le seau est plein
+/// sont oubliés, puis les moins récemment vus jusqu'à n'en garder que
+/// `RETAINED_BUCKETS` : le nettoyage, qui parcourt toute la table, n'a lieu
+/// qu'une fois tous les `MAX_BUCKETS - RETAINED_BUCKETS` nouveaux réseaux
+const MAX_BUCKETS: usize = 65536;
+const RETAINED_BUCKETS: usize = MAX_BUCKETS / 4 * 3;
+
+/// Paramètres de la limitation du débit des réponses
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Réponses par seconde accordées à chaque réseau
+    pub rate: f64,
+    /// Réponses pouvant partir d'un coup après une période calme
+    pub burst: f64,
+    /// Une réponse limitée sur `slip` part tronquée (TC), pour qu'un vrai
+    /// client puisse reposer sa question en TCP ; 0 : aucune
+    pub slip: u32,
+    /// Clients jamais limités
+    pub exempt: Vec<AddressRange>,
+}
+
+/// Sort d'une réponse UDP
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Allow,
+    Drop,
+    /// Réponse vide tronquée à la place de la vraie
+    Slip,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// Réponses limitées depuis que le seau est vide
+    limited: u64,
+}
+
+/// Limitation du débit des réponses UDP par seau à jetons, pour que le
+/// serveur ne serve pas d'amplificateur contre une adresse usurpée
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+    dropped: AtomicU64,
+    slipped: AtomicU64,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+            dropped: AtomicU64::new(0),
+            slipped: AtomicU64::new(0),
+        }
+    }
+
+    /// Décide du sort d'une réponse à `addr` et consomme un jeton
+    pub fn check(&self, addr: IpAddr) -> Verdict {
+        if self.config.exempt.iter().any(|range| range.contains(addr)) {
+            return Verdict::Allow;
+        }
+
+        let now = Instant::now();
+        let network = client_network(addr);
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+
+        if buckets.len() >= MAX_BUCKETS && !buckets.contains_key(&network) {
+            self.evict(&mut buckets, now);
+        }
+
+        let bucket = buckets.entry(network).or_insert(Bucket { tokens: self.config.burst, updated: now, limited: 0 });
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.config.rate).min(self.config.burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            bucket.limited = 0;
+            return Verdict::Allow;
+        }
+
+        bucket.limited += 1;
+        if bucket.limited == 1 {
+            println!("Limite de débit atteinte pour {}/{}", network, prefix_len(network));
+        }
+        let slip = self.config.slip as u64;
+        if slip > 0 && bucket.limited.is_multiple_of(slip) {
+            self.slipped.fetch_add(1, Ordering::Relaxed);
+            Verdict::Slip
+        } else {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            Verdict::Drop
+        }
+    }
+
+    /// Ramène la table sous `RETAINED_BUCKETS` réseaux : d'abord ceux dont
+    /// le seau est de nouveau plein (ils seraient recréés à l'identique),
+    /// puis les moins récemment vus
+    fn evict(&self, buckets: &mut HashMap<IpAddr, Bucket>, now: Instant) {
+        let RateLimitConfig { rate, burst, .. } = self.config;
+        buckets.retain(|_, bucket| bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate < burst);
+        if buckets.len() <= RETAINED_BUCKETS {
+            return;
+        }
+
+        let mut updated: Vec<Instant> = buckets.values().map(|bucket| bucket.updated).collect();
+        let excess = buckets.len() - RETAINED_BUCKETS;
+        let (_, &mut cutoff, _) = updated.select_nth_unstable(excess - 1);
+        buckets.retain(|_, bucket| bucket.updated > cutoff);
+    }
+
+    /// Compteurs (réponses supprimées, réponses tronquées) depuis le démarrage
+    pub fn stats(&self) -> (u64, u64) {
+        (self.dropped.load(Ordering::Relaxed), self.slipped.load(Ordering::Relaxed))
+    }
+}
+
+/// Réseau auquel `addr` est rattachée pour la limitation
+fn client_network(addr: IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V4(addr) => {
+            let mask = u32::MAX << (32 - IPV4_PREFIX_LEN);
+            IpAddr::V4((u32::from(addr) & mask).into())
+        }
+        IpAddr::V6(addr) => {
+            let mask = u128::MAX << (128 - IPV6_PREFIX_LEN);
+            IpAddr::V6((u128::from(addr) & mask).into())
+        }
+    }
+}
+
+fn prefix_len(addr: IpAddr) -> u32 {
+    if addr.is_ipv4() { IPV4_PREFIX_LEN } else { IPV6_PREFIX_LEN }
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use tokio::time::Duration;
+use crate::client::{ClientError, DnsClient};
+use crate::dns_message::{RecordData, RecordType, ResponseCode, name_labels};
+
+/// Fichiers de configuration du système
+pub const RESOLV_CONF: &str = "/etc/resolv.conf";
+pub const HOSTS: &str = "/etc/hosts";
+
+/// Port des serveurs de `nameserver` donnés par leur seule adresse
+const DNS_PORT: u16 = 53;
+
+/// Nombre de serveurs retenus, comme la libc (MAXNS)
+const MAX_NAMESERVERS: usize = 3;
+
+/// Bornes des options, celles de resolv.conf(5)
+const MAX_NDOTS: usize = 15;
+const MAX_TIMEOUT: u64 = 30;
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Configuration au format resolv.conf(5)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvConf {
+    /// Serveurs interrogés à tour de rôle ; le serveur local sans `nameserver`
+    pub nameservers: Vec<SocketAddr>,
+    /// Domaines ajoutés aux noms relatifs (`search`, ou `domain`)
+    pub search: Vec<String>,
+    /// Nombre de points à partir duquel un nom est d'abord essayé tel quel
+    pub ndots: usize,
+    /// Délai d'attente du premier essai
+    pub timeout: Duration,
+    /// Nombre total d'essais par requête
+    pub attempts: u32,
+}
+
+impl Default for ResolvConf {
+    fn default() -> Self {
+        Self {
+            nameservers: vec![SocketAddr::from(([127, 0, 0, 1], DNS_PORT))],
+            search: Vec::new(),
+            ndots: 1,
+            timeout: Duration::from_secs(5),
+            attempts: 2,
+        }
+    }
+}
+
+impl ResolvConf {
+    /// Lit les lignes `nameserver`, `search`, `domain` et `options` ; les
+    /// autres, et les valeurs invalides, sont ignorées comme par la libc.
+    ///
+    /// En plus d'une adresse, `nameserver` accepte `adresse:port` (ou
+    /// `[adresse]:port` en IPv6) pour joindre un serveur de test.
+    pub fn parse(text: &str) -> Self {
+        let mut config = Self { nameservers: Vec::new(), ..Self::default() };
+        for line in text.lines() {
+            let line = line.split(['#', ';']).next().unwrap_or("");
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("nameserver") => {
+                    let server = fields.next().and_then(|server| {
+                        server.parse::<SocketAddr>().ok().or_else(|| Some(SocketAddr::new(server.parse().ok()?, DNS_PORT)))
+                    });
+                    if let Some(server) = server
+                        && config.nameservers.len() < MAX_NAMESERVERS
+                    {
+                        config.nameservers.push(server);
+                    }
+                }
+                // La dernière des lignes `search` et `domain` l'emporte
+                Some("search") => config.search = fields.filter_map(canonical_name).collect(),
+                Some("domain") => config.search = fields.next().and_then(canonical_name).into_iter().collect(),
+                Some("options") => {
+                    for option in fields {
+                        let (name, value) = option.split_once(':').unwrap_or((option, ""));
+                        match (name, value.parse::<u64>()) {
+                            ("ndots", Ok(ndots)) => config.ndots = (ndots as usize).min(MAX_NDOTS),
+                            ("timeout", Ok(seconds)) => config.timeout = Duration::from_secs(seconds.clamp(1, MAX_TIMEOUT)),
+                            ("attempts", Ok(attempts)) => config.attempts = (attempts as u32).clamp(1, MAX_ATTEMPTS),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if config.nameservers.is_empty() {
+            config.nameservers = Self::default().nameservers;
+        }
+        config
+    }
+
+    /// Lit un fichier ; un fichier absent donne la configuration par défaut
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e).into()),
+        }
+    }
+
+    /// Noms à essayer pour `name`, dans l'ordre : un nom terminé par un
+    /// point est absolu ; un nom d'au moins `ndots` points est essayé tel
+    /// quel avant les domaines de recherche, un nom plus court après
+    pub fn candidates(&self, name: &str) -> Vec<String> {
+        if let Some(absolute) = name.strip_suffix('.') {
+            return vec![absolute.to_string()];
+        }
+        let searched = self.search.iter().map(|domain| format!("{}.{}", name, domain));
+        if name.matches('.').count() >= self.ndots {
+            std::iter::once(name.to_string()).chain(searched).collect()
+        } else {
+            searched.chain(std::iter::once(name.to_string())).collect()
+        }
+    }
+}
+
+/// Nom sans point final, en minuscules ; `None` s'il est invalide
+fn canonical_name(name: &str) -> Option<String> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    name_labels(name).ok().filter(|labels| !labels.is_empty())?;
+    Some(name.to_ascii_lowercase())
+}
+
+/// Table au format hosts(5) : une adresse suivie de ses noms par ligne
+#[derive(Debug, Clone, Default)]
+pub struct Hosts {
+    /// Adresses par nom en minuscules, dans l'ordre du fichier
+    addresses: HashMap<String, Vec<IpAddr>>,
+}
+
+impl Hosts {
+    pub fn parse(text: &str) -> Self {
+        let mut hosts = Self::default();
+        for line in text.lines() {
+            let line = line.split_once('#').map_or(line, |(line, _)| line);
+            let mut fields = line.split_whitespace();
+            let Some(ip) = fields.next().and_then(|ip| ip.parse::<IpAddr>().ok()) else {
+                continue;
+            };
+            for name in fields.filter_map(canonical_name) {
+                let addresses = hosts.addresses.entry(name).or_default();
+                if !addresses.contains(&ip) {
+                    addresses.push(ip);
+                }
+            }
+        }
+        hosts
+    }
+
+    /// Lit un fichier ; un fichier absent donne une table vide
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e).into()),
+        }
+    }
+
+    /// Adresses de `name` (sans distinction de casse, point final ignoré)
+    pub fn lookup(&self, name: &str) -> &[IpAddr] {
+        let name = name.strip_suffix('.').unwrap_or(name);
+        self.addresses.get(&name.to_ascii_lowercase()).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Résolveur de noms en adresses, à la manière de `getaddrinfo` : la table
+/// hosts d'abord, puis les serveurs de la configuration avec les domaines
+/// de recherche
+pub struct StubResolver {
+    config: ResolvConf,
+    hosts: Hosts,
+    client: DnsClient,
+}
+
+impl StubResolver {
+    pub async fn new(config: ResolvConf, hosts: Hosts) -> Result<Self, ClientError> {
+        let mut client = DnsClient::with_servers(config.nameservers.clone()).await?;
+        client.set_verbose(false);
+        client.set_timeout(config.timeout);
+        client.set_retries(config.attempts.saturating_sub(1));
+        Ok(Self { config, hosts, client })
+    }
+
+    /// Résolveur configuré par des fichiers aux formats resolv.conf et
+    /// hosts, `RESOLV_CONF` et `HOSTS` pour faire comme le système
+    pub async fn from_files(resolv_conf: &Path, hosts: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let config = ResolvConf::load(resolv_conf)?;
+        let hosts = Hosts::load(hosts)?;
+        Ok(Self::new(config, hosts).await?)
+    }
+
+    /// Toutes les adresses de `name`, IPv4 puis IPv6 : celles de la table
+    /// hosts si elle le connaît, sinon celles du premier nom candidat qui en
+    /// a. Une adresse littérale est rendue telle quelle.
+    pub async fn lookup_ip(&self, name: &str) -> Result<Vec<IpAddr>, ClientError> {
+        if let Ok(ip) = name.parse::<IpAddr>() {
+            return Ok(vec![ip]);
+        }
+        let known = self.hosts.lookup(name);
+        if !known.is_empty() {
+            return Ok(known.to_vec());
+        }
+        name_labels(name.strip_suffix('.').unwrap_or(name)).map_err(ClientError::InvalidName)?;
+
+        // Un nom sans adresse passe au candidat suivant ; une panne d'un
+        // serveur aussi, mais elle est rendue si aucun candidat n'aboutit
+        let mut failure = ClientError::Rcode(ResponseCode::NXDomain);
+        for candidate in self.config.candidates(name) {
+            let (v4, v6) = tokio::join!(
+                self.addresses(&candidate, RecordType::A),
+                self.addresses(&candidate, RecordType::AAAA),
+            );
+            let mut addresses = Vec::new();
+            for result in [v4, v6] {
+                match result {
+                    Ok(found) => addresses.extend(found),
+                    Err(ClientError::Rcode(ResponseCode::NXDomain) | ClientError::NoAnswer) => {}
+                    Err(e @ (ClientError::Rcode(_) | ClientError::Malformed(_) | ClientError::Mismatch)) => failure = e,
+                    Err(e) => return Err(e),
+                }
+            }
+            if !addresses.is_empty() {
+                return Ok(addresses);
+            }
+        }
+        Err(failure)
+    }
+
+    /// Adresses du type `qtype` de `name`, au bout d'éventuels CNAME
+    async fn addresses(&self, name: &str, qtype: RecordType) -> Result<Vec<IpAddr>, ClientError> {
+        let records = self.client.lookup(name, qtype).await?;
+        Ok(records
+            .into_iter()
+            .filter_map(|record| match record {
+                RecordData::A(ip) => Some(IpAddr::V4(ip)),
+                RecordData::AAAA(ip) => Some(IpAddr::V6(ip)),
+                _ => None,
+            })
+            .collect())
+    }
+}
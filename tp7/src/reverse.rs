@@ -0,0 +1,55 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Nom de la recherche inverse d'une adresse (RFC 1035 §3.5, RFC 3596 §2.5),
+/// sans point final
+pub fn reverse_name(addr: IpAddr) -> String {
+    match addr {
+        IpAddr::V4(addr) => {
+            let [a, b, c, d] = addr.octets();
+            format!("{}.{}.{}.{}.in-addr.arpa", d, c, b, a)
+        }
+        IpAddr::V6(addr) => {
+            let mut labels: Vec<String> = addr
+                .octets()
+                .iter()
+                .flat_map(|byte| [byte >> 4, byte & 0x0F])
+                .map(|nibble| format!("{:x}", nibble))
+                .collect();
+            labels.reverse();
+            format!("{}.ip6.arpa", labels.join("."))
+        }
+    }
+}
+
+/// Adresse désignée par un nom `in-addr.arpa` ou `ip6.arpa` complet ; les
+/// noms partiels (réseaux) et les autres noms donnent `None`
+pub fn parse_reverse_name(name: &str) -> Option<IpAddr> {
+    let name = name.to_ascii_lowercase();
+
+    if let Some(labels) = name.strip_suffix(".in-addr.arpa") {
+        let mut octets: Vec<u8> = labels.split('.').map(|label| label.parse().ok()).collect::<Option<_>>()?;
+        octets.reverse();
+        let octets: [u8; 4] = octets.try_into().ok()?;
+        return Some(IpAddr::V4(Ipv4Addr::from(octets)));
+    }
+
+    if let Some(labels) = name.strip_suffix(".ip6.arpa") {
+        let nibbles: Vec<u8> = labels
+            .split('.')
+            .map(|label| match label.len() {
+                1 => u8::from_str_radix(label, 16).ok(),
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+        if nibbles.len() != 32 {
+            return None;
+        }
+        let mut octets = [0u8; 16];
+        for (index, pair) in nibbles.rchunks(2).enumerate() {
+            octets[index] = pair[1] << 4 | pair[0];
+        }
+        return Some(IpAddr::V6(Ipv6Addr::from(octets)));
+    }
+
+    None
+}
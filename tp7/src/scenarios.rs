@@ -0,0 +1,632 @@
+use std::fs;
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::Semaphore;
+use tokio::time::{Duration, Instant, timeout};
+use tp7::client::bind_udp_for;
+use tp7::dns_message::{CLASS_ANY, CLASS_NONE, DnsAnswer, DnsMessage, RecordData, RecordType, ResponseCode};
+use tp7::dnssec::{Algorithm, SigningKey, ds_for, load_trust_anchors};
+use tp7::doh::{self, Method};
+use tp7::metrics::Transport;
+use tp7::pcap::{DNS_PORT, PcapWriter, extract_messages, read_packets, to_pcapng};
+use tp7::transfer::Transfer;
+use tp7::{ClientError, DnsClient, Protocol, ResolvConf, StubResolver};
+
+// Fonction utilitaire pour les tests
+pub(crate) async fn test_client(server_addr: SocketAddr) -> Result<(), Box<dyn std::error::Error>> {
+    let client = DnsClient::new(server_addr).await?;
+    
+    println!("=== Test du client DNS ({}) ===", server_addr);
+    
+    // www et alias passent par des CNAME, host.dev par un joker, loop1 par
+    // une boucle de CNAME qui doit échouer proprement
+    let domains = vec![
+        "example.com",
+        "google.com",
+        "github.com",
+        "www.example.com",
+        "alias.example.com",
+        "Example.COM",
+        "host.dev.example.com",
+        "loop1.example.com",
+    ];
+    
+    for domain in domains {
+        match client.resolve(domain).await {
+            Ok(ip) => println!("{} -> {}", domain, ip),
+            Err(e) => println!("Erreur pour {}: {}", domain, e),
+        }
+    }
+    
+    // big.example.com dépasse 512 octets : la réponse tient dans la taille
+    // annoncée en EDNS
+    let lookups = [
+        ("example.com", RecordType::MX),
+        ("localhost", RecordType::AAAA),
+        ("big.example.com", RecordType::TXT),
+    ];
+    for (domain, qtype) in lookups {
+        match client.lookup(domain, qtype).await {
+            Ok(records) => {
+                for record in records {
+                    println!("{} {} {}", domain, qtype, record);
+                }
+            }
+            Err(e) => println!("Erreur pour {} ({}): {}", domain, qtype, e),
+        }
+    }
+    
+    // Recherches inverses, synthétisées à partir des A et AAAA
+    for ip in ["93.184.216.34", "127.0.0.1", "::1"] {
+        match client.reverse(ip.parse()?).await {
+            Ok(name) => println!("{} -> {}", ip, name),
+            Err(e) => println!("Erreur pour {}: {}", ip, e),
+        }
+    }
+    
+    // Sans EDNS, la même réponse est tronquée et reposée en TCP
+    let mut client = client;
+    client.set_edns_payload_size(None);
+    match client.lookup("big.example.com", RecordType::TXT).await {
+        Ok(records) => println!("big.example.com TXT: {} enregistrements sans EDNS", records.len()),
+        Err(e) => println!("Erreur pour big.example.com sans EDNS: {}", e),
+    }
+    
+    Ok(())
+}
+
+/// Scénario de mise à jour dynamique sur la zone de test (racine) : ajout
+/// d'un nom, rejet d'un second ajout par prérequis, puis suppression
+pub(crate) async fn test_update(server_addr: SocketAddr) -> Result<(), Box<dyn std::error::Error>> {
+    let client = DnsClient::new(server_addr).await?;
+    let name = "dyn.example.com";
+    let record = DnsAnswer::with_ttl(name.to_string(), 60, RecordData::A("10.0.0.1".parse()?));
+    // Prérequis : le nom ne porte encore aucun enregistrement
+    let unused = DnsAnswer::empty(name.to_string(), RecordType::ANY, CLASS_NONE);
+    
+    println!("=== Test des mises à jour dynamiques ({}) ===", server_addr);
+    
+    match client.update("", vec![unused.clone()], vec![record.clone()]).await {
+        Ok(()) => println!("Ajout de {}: accepté", name),
+        Err(e) => println!("Ajout de {}: {}", name, e),
+    }
+    match client.resolve(name).await {
+        Ok(ip) => println!("{} -> {}", name, ip),
+        Err(e) => println!("Erreur pour {}: {}", name, e),
+    }
+    
+    match client.update("", vec![unused], vec![record]).await {
+        Ok(()) => println!("Second ajout de {}: accepté (inattendu)", name),
+        Err(e) => println!("Second ajout de {}: {} (attendu: YXDOMAIN)", name, e),
+    }
+    
+    let delete = DnsAnswer::empty(name.to_string(), RecordType::ANY, CLASS_ANY);
+    match client.update("", vec![], vec![delete]).await {
+        Ok(()) => println!("Suppression de {}: acceptée", name),
+        Err(e) => println!("Suppression de {}: {}", name, e),
+    }
+    match client.resolve(name).await {
+        Ok(ip) => println!("{} -> {} (inattendu)", name, ip),
+        Err(e) => println!("Erreur pour {}: {} (attendu: NXDOMAIN)", name, e),
+    }
+    
+    Ok(())
+}
+
+/// Scénario de transfert de zone : un nom ajouté puis retiré sur le primaire
+/// doit apparaître puis disparaître sur le secondaire, prévenu par NOTIFY.
+/// Le primaire, qui n'autorise les transferts que depuis 127.0.0.1, doit
+/// refuser celui demandé depuis ::1
+pub(crate) async fn test_transfer(primary: SocketAddr, secondary: SocketAddr) -> Result<(), Box<dyn std::error::Error>> {
+    let outsider = DnsClient::new(SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), primary.port())).await?;
+    let primary = DnsClient::new(primary).await?;
+    let secondary_client = DnsClient::new(secondary).await?;
+    let name = "xfr.example.com";
+    // Laisser au secondaire le temps de transférer la zone
+    let wait = || tokio::time::sleep(Duration::from_millis(300));
+    
+    println!("=== Test des transferts de zone (secondaire {}) ===", secondary);
+    
+    match secondary_client.resolve("example.com").await {
+        Ok(ip) => println!("example.com -> {} (via le secondaire)", ip),
+        Err(e) => println!("Erreur pour example.com sur le secondaire: {}", e),
+    }
+    
+    let record = DnsAnswer::with_ttl(name.to_string(), 60, RecordData::A("10.0.0.2".parse()?));
+    primary.update("", vec![], vec![record]).await?;
+    wait().await;
+    match secondary_client.resolve(name).await {
+        Ok(ip) => println!("{} -> {} (via le secondaire)", name, ip),
+        Err(e) => println!("Erreur pour {} sur le secondaire: {}", name, e),
+    }
+    
+    primary.update("", vec![], vec![DnsAnswer::empty(name.to_string(), RecordType::ANY, CLASS_ANY)]).await?;
+    wait().await;
+    match secondary_client.resolve(name).await {
+        Ok(ip) => println!("{} -> {} (inattendu)", name, ip),
+        Err(e) => println!("Erreur pour {} sur le secondaire: {} (attendu: NXDOMAIN)", name, e),
+    }
+    
+    // Le transfert complet est aussi possible directement
+    match secondary_client.transfer("", None).await {
+        Ok(Transfer::Full(records)) => println!("AXFR du secondaire: {} enregistrements", records.len()),
+        Ok(other) => println!("AXFR du secondaire: réponse inattendue {:?}", other),
+        Err(e) => println!("AXFR du secondaire: {}", e),
+    }
+    match outsider.transfer("", None).await {
+        Err(ClientError::Rcode(ResponseCode::Refused)) => println!("AXFR hors de la liste d'accès: REFUSED"),
+        Ok(_) => return Err("AXFR accepté hors de la liste d'accès".into()),
+        Err(e) => return Err(format!("AXFR hors de la liste d'accès: {}", e).into()),
+    }
+    
+    Ok(())
+}
+
+/// Test de charge : envoie `total` requêtes A au serveur, dont au plus
+/// `concurrency` en parallèle, puis affiche le débit et le nombre d'échecs ;
+/// une seule requête sans réponse fait échouer le test
+pub(crate) async fn load_test(server_addr: SocketAddr, total: usize, concurrency: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let domains = ["example.com", "google.com", "github.com", "localhost", "inconnu.example.com"];
+    let slots = Arc::new(Semaphore::new(concurrency));
+    let answered = Arc::new(AtomicUsize::new(0));
+    let failed = Arc::new(AtomicUsize::new(0));
+    
+    println!("=== Test de charge: {} requêtes, {} en parallèle ===", total, concurrency);
+    let start = Instant::now();
+    
+    let mut tasks = Vec::with_capacity(total);
+    for i in 0..total {
+        let permit = Arc::clone(&slots).acquire_owned().await?;
+        let domain = domains[i % domains.len()];
+        let answered = Arc::clone(&answered);
+        let failed = Arc::clone(&failed);
+        
+        // Un socket par requête, sans passer par DnsClient pour ne pas
+        // afficher chaque échange
+        tasks.push(tokio::spawn(async move {
+            let exchange = async {
+                let socket = bind_udp_for(server_addr).await?;
+                let query = DnsMessage::new_typed_query(i as u16, domain.to_string(), RecordType::A);
+                socket.send_to(&query.to_bytes(), server_addr).await?;
+                
+                let mut buffer = [0u8; 512];
+                let size = socket.recv(&mut buffer).await?;
+                let id = DnsMessage::from_bytes(&buffer[..size]).map(|response| response.header.id);
+                Ok::<_, std::io::Error>(id.ok() == Some(i as u16))
+            };
+            
+            match timeout(Duration::from_secs(5), exchange).await {
+                Ok(Ok(true)) => answered.fetch_add(1, Ordering::Relaxed),
+                _ => failed.fetch_add(1, Ordering::Relaxed),
+            };
+            drop(permit);
+        }));
+    }
+    
+    for task in tasks {
+        task.await?;
+    }
+    
+    let elapsed = start.elapsed();
+    let failed = failed.load(Ordering::Relaxed);
+    println!(
+        "=== {} réponses, {} échecs en {:.2?} ({:.0} requêtes/s) ===",
+        answered.load(Ordering::Relaxed),
+        failed,
+        elapsed,
+        total as f64 / elapsed.as_secs_f64()
+    );
+    if failed > 0 {
+        return Err(format!("{} requête(s) sur {} sans réponse", failed, total).into());
+    }
+    
+    Ok(())
+}
+
+/// Inonde `server_addr` de `total` requêtes UDP depuis un même socket, sans
+/// attendre les réponses, puis compte les réponses complètes, tronquées
+/// (« slip ») et absentes : un serveur avec `--rate-limit` doit en
+/// supprimer une partie, et en tronquer une autre s'il a été lancé avec
+/// `--rate-slip <slip>` non nul
+pub(crate) async fn test_rate_limit(server_addr: SocketAddr, total: usize, slip: u32) -> Result<(), Box<dyn std::error::Error>> {
+    println!("=== Test de limitation du débit ({}, {} requêtes) ===", server_addr, total);
+    
+    let socket = bind_udp_for(server_addr).await?;
+    for i in 0..total {
+        let query = DnsMessage::new_typed_query(i as u16, "example.com".to_string(), RecordType::A);
+        socket.send_to(&query.to_bytes(), server_addr).await?;
+    }
+    
+    // Les réponses arrivent dans la foulée ; une demi-seconde de silence
+    // marque la fin
+    let (mut full, mut truncated) = (0, 0);
+    let mut buffer = [0u8; 512];
+    while let Ok(received) = timeout(Duration::from_millis(500), socket.recv(&mut buffer)).await {
+        match DnsMessage::from_bytes(&buffer[..received?]) {
+            Ok(response) if response.header.flags.tc => truncated += 1,
+            Ok(_) => full += 1,
+            Err(_) => {}
+        }
+    }
+    
+    // Un datagramme dupliqué en route donne plus de réponses que de requêtes
+    let unanswered = total.saturating_sub(full + truncated);
+    println!("{} réponses complètes, {} tronquées, {} sans réponse", full, truncated, unanswered);
+    if unanswered == 0 {
+        return Err("Aucune réponse supprimée par la limitation du débit".into());
+    }
+    if slip > 0 && truncated == 0 {
+        return Err("Aucune réponse tronquée malgré --rate-slip".into());
+    }
+    Ok(())
+}
+
+/// Scénario de blocage sur un serveur lancé avec `--blocklist list
+/// --sinkhole 0.0.0.0` : les noms de la liste et leurs sous-domaines sont
+/// redirigés, puis un nom ajouté au fichier est bloqué après rechargement
+pub(crate) async fn test_blocklist(server_addr: SocketAddr, list: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let client = DnsClient::new(server_addr).await?;
+    
+    println!("=== Test des listes de blocage ({}) ===", server_addr);
+    
+    // ads est bloqué par la liste au format hosts, sub.tracker par la
+    // ligne tracker.example.com ; sans adresse IPv6 de redirection, AAAA
+    // n'a pas de réponse
+    let lookups = [
+        ("ads.example.com", RecordType::A),
+        ("sub.tracker.example.com", RecordType::A),
+        ("sub.tracker.example.com", RecordType::AAAA),
+        ("www.example.com", RecordType::A),
+    ];
+    for (domain, qtype) in lookups {
+        match client.lookup(domain, qtype).await {
+            Ok(records) => {
+                for record in records {
+                    println!("{} {} {}", domain, qtype, record);
+                }
+            }
+            Err(e) => println!("Erreur pour {} ({}): {}", domain, qtype, e),
+        }
+    }
+    
+    // Le serveur relit la liste quand le fichier change
+    let mut text = std::fs::read_to_string(list)?;
+    text.push_str("www.example.com\n");
+    std::fs::write(list, text)?;
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    match client.resolve("www.example.com").await {
+        Ok(ip) => println!("www.example.com -> {} (après rechargement)", ip),
+        Err(e) => println!("Erreur pour www.example.com après rechargement: {}", e),
+    }
+    
+    Ok(())
+}
+
+/// Interroge le même serveur en UDP, en DNS sur TLS et en DNS sur HTTPS
+/// (GET et POST) et compare les réponses ; le certificat autosigné `ca`
+/// est valable pour 127.0.0.1 et localhost
+pub(crate) async fn test_transports(udp: SocketAddr, dot: SocketAddr, doh: SocketAddr, ca: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    println!("=== Test des transports chiffrés ({}, {}, {}) ===", udp, dot, doh);
+    
+    let mut clients = Vec::new();
+    let mut client = DnsClient::new(udp).await?;
+    client.set_verbose(false);
+    clients.push(("UDP".to_string(), client));
+    let mut client = DnsClient::new(dot).await?;
+    client.set_verbose(false);
+    client.set_protocol(Protocol::tls(ca, "localhost")?);
+    clients.push(("TLS".to_string(), client));
+    for method in [Method::Get, Method::Post] {
+        let mut client = DnsClient::new(doh).await?;
+        client.set_verbose(false);
+        client.set_protocol(Protocol::https(ca, "127.0.0.1", doh::DEFAULT_PATH, method)?);
+        clients.push((format!("HTTPS {}", method), client));
+    }
+    
+    for (domain, qtype) in [("www.example.com", RecordType::A), ("example.com", RecordType::MX), ("nothere.example.com", RecordType::A)] {
+        let mut reference: Option<DnsMessage> = None;
+        for (label, client) in &clients {
+            match client.exchange_detailed(&DnsMessage::new_typed_query(rand::random::<u16>(), domain.to_string(), qtype)).await {
+                Ok(exchange) => {
+                    let response = exchange.response;
+                    let same = reference.as_ref().is_none_or(|reference| {
+                        reference.answers == response.answers && reference.rcode() == response.rcode()
+                    });
+                    println!(
+                        "{} {} par {}: {}, {} réponse(s), {} octets{}",
+                        domain, qtype, label, response.rcode(), response.answers.len(), exchange.size,
+                        if same { "" } else { " (DIFFÉRENT de UDP)" }
+                    );
+                    reference.get_or_insert(response);
+                }
+                Err(e) => println!("Erreur pour {} ({}) par {}: {}", domain, qtype, label, e),
+            }
+        }
+    }
+    
+    // Un nom absent du certificat, ou un chemin inconnu, sont refusés
+    let mut client = DnsClient::new(dot).await?;
+    client.set_verbose(false);
+    client.set_retries(0);
+    client.set_protocol(Protocol::tls(ca, "ailleurs.example")?);
+    match client.resolve("example.com").await {
+        Ok(ip) => println!("Certificat accepté pour un autre nom (inattendu): {}", ip),
+        Err(e) => println!("Certificat refusé pour ailleurs.example: {}", e),
+    }
+    let mut client = DnsClient::new(doh).await?;
+    client.set_verbose(false);
+    client.set_retries(0);
+    client.set_protocol(Protocol::https(ca, "localhost", "/autre", Method::Post)?);
+    match client.resolve("example.com").await {
+        Ok(ip) => println!("Chemin /autre accepté (inattendu): {}", ip),
+        Err(e) => println!("Chemin /autre refusé: {}", e),
+    }
+    
+    Ok(())
+}
+
+/// Le même serveur, écouté en IPv4 et en IPv6, donne les mêmes réponses
+/// dans les deux familles ; un client peut aussi mêler les deux
+pub(crate) async fn test_dual_stack(v4: SocketAddr, v6: SocketAddr) -> Result<(), Box<dyn std::error::Error>> {
+    println!("=== Test double pile ({}, {}) ===", v4, v6);
+    
+    let mut answers = Vec::new();
+    for server in [v4, v6] {
+        let mut client = DnsClient::new(server).await?;
+        client.set_verbose(false);
+        let query = DnsMessage::new_typed_query(rand::random::<u16>(), "www.example.com".to_string(), RecordType::A);
+        for exchange in [client.exchange_detailed(&query).await, client.exchange_tcp(&query, server).await] {
+            let exchange = exchange.map_err(|e| format!("www.example.com par {}: {}", server, e))?;
+            println!("www.example.com A par {} ({}): {} réponse(s)", exchange.server, exchange.transport, exchange.response.answers.len());
+            answers.push(exchange.response.answers);
+        }
+    }
+    let identical = answers.windows(2).all(|pair| pair[0] == pair[1]);
+    println!("Réponses identiques dans les deux familles: {}", identical);
+    if !identical {
+        return Err("Réponses différentes selon la famille ou le transport".into());
+    }
+    
+    // Serveurs des deux familles dans un même client
+    let mut client = DnsClient::with_servers(vec![v6, v4]).await?;
+    client.set_verbose(false);
+    match client.resolve("example.com").await {
+        Ok(ip) => println!("example.com -> {} (client double pile)", ip),
+        Err(e) => println!("Erreur pour example.com (client double pile): {}", e),
+    }
+    
+    Ok(())
+}
+
+/// Résout quelques noms avec un resolv.conf et un fichier hosts de test,
+/// qui désignent le serveur `server` et cherchent dans example.com puis
+/// dev.example.com
+pub(crate) async fn test_resolver(server: SocketAddr, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    println!("=== Test du résolveur (resolv.conf et hosts, {}) ===", server);
+
+    std::fs::create_dir_all(dir)?;
+    let resolv_conf = dir.join("resolv.conf");
+    let hosts = dir.join("hosts");
+    std::fs::write(
+        &resolv_conf,
+        format!(
+            "# Configuration de test\nnameserver {}\ndomain ignored.example\nsearch example.com. dev.example.com\noptions ndots:1 timeout:1 attempts:2 rotate\n",
+            server
+        ),
+    )?;
+    std::fs::write(&hosts, "127.0.0.1 localhost\n::1 localhost ip6-localhost\n10.1.2.3 intranet Intranet.example.com # poste local\n")?;
+
+    let resolver = StubResolver::from_files(&resolv_conf, &hosts).await?;
+    let config = ResolvConf::load(&resolv_conf)?;
+    println!("Serveurs {:?}, recherche {:?}, ndots {}", config.nameservers, config.search, config.ndots);
+    for name in ["www", "host", "google.com", "intranet.example.com."] {
+        println!("Candidats pour {}: {}", name, config.candidates(name).join(", "));
+    }
+
+    // www et host ne se résolvent qu'avec le domaine de recherche, dans le
+    // second pour host ; intranet vient de la table hosts. Un nom relatif
+    // inconnu tomberait sur le joker de dev.example.com : le nom absent est
+    // donc absolu
+    for name in ["www", "host", "google.com", "github.com.", "localhost", "INTRANET", "192.0.2.1", "nothere.invalid."] {
+        match resolver.lookup_ip(name).await {
+            Ok(addresses) => {
+                let addresses: Vec<String> = addresses.iter().map(IpAddr::to_string).collect();
+                println!("{} -> {}", name, addresses.join(", "));
+            }
+            Err(e) => println!("Erreur pour {}: {}", name, e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Récupère la page de métriques de `addr` et en affiche les compteurs, puis
+/// compte les lignes du journal des requêtes
+pub(crate) async fn test_metrics(addr: SocketAddr, query_log: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    println!("=== Test des métriques ({}) ===", addr);
+
+    let mut stream = TcpStream::connect(addr).await?;
+    stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+
+    let (head, body) = response.split_once("\r\n\r\n").ok_or("réponse HTTP sans corps")?;
+    println!("{}", head.lines().next().unwrap_or(""));
+    for line in body.lines().filter(|line| line.starts_with("tp7_") && !line.contains("_bucket")) {
+        println!("  {}", line);
+    }
+
+    let log = std::fs::read_to_string(query_log)?;
+    let lines: Vec<&str> = log.lines().collect();
+    let valid = lines.iter().filter(|line| line.starts_with('{') && line.ends_with('}')).count();
+    println!("Journal {}: {} requête(s), {} ligne(s) JSON", query_log.display(), lines.len(), valid);
+    if let Some(last) = lines.last() {
+        println!("  {}", last);
+    }
+    Ok(())
+}
+
+/// Prépare dans `dir` les fichiers du scénario de test : zone `com` signée
+/// (l'ancre de confiance), qui délègue `example.com` avec un DS et
+/// `unsigned.com` sans DS, les clés de `com` et `example.com` (KSK P-256 et
+/// ZSK Ed25519), l'ancre et une ancre fausse. Renvoie les arguments du
+/// serveur qui sert ces zones à `bind`.
+pub(crate) fn write_test_zones(dir: &Path, bind: SocketAddr) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    fs::create_dir_all(dir)?;
+    let path = |file: &str| dir.join(file);
+    let write_key = |file: &str, zone: &str, algorithm: Algorithm, ksk: bool| -> Result<PathBuf, Box<dyn std::error::Error>> {
+        fs::write(path(file), SigningKey::generate(zone, algorithm, ksk)?)?;
+        Ok(path(file))
+    };
+
+    let com_key = write_key("com.key", "com", Algorithm::Ed25519, true)?;
+    let example_ksk = write_key("example.com.ksk", "example.com", Algorithm::EcdsaP256Sha256, true)?;
+    let example_zsk = write_key("example.com.zsk", "example.com", Algorithm::Ed25519, false)?;
+    let other_key = write_key("other.key", "com", Algorithm::Ed25519, true)?;
+
+    let example_ds = ds_for(&SigningKey::load(&example_ksk)?.dnskey);
+    fs::write(
+        path("com.zone"),
+        format!(
+            "$ORIGIN com.\n$TTL 1h\n\
+             @         IN SOA ns1.example.com. hostmaster.example.com. 1 2h 15m 1w 5m\n\
+             @         IN NS  ns1.example.com.\n\
+             example   IN NS  ns1.example.com.\n\
+             {}\n\
+             unsigned  IN NS  ns1.unsigned.com.\n",
+            example_ds
+        ),
+    )?;
+    fs::write(
+        path("unsigned.com.zone"),
+        "$ORIGIN unsigned.com.\n$TTL 1h\n\
+         @    IN SOA ns1 hostmaster 1 2h 15m 1w 5m\n\
+         @    IN NS  ns1\n\
+         ns1  IN A   192.0.2.153\n\
+         www  IN A   192.0.2.154\n",
+    )?;
+    fs::write(path("anchor.ds"), format!("{}\n", ds_for(&SigningKey::load(&com_key)?.dnskey)))?;
+    fs::write(path("bogus-anchor.ds"), format!("{}\n", ds_for(&SigningKey::load(&other_key)?.dnskey)))?;
+
+    let example_zone = Path::new(env!("CARGO_MANIFEST_DIR")).join("zones/example.com.zone");
+    let mut args = vec!["--bind".to_string(), bind.to_string()];
+    for key in [&com_key, &example_ksk, &example_zsk] {
+        args.extend(["--dnssec-key".to_string(), key.display().to_string()]);
+    }
+    for zone in [path("com.zone"), example_zone, path("unsigned.com.zone")] {
+        args.push(zone.display().to_string());
+    }
+    Ok(args)
+}
+
+/// Scénario de validation sur le serveur préparé par `write_test_zones` :
+/// réponses positives, par joker, NODATA et NXDOMAIN sûres, délégation non
+/// signée, puis ancre fausse
+pub(crate) async fn test_dnssec(server_addr: SocketAddr, dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    println!("=== Test de DNSSEC ({}) ===", server_addr);
+
+    let mut client = DnsClient::new(server_addr).await?;
+    client.set_verbose(false);
+    for anchor in load_trust_anchors(&dir.join("anchor.ds"))? {
+        client.add_trust_anchor(anchor);
+    }
+
+    let lookups = [
+        ("example.com", RecordType::A),
+        ("www.example.com", RecordType::A),
+        ("host.dev.example.com", RecordType::A),
+        ("example.com", RecordType::AAAA),
+        ("nothere.example.com", RecordType::A),
+        ("www.unsigned.com", RecordType::A),
+    ];
+    for (domain, qtype) in lookups {
+        match client.validate(domain, qtype).await {
+            Ok((response, security)) => {
+                println!("{} {}: {}, {} réponse(s), {}", domain, qtype, response.rcode(), response.answers.len(), security)
+            }
+            Err(e) => println!("Erreur pour {} ({}): {}", domain, qtype, e),
+        }
+    }
+
+    // Avec une ancre qui ne correspond à aucune clé de com, rien ne tient
+    let mut client = DnsClient::new(server_addr).await?;
+    client.set_verbose(false);
+    for anchor in load_trust_anchors(&dir.join("bogus-anchor.ds"))? {
+        client.add_trust_anchor(anchor);
+    }
+    match client.validate("example.com", RecordType::A).await {
+        Ok((_, security)) => println!("example.com A avec une ancre fausse: {} (attendu: bogus)", security),
+        Err(e) => println!("Erreur pour example.com avec une ancre fausse: {}", e),
+    }
+
+    Ok(())
+}
+
+/// Relit la capture d'un serveur lancé avec `--pcap` : chaque message doit
+/// s'analyser, et la même capture recopiée en pcapng donner les mêmes
+/// messages. Une seconde capture, écrite directement, vérifie qu'un message
+/// découpé en plusieurs segments TCP est bien réassemblé.
+pub(crate) fn test_pcap(capture: &Path, ports: &[u16]) -> Result<(), Box<dyn std::error::Error>> {
+    println!("=== Test de la capture pcap ({}) ===", capture.display());
+
+    let bytes = std::fs::read(capture)?;
+    let packets = read_packets(&bytes)?;
+    let (messages, ignored) = extract_messages(&packets, ports);
+    let udp = messages.iter().filter(|message| message.transport == Transport::Udp).count();
+    let failures = messages.iter().filter(|message| DnsMessage::from_bytes(&message.data).is_err()).count();
+    println!(
+        "{} paquet(s), {} message(s) ({} en UDP, {} en TCP), {} ignoré(s), {} illisible(s)",
+        packets.len(),
+        messages.len(),
+        udp,
+        messages.len() - udp,
+        ignored,
+        failures
+    );
+    if failures > 0 || udp == 0 || udp == messages.len() {
+        return Err("Capture incomplète ou illisible".into());
+    }
+
+    let pcapng = to_pcapng(&packets);
+    let (replayed, _) = extract_messages(&read_packets(&pcapng)?, ports);
+    let same = replayed.len() == messages.len()
+        && replayed.iter().zip(&messages).all(|(a, b)| a.data == b.data && a.timestamp == b.timestamp);
+    println!("Mêmes messages relus en pcapng: {}", same);
+
+    // Une réponse d'environ 4 Ko, en UDP puis en TCP, entre un client IPv4
+    // et un serveur IPv6
+    let large = capture.with_extension("large.pcap");
+    let (client, server): (SocketAddr, SocketAddr) = ("192.0.2.1:40000".parse()?, "[2001:db8::53]:53".parse()?);
+    let mut builder = DnsMessage::builder().id(1).qr(true).question("large.example.com", RecordType::TXT);
+    for index in 0..40u8 {
+        let text = vec![b'a' + index % 26; 100];
+        builder = builder.answer(DnsAnswer::with_ttl("large.example.com".to_string(), 60, RecordData::TXT(vec![text])));
+    }
+    let message = builder.build()?.to_bytes();
+    let writer = PcapWriter::create(&large)?;
+    writer.write_message(Transport::Udp, server, client, &message)?;
+    writer.write_message(Transport::Tcp, server, client, &message)?;
+    writer.write_message(Transport::Tcp, server, client, &message)?;
+    writer.close_connection(client, server);
+
+    let bytes = std::fs::read(&large)?;
+    let packets = read_packets(&bytes)?;
+    let (messages, _) = extract_messages(&packets, &[DNS_PORT]);
+    let reassembled = messages.len() == 3 && messages.iter().all(|captured| captured.data == message);
+    println!(
+        "Message de {} octets en {} paquet(s), relu {} fois: {}",
+        message.len(),
+        packets.len(),
+        messages.len(),
+        reassembled
+    );
+    if !same || !reassembled {
+        return Err("Capture mal relue".into());
+    }
+
+    Ok(())
+}
@@ -1,94 +1,1613 @@
-use tokio::net::UdpSocket;
-use std::collections::HashMap;
-use std::net::SocketAddr;
-use crate::dns_message::{DnsMessage, DnsAnswer};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::sync::{Notify, Semaphore};
+use tokio::task::JoinSet;
+use tokio::time::{timeout, Duration, Instant};
+use tokio_rustls::TlsAcceptor;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use socket2::{Domain, Protocol, Socket, Type};
+use crate::acl::AddressRange;
+use crate::blocklist::{BlockAction, Blocklist};
+use crate::cache::{CachedResponse, DnsCache};
+use crate::client::DnsClient;
+use crate::dnssec::{self, SigningKey};
+use crate::dns_message::{DnsMessage, DnsAnswer, DnsHeader, DnsQuestion, Edns, Opcode, RecordData, RecordType, ResponseCode};
+use crate::doh;
+use crate::metrics::{self, Metrics, QueryEvent, QueryLog, Transport};
+use crate::pcap::PcapWriter;
+use crate::ratelimit::{RateLimitConfig, RateLimiter, Verdict};
+use crate::reverse;
+use crate::store::{RecordStore, serial_newer, soa_serial};
+use crate::tcp;
+use crate::tls;
+use crate::transfer::{self, Transfer};
+use crate::update;
+use crate::zone::{Zone, is_subdomain};
+
+/// Adresse d'écoute sans `--bind`
+const DEFAULT_BIND: &str = "127.0.0.1:5353";
+
+/// Nombre de connexions TCP en attente d'acceptation par adresse d'écoute
+const TCP_BACKLOG: i32 = 1024;
+
+/// Délai d'attente du premier essai auprès d'un serveur amont
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Taille maximale d'une réponse UDP sans EDNS (RFC 1035 §4.2.1)
+const MAX_UDP_PAYLOAD: usize = 512;
+
+/// Taille UDP annoncée par le serveur en EDNS, et plafond appliqué à celle
+/// annoncée par les clients
+const MAX_EDNS_PAYLOAD: u16 = 4096;
+
+/// Durée d'inactivité après laquelle une connexion TCP est fermée
+const TCP_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Nombre maximal de requêtes traitées en même temps, tous transports
+/// confondus ; au-delà, le serveur attend qu'une requête se termine avant de
+/// lire le paquet ou le message suivant
+const MAX_IN_FLIGHT: usize = 1024;
+
+/// Nombre maximal de connexions TCP, TLS et HTTPS ouvertes en même temps ;
+/// au-delà, les nouvelles connexions attendent. Une connexion inactive ne
+/// retient donc jamais les requêtes UDP.
+const MAX_CONNECTIONS: usize = 512;
+
+/// Délai laissé aux requêtes en cours lors de l'arrêt du serveur
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Délai entre deux tentatives de transfert d'une zone secondaire dont on
+/// n'a encore aucune version (sans SOA, pas de champ RETRY)
+const SECONDARY_INITIAL_RETRY: Duration = Duration::from_secs(10);
+
+/// Longueur maximale d'une chaîne de CNAME suivie dans nos zones
+const MAX_CNAME_CHAIN: usize = 8;
+
+/// Intervalle entre deux vérifications des fichiers des listes de blocage
+const BLOCKLIST_RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Intervalle entre deux signatures complètes des zones signées, bien
+/// plus court que la validité des signatures
+const RESIGN_INTERVAL: Duration = Duration::from_secs(86400);
+
+/// Zone de test utilisée quand aucun fichier n'est fourni ; elle couvre
+/// tout l'espace de noms, le serveur ne refuse donc aucune requête
+const TEST_ZONE: &str = "\
+$ORIGIN .
+$TTL 300
+@                   IN SOA   localhost. hostmaster.localhost. 1 3600 600 86400 300
+example.com.        IN A     93.184.216.34
+example.com.        IN MX    10 mail.example.com.
+example.com.        IN TXT   \"v=spf1 -all\"
+www.example.com.    IN CNAME example.com.
+alias.example.com.  IN CNAME www.example.com.
+*.dev.example.com.  IN A     10.0.0.10
+loop1.example.com.  IN CNAME loop2.example.com.
+loop2.example.com.  IN CNAME loop1.example.com.
+google.com.         IN A     142.250.191.14
+github.com.         IN A     140.82.114.4
+localhost.          IN A     127.0.0.1
+localhost.          IN AAAA  ::1
+big.example.com.    IN TXT   \"Cet enregistrement est volontairement long afin que la réponse complète\"
+big.example.com.    IN TXT   \"dépasse les 512 octets autorisés en UDP sans EDNS : le serveur doit alors\"
+big.example.com.    IN TXT   \"tronquer sa réponse en positionnant le bit TC, et le client doit reposer\"
+big.example.com.    IN TXT   \"sa question sur une connexion TCP, où chaque message est précédé de sa\"
+big.example.com.    IN TXT   \"longueur sur deux octets (RFC 1035, section 4.2.2). Les lignes suivantes\"
+big.example.com.    IN TXT   \"ne servent qu'à grossir la réponse au-delà de la limite, rien de plus.\"
+big.example.com.    IN TXT   \"Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod.\"
+";
+
+/// Paramètres de démarrage du serveur
+pub struct ServerConfig {
+    /// Adresses d'écoute UDP et TCP. Une adresse IPv6 ne reçoit que de
+    /// l'IPv6 : `--bind 0.0.0.0:53 --bind [::]:53` écoute sur les deux familles
+    pub bind_addrs: Vec<SocketAddr>,
+    /// Zones pour lesquelles le serveur fait autorité
+    pub zones: Vec<Zone>,
+    /// Serveurs amont interrogés pour les noms hors de nos zones ; vide,
+    /// le serveur refuse ces requêtes
+    pub forwarders: Vec<SocketAddr>,
+    /// Clients autorisés à mettre à jour chaque zone (sommet, plage) ; une
+    /// zone absente de la liste refuse toute mise à jour
+    pub update_acl: Vec<(String, AddressRange)>,
+    /// Clients autorisés à transférer chaque zone (sommet, plage), par AXFR
+    /// ou IXFR ; une zone absente de la liste refuse tout transfert
+    pub transfer_acl: Vec<(String, AddressRange)>,
+    /// Fichier où sont conservées les mises à jour acceptées
+    pub journal: Option<PathBuf>,
+    /// Zones secondaires (sommet, serveur primaire), recopiées par transfert
+    pub secondaries: Vec<(String, SocketAddr)>,
+    /// Serveurs secondaires prévenus (NOTIFY) à chaque changement de zone
+    pub notify: Vec<SocketAddr>,
+    /// Zones dont les adresses ne servent pas à synthétiser de PTR
+    pub no_reverse: Vec<String>,
+    /// Fichier où chaque requête est journalisée (une ligne JSON)
+    pub query_log: Option<PathBuf>,
+    /// Fichier pcap où chaque message reçu ou envoyé est recopié
+    pub pcap: Option<PathBuf>,
+    /// Adresse HTTP où les compteurs sont publiés au format Prometheus
+    pub metrics_addr: Option<SocketAddr>,
+    /// Limitation du débit des réponses UDP, désactivée par défaut
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Listes de noms bloqués, rechargées quand elles changent
+    pub blocklists: Vec<PathBuf>,
+    /// Réponse aux noms bloqués : NXDOMAIN sans adresse de redirection
+    pub block_action: BlockAction,
+    /// Fichiers des clés DNSSEC (produits par `keygen`) ; les zones qui ont
+    /// des clés sont signées au démarrage
+    pub dnssec_keys: Vec<PathBuf>,
+    /// Certificat (suivi de sa chaîne) et clé privée PEM de DoT et DoH
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    /// Adresse d'écoute de DNS sur TLS (RFC 7858)
+    pub dot_addr: Option<SocketAddr>,
+    /// Adresse d'écoute de DNS sur HTTPS (RFC 8484), servi sur `/dns-query`
+    pub doh_addr: Option<SocketAddr>,
+}
+
+impl ServerConfig {
+    /// Lit les options de la sous-commande `server` :
+    /// `[--bind <addr>]... [--forward <addr>]... [--allow-update <zone>=<plage>]...
+    /// [--allow-transfer <zone>=<plage>]... [--journal <fichier>] [--secondary <zone>=<primaire>]... [--notify <addr>]...
+    /// [--no-reverse <zone>]... [--query-log <fichier>] [--pcap <fichier>] [--metrics <addr>]
+    /// [--rate-limit <réponses/s> [--rate-burst <n>] [--rate-slip <n>] [--rate-exempt <plage>]...]
+    /// [--blocklist <fichier>... [--sinkhole <adresse>]...] [--dnssec-key <fichier>]...
+    /// [--tls-cert <pem> --tls-key <pem> [--dot <addr>] [--doh <addr>]] [zone...]`
+    pub fn from_args(args: &[String]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut config = Self {
+            bind_addrs: Vec::new(),
+            zones: Vec::new(),
+            forwarders: Vec::new(),
+            update_acl: Vec::new(),
+            transfer_acl: Vec::new(),
+            journal: None,
+            secondaries: Vec::new(),
+            notify: Vec::new(),
+            no_reverse: Vec::new(),
+            query_log: None,
+            pcap: None,
+            metrics_addr: None,
+            rate_limit: None,
+            blocklists: Vec::new(),
+            block_action: BlockAction::default(),
+            dnssec_keys: Vec::new(),
+            tls_cert: None,
+            tls_key: None,
+            dot_addr: None,
+            doh_addr: None,
+        };
+        let mut rate: Option<f64> = None;
+        let mut burst: Option<f64> = None;
+        let mut slip: Option<u32> = None;
+        let mut exempt = Vec::new();
+        
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--bind" => {
+                    config.bind_addrs.push(args.next().ok_or("--bind attend une adresse")?.parse()?);
+                }
+                "--forward" => {
+                    config.forwarders.push(args.next().ok_or("--forward attend une adresse")?.parse()?);
+                }
+                "--allow-update" => {
+                    let rule = args.next().ok_or("--allow-update attend <zone>=<plage>")?;
+                    let (zone, range) = rule.split_once('=').ok_or("--allow-update attend <zone>=<plage>")?;
+                    let zone = zone.strip_suffix('.').unwrap_or(zone).to_string();
+                    config.update_acl.push((zone, range.parse()?));
+                }
+                "--allow-transfer" => {
+                    let rule = args.next().ok_or("--allow-transfer attend <zone>=<plage>")?;
+                    let (zone, range) = rule.split_once('=').ok_or("--allow-transfer attend <zone>=<plage>")?;
+                    let zone = zone.strip_suffix('.').unwrap_or(zone).to_string();
+                    config.transfer_acl.push((zone, range.parse()?));
+                }
+                "--journal" => {
+                    config.journal = Some(PathBuf::from(args.next().ok_or("--journal attend un fichier")?));
+                }
+                "--secondary" => {
+                    let rule = args.next().ok_or("--secondary attend <zone>=<primaire>")?;
+                    let (zone, primary) = rule.split_once('=').ok_or("--secondary attend <zone>=<primaire>")?;
+                    let zone = zone.strip_suffix('.').unwrap_or(zone).to_string();
+                    config.secondaries.push((zone, primary.parse()?));
+                }
+                "--notify" => {
+                    config.notify.push(args.next().ok_or("--notify attend une adresse")?.parse()?);
+                }
+                "--no-reverse" => {
+                    let zone = args.next().ok_or("--no-reverse attend une zone")?;
+                    config.no_reverse.push(zone.strip_suffix('.').unwrap_or(zone).to_string());
+                }
+                "--query-log" => {
+                    config.query_log = Some(PathBuf::from(args.next().ok_or("--query-log attend un fichier")?));
+                }
+                "--pcap" => {
+                    config.pcap = Some(PathBuf::from(args.next().ok_or("--pcap attend un fichier")?));
+                }
+                "--metrics" => {
+                    config.metrics_addr = Some(args.next().ok_or("--metrics attend une adresse")?.parse()?);
+                }
+                "--rate-limit" => {
+                    rate = Some(args.next().ok_or("--rate-limit attend un nombre de réponses par seconde")?.parse()?);
+                }
+                "--rate-burst" => {
+                    burst = Some(args.next().ok_or("--rate-burst attend un nombre de réponses")?.parse()?);
+                }
+                "--rate-slip" => {
+                    slip = Some(args.next().ok_or("--rate-slip attend un nombre")?.parse()?);
+                }
+                "--rate-exempt" => {
+                    exempt.push(args.next().ok_or("--rate-exempt attend une plage d'adresses")?.parse()?);
+                }
+                "--blocklist" => {
+                    config.blocklists.push(PathBuf::from(args.next().ok_or("--blocklist attend un fichier")?));
+                }
+                "--sinkhole" => {
+                    // Une adresse de chaque famille au plus
+                    let addr: IpAddr = args.next().ok_or("--sinkhole attend une adresse")?.parse()?;
+                    let previous = match addr {
+                        IpAddr::V4(addr) => config.block_action.sinkhole_v4.replace(addr).map(IpAddr::V4),
+                        IpAddr::V6(addr) => config.block_action.sinkhole_v6.replace(addr).map(IpAddr::V6),
+                    };
+                    if let Some(previous) = previous {
+                        return Err(format!("--sinkhole: {} et {} sont de la même famille", previous, addr).into());
+                    }
+                }
+                "--dnssec-key" => {
+                    config.dnssec_keys.push(PathBuf::from(args.next().ok_or("--dnssec-key attend un fichier")?));
+                }
+                "--tls-cert" => {
+                    config.tls_cert = Some(PathBuf::from(args.next().ok_or("--tls-cert attend un fichier")?));
+                }
+                "--tls-key" => {
+                    config.tls_key = Some(PathBuf::from(args.next().ok_or("--tls-key attend un fichier")?));
+                }
+                "--dot" => {
+                    config.dot_addr = Some(args.next().ok_or("--dot attend une adresse")?.parse()?);
+                }
+                "--doh" => {
+                    config.doh_addr = Some(args.next().ok_or("--doh attend une adresse")?.parse()?);
+                }
+                option if option.starts_with("--") => {
+                    return Err(format!("Option inconnue: {}", option).into());
+                }
+                // Une zone invalide empêche le démarrage
+                file => config.zones.push(Zone::load(Path::new(file))?),
+            }
+        }
+        
+        // Par défaut, une salve vaut une seconde de débit et une réponse
+        // limitée sur deux part tronquée
+        match rate {
+            Some(rate) if rate > 0.0 => {
+                let burst = burst.unwrap_or(rate).max(1.0);
+                config.rate_limit = Some(RateLimitConfig { rate, burst, slip: slip.unwrap_or(2), exempt });
+            }
+            Some(_) => return Err("--rate-limit attend un débit positif".into()),
+            None if burst.is_some() || slip.is_some() || !exempt.is_empty() => {
+                return Err("--rate-burst, --rate-slip et --rate-exempt demandent --rate-limit".into());
+            }
+            None => {}
+        }
+        
+        let sinkhole = &config.block_action;
+        if config.blocklists.is_empty() && (sinkhole.sinkhole_v4.is_some() || sinkhole.sinkhole_v6.is_some()) {
+            return Err("--sinkhole demande --blocklist".into());
+        }
+        
+        if config.bind_addrs.is_empty() {
+            config.bind_addrs.push(DEFAULT_BIND.parse()?);
+        }
+        
+        let tls_service = config.dot_addr.is_some() || config.doh_addr.is_some();
+        match (&config.tls_cert, &config.tls_key) {
+            (Some(_), Some(_)) if !tls_service => return Err("--tls-cert et --tls-key demandent --dot ou --doh".into()),
+            (Some(_), Some(_)) => {}
+            (None, None) if tls_service => return Err("--dot et --doh demandent --tls-cert et --tls-key".into()),
+            (None, None) => {}
+            _ => return Err("--tls-cert et --tls-key vont ensemble".into()),
+        }
+        
+        Ok(config)
+    }
+}
+
+/// Zone secondaire : copie d'une zone d'un serveur primaire, tenue à jour
+/// par transferts
+struct SecondaryZone {
+    origin: String,
+    primary: SocketAddr,
+    /// Réveille la boucle de rafraîchissement à la réception d'un NOTIFY
+    refresh_now: Notify,
+}
 
 pub struct DnsServer {
-    socket: UdpSocket,
-    records: HashMap<String, [u8; 4]>,
+    /// Sockets UDP et TCP de chaque adresse d'écoute ; une réponse UDP part
+    /// du socket qui a reçu la requête
+    sockets: Vec<UdpSocket>,
+    listeners: Vec<TcpListener>,
+    /// Enregistrements des zones servies, modifiables par mise à jour
+    /// dynamique ; le verrou n'est jamais gardé à travers un `await`
+    store: RwLock<RecordStore>,
+    /// Une seule modification des zones à la fois (voir `update_store`)
+    store_writer: Mutex<()>,
+    forwarders: Vec<SocketAddr>,
+    update_acl: Vec<(String, AddressRange)>,
+    transfer_acl: Vec<(String, AddressRange)>,
+    journal: Option<PathBuf>,
+    secondaries: Vec<SecondaryZone>,
+    notify: Vec<SocketAddr>,
+    no_reverse: Vec<String>,
+    cache: DnsCache,
+    query_log: Option<QueryLog>,
+    /// Capture des messages reçus et envoyés
+    capture: Option<PcapWriter>,
+    metrics: Metrics,
+    /// Point d'accès HTTP des métriques
+    metrics_listener: Option<TcpListener>,
+    rate_limiter: Option<RateLimiter>,
+    blocklist: Option<Blocklist>,
+    /// Clés DNSSEC de chaque zone signée
+    dnssec_keys: Vec<(String, Vec<SigningKey>)>,
+    /// Écoute et configuration TLS de DNS sur TLS
+    dot: Option<(TcpListener, TlsAcceptor)>,
+    /// Écoute et configuration TLS de DNS sur HTTPS
+    doh: Option<(TcpListener, TlsAcceptor)>,
+    /// Jetons des requêtes en cours, quel que soit leur transport
+    in_flight: Arc<Semaphore>,
+    /// Jetons des connexions ouvertes, pris pour toute leur durée
+    connections: Arc<Semaphore>,
 }
 
 impl DnsServer {
-    pub async fn new(bind_addr: SocketAddr) -> Result<Self, Box<dyn std::error::Error>> {
-        let socket = UdpSocket::bind(bind_addr).await?;
+    /// Démarre un serveur faisant autorité pour les zones de `config`, ou
+    /// pour une zone de test s'il n'a ni zone ni serveur amont
+    pub async fn new(config: ServerConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let ServerConfig {
+            bind_addrs, mut zones, forwarders, update_acl, transfer_acl, journal, secondaries, notify, no_reverse,
+            query_log, pcap, metrics_addr, rate_limit, blocklists, block_action, dnssec_keys, tls_cert, tls_key, dot_addr, doh_addr,
+        } = config;
+        let mut sockets = Vec::new();
+        let mut listeners = Vec::new();
+        for &addr in &bind_addrs {
+            let socket = bind_udp(addr).map_err(|e| format!("{}: {}", addr, e))?;
+            // Sur le port 0, TCP prend le port que le système a donné à UDP
+            let addr = socket.local_addr()?;
+            sockets.push(socket);
+            listeners.push(bind_tcp(addr).map_err(|e| format!("{}: {}", addr, e))?);
+        }
         
-        // Initialiser quelques enregistrements DNS de test
-        let mut records = HashMap::new();
-        records.insert("example.com".to_string(), [93, 184, 216, 34]);
-        records.insert("google.com".to_string(), [142, 250, 191, 14]);
-        records.insert("github.com".to_string(), [140, 82, 114, 4]);
-        records.insert("localhost".to_string(), [127, 0, 0, 1]);
+        if zones.is_empty() && forwarders.is_empty() && secondaries.is_empty() {
+            zones.push(Zone::parse(TEST_ZONE, Path::new("<zone de test>"))?);
+        }
+        
+        for zone in &zones {
+            println!("Zone chargée: {}. ({} enregistrements)", zone.origin, zone.records.len());
+        }
+        let mut store = RecordStore::new(zones);
         
-        println!("Serveur DNS démarré sur {}", bind_addr);
+        // Les mises à jour acceptées avant l'arrêt s'appliquent par-dessus
+        // les fichiers de zone
+        if let Some(journal) = &journal {
+            let count = update::replay_journal(&mut store, journal)?;
+            println!("Journal {}: {} mise(s) à jour rejouée(s)", journal.display(), count);
+        }
+        
+        // Les zones sont signées une fois le journal rejoué
+        let mut zone_keys: Vec<(String, Vec<SigningKey>)> = Vec::new();
+        for path in &dnssec_keys {
+            let key = SigningKey::load(path)?;
+            if !store.is_origin(key.zone()) {
+                return Err(format!("{}: la zone {}. n'est pas servie", path.display(), key.zone()).into());
+            }
+            match zone_keys.iter_mut().find(|(zone, _)| zone.eq_ignore_ascii_case(key.zone())) {
+                Some((_, keys)) => keys.push(key),
+                None => zone_keys.push((key.zone().to_string(), vec![key])),
+            }
+        }
+        for (zone, keys) in &zone_keys {
+            let count = dnssec::sign_zone(&mut store, zone, keys, dnssec::now())?;
+            let tags: Vec<String> = keys
+                .iter()
+                .map(|key| format!("{}{}", key.key_tag(), if key.is_ksk() { " (KSK)" } else { "" }))
+                .collect();
+            println!("Zone {}. signée: {} signature(s), clé(s) {}", zone, count, tags.join(", "));
+        }
+        
+        let addrs: Vec<String> = bind_addrs.iter().map(SocketAddr::to_string).collect();
+        println!("Serveur DNS démarré sur {} (UDP et TCP)", addrs.join(", "));
         println!("Enregistrements disponibles:");
-        for (domain, ip) in &records {
-            println!("  {} -> {}.{}.{}.{}", domain, ip[0], ip[1], ip[2], ip[3]);
+        for record in store.records() {
+            println!("  {} {} {}", record.name, record.rdata.record_type(), record.rdata);
+        }
+        
+        for forwarder in &forwarders {
+            println!("Serveur amont: {}", forwarder);
+        }
+        for (zone, range) in &update_acl {
+            println!("Mises à jour de {}. autorisées depuis {}", zone, range);
+        }
+        for (zone, range) in &transfer_acl {
+            println!("Transferts de {}. autorisés depuis {}", zone, range);
+        }
+        for (zone, primary) in &secondaries {
+            println!("Zone secondaire {}. (primaire: {})", zone, primary);
         }
+        let query_log = match query_log {
+            Some(path) => {
+                println!("Journal des requêtes: {}", path.display());
+                Some(QueryLog::open(&path)?)
+            }
+            None => None,
+        };
+        let capture = match pcap {
+            Some(path) => {
+                println!("Capture des messages: {}", path.display());
+                Some(PcapWriter::create(&path).map_err(|e| format!("{}: {}", path.display(), e))?)
+            }
+            None => None,
+        };
+        let metrics_listener = match metrics_addr {
+            Some(addr) => {
+                println!("Métriques sur http://{}/metrics", addr);
+                Some(TcpListener::bind(addr).await?)
+            }
+            None => None,
+        };
+        if let Some(limit) = &rate_limit {
+            println!(
+                "Limitation du débit: {} réponses/s par réseau (salve: {}, slip: {})",
+                limit.rate, limit.burst, limit.slip
+            );
+            for range in &limit.exempt {
+                println!("  Exemptés: {}", range);
+            }
+        }
+        let blocklist = if blocklists.is_empty() {
+            None
+        } else {
+            let blocklist = Blocklist::load(blocklists, block_action)?;
+            let action = blocklist.action();
+            let mut sinkholes: Vec<String> = action.sinkhole_v4.iter().map(ToString::to_string).collect();
+            sinkholes.extend(action.sinkhole_v6.iter().map(ToString::to_string));
+            println!(
+                "Listes de blocage: {} nom(s) bloqué(s), réponse: {}",
+                blocklist.len(),
+                if sinkholes.is_empty() { "NXDOMAIN".to_string() } else { sinkholes.join(", ") }
+            );
+            Some(blocklist)
+        };
+        // Un certificat illisible empêche le démarrage
+        let (tls_cert, tls_key) = (&tls_cert, &tls_key);
+        let tls_listener = |addr: Option<SocketAddr>, alpn: &'static [u8]| async move {
+            match (addr, tls_cert, tls_key) {
+                (Some(addr), Some(cert), Some(key)) => {
+                    let acceptor = tls::acceptor(cert, key, alpn)?;
+                    Ok::<_, Box<dyn std::error::Error>>(Some((TcpListener::bind(addr).await?, acceptor)))
+                }
+                _ => Ok(None),
+            }
+        };
+        let dot = tls_listener(dot_addr, tls::ALPN_DOT).await?;
+        let doh = tls_listener(doh_addr, tls::ALPN_HTTP1).await?;
+        if let Some(addr) = dot_addr {
+            println!("DNS sur TLS sur {}", addr);
+        }
+        if let Some(addr) = doh_addr {
+            println!("DNS sur HTTPS sur https://{}{}", addr, doh::DEFAULT_PATH);
+        }
+        let secondaries = secondaries
+            .into_iter()
+            .map(|(origin, primary)| SecondaryZone { origin, primary, refresh_now: Notify::new() })
+            .collect();
         
         Ok(Self {
-            socket,
-            records,
+            sockets,
+            listeners,
+            store: RwLock::new(store),
+            store_writer: Mutex::new(()),
+            forwarders,
+            update_acl,
+            transfer_acl,
+            journal,
+            secondaries,
+            notify,
+            no_reverse,
+            cache: DnsCache::new(),
+            query_log,
+            capture,
+            metrics: Metrics::new(),
+            metrics_listener,
+            rate_limiter: rate_limit.map(RateLimiter::new),
+            blocklist,
+            dnssec_keys: zone_keys,
+            dot,
+            doh,
+            in_flight: Arc::new(Semaphore::new(MAX_IN_FLIGHT)),
+            connections: Arc::new(Semaphore::new(MAX_CONNECTIONS)),
         })
     }
 
-    pub async fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let mut buffer = [0u8; 512];
+    /// Adresses d'écoute UDP et TCP, avec le port choisi par le système pour
+    /// un `--bind` sur le port 0
+    pub fn local_addrs(&self) -> std::io::Result<Vec<SocketAddr>> {
+        self.sockets.iter().map(|socket| socket.local_addr()).collect()
+    }
+
+    /// Sert UDP et TCP jusqu'à Ctrl+C, puis laisse les requêtes en cours se
+    /// terminer avant de rendre la main
+    pub async fn run(self: Arc<Self>) -> Result<(), Box<dyn std::error::Error>> {
+        for index in 0..self.secondaries.len() {
+            tokio::spawn(Arc::clone(&self).run_secondary(index));
+        }
+        if self.metrics_listener.is_some() {
+            tokio::spawn(Arc::clone(&self).run_metrics());
+        }
+        if self.blocklist.is_some() {
+            tokio::spawn(Arc::clone(&self).run_blocklist_reload());
+        }
+        if !self.dnssec_keys.is_empty() {
+            tokio::spawn(Arc::clone(&self).run_resign());
+        }
+        // DoT et DoH : une erreur d'écoute n'arrête que ce service
+        let mut encrypted = JoinSet::new();
+        if self.dot.is_some() {
+            encrypted.spawn(Arc::clone(&self).run_tls(Transport::Tls));
+        }
+        if self.doh.is_some() {
+            encrypted.spawn(Arc::clone(&self).run_tls(Transport::Https));
+        }
+        
+        let mut transports = JoinSet::new();
+        for index in 0..self.sockets.len() {
+            transports.spawn(Arc::clone(&self).run_udp(index));
+            transports.spawn(Arc::clone(&self).run_tcp(index));
+        }
+        
+        tokio::select! {
+            // Le premier transport qui échoue arrête le serveur
+            Some(result) = transports.join_next() => result??,
+            result = tokio::signal::ctrl_c() => {
+                result?;
+                println!("Arrêt demandé, attente des requêtes en cours...");
+            }
+        }
+        
+        // Plus aucune requête ni connexion n'est acceptée
+        transports.abort_all();
+        encrypted.abort_all();
+        while transports.join_next().await.is_some() {}
+        while encrypted.join_next().await.is_some() {}
+        
+        // Toutes les requêtes ont rendu leur jeton quand on peut tous les
+        // reprendre ; les connexions inactives ne retardent pas l'arrêt
+        match timeout(SHUTDOWN_TIMEOUT, self.in_flight.acquire_many(MAX_IN_FLIGHT as u32)).await {
+            Ok(_) => println!("Serveur arrêté"),
+            Err(_) => println!(
+                "Serveur arrêté, {} requête(s) abandonnée(s)",
+                MAX_IN_FLIGHT - self.in_flight.available_permits()
+            ),
+        }
+        
+        Ok(())
+    }
+
+    async fn run_udp(self: Arc<Self>, index: usize) -> std::io::Result<()> {
+        let mut buffer = [0u8; MAX_EDNS_PAYLOAD as usize];
+        let server_addr = self.sockets[index].local_addr()?;
         
         loop {
             // Recevoir une requête
-            let (size, client_addr) = self.socket.recv_from(&mut buffer).await?;
+            let (size, client_addr) = self.sockets[index].recv_from(&mut buffer).await?;
+            let data = buffer[..size].to_vec();
+            
+            // Attendre une place libre ; le paquet suivant n'est lu qu'ensuite
+            let permit = Arc::clone(&self.in_flight).acquire_owned().await.expect("sémaphore jamais fermé");
             
             // Traiter la requête en arrière-plan
-            if let Err(e) = self.handle_query(&buffer[..size], client_addr).await {
-                eprintln!("Erreur lors du traitement de la requête: {}", e);
+            let server = Arc::clone(&self);
+            tokio::spawn(async move {
+                if let Err(e) = server.handle_query(&server.sockets[index], &data, client_addr, server_addr).await {
+                    eprintln!("Erreur lors du traitement de la requête: {}", e);
+                }
+                drop(permit);
+            });
+        }
+    }
+
+    async fn run_tcp(self: Arc<Self>, index: usize) -> std::io::Result<()> {
+        let listener_addr = self.listeners[index].local_addr()?;
+        loop {
+            let permit = Arc::clone(&self.connections).acquire_owned().await.expect("sémaphore jamais fermé");
+            let (stream, client_addr) = self.listeners[index].accept().await?;
+            // L'adresse précise de la connexion, pour une écoute sur [::]
+            let server_addr = stream.local_addr().unwrap_or(listener_addr);
+            
+            let server = Arc::clone(&self);
+            tokio::spawn(async move {
+                if let Err(e) = server.handle_tcp_connection(stream, client_addr, server_addr, Transport::Tcp).await {
+                    eprintln!("Erreur sur la connexion TCP de {}: {}", client_addr, e);
+                }
+                server.capture_closed(client_addr, server_addr);
+                drop(permit);
+            });
+        }
+    }
+
+    /// Sert DNS sur TLS ou DNS sur HTTPS ; comme pour les métriques, une
+    /// erreur d'écoute n'arrête que ce service
+    async fn run_tls(self: Arc<Self>, transport: Transport) {
+        let service = if transport == Transport::Tls { &self.dot } else { &self.doh };
+        let Some((listener, acceptor)) = service else {
+            return;
+        };
+        let listener_addr = match listener.local_addr() {
+            Ok(addr) => addr,
+            Err(e) => {
+                eprintln!("Erreur d'écoute {}: {}", transport, e);
+                return;
             }
+        };
+        loop {
+            let permit = Arc::clone(&self.connections).acquire_owned().await.expect("sémaphore jamais fermé");
+            let (stream, client_addr) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    eprintln!("Erreur d'écoute {}: {}", transport, e);
+                    return;
+                }
+            };
+            let server_addr = stream.local_addr().unwrap_or(listener_addr);
+            
+            let server = Arc::clone(&self);
+            let acceptor = acceptor.clone();
+            tokio::spawn(async move {
+                // La poignée de main compte dans le délai d'inactivité
+                let result = match timeout(TCP_IDLE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) if transport == Transport::Tls => {
+                        server.handle_tcp_connection(stream, client_addr, server_addr, transport).await
+                    }
+                    Ok(Ok(stream)) => server.handle_doh_connection(stream, client_addr, server_addr).await,
+                    Ok(Err(e)) => Err(format!("poignée de main TLS: {}", e).into()),
+                    Err(_) => Ok(()),
+                };
+                if let Err(e) = result {
+                    eprintln!("Erreur sur la connexion {} de {}: {}", transport, client_addr, e);
+                }
+                server.capture_closed(client_addr, server_addr);
+                drop(permit);
+            });
+        }
+    }
+
+    /// Sert les pages de métriques ; une erreur n'arrête que ce service
+    async fn run_metrics(self: Arc<Self>) {
+        let Some(listener) = &self.metrics_listener else {
+            return;
+        };
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    eprintln!("Erreur du point d'accès des métriques: {}", e);
+                    return;
+                }
+            };
+            let server = Arc::clone(&self);
+            tokio::spawn(async move {
+                let page = || server.metrics_page();
+                if let Err(e) = metrics::serve_http(stream, page).await {
+                    eprintln!("Erreur HTTP: {}", e);
+                }
+            });
         }
     }
 
-    async fn handle_query(&self, data: &[u8], client_addr: SocketAddr) -> Result<(), Box<dyn std::error::Error>> {
-        // Parser la requête DNS
-        let query = DnsMessage::from_bytes(data)?;
+    /// Recharge les listes de blocage dès que leurs fichiers changent
+    async fn run_blocklist_reload(self: Arc<Self>) {
+        let Some(blocklist) = &self.blocklist else {
+            return;
+        };
+        loop {
+            tokio::time::sleep(BLOCKLIST_RELOAD_INTERVAL).await;
+            match blocklist.reload_if_changed() {
+                Ok(true) => println!("Listes de blocage rechargées: {} nom(s) bloqué(s)", blocklist.len()),
+                Ok(false) => {}
+                Err(e) => println!("Listes de blocage non rechargées, les anciennes règles restent: {}", e),
+            }
+        }
+    }
+
+    /// Signe de nouveau les zones signées : le numéro de série change pour
+    /// que les secondaires reprennent les nouvelles signatures
+    async fn run_resign(self: Arc<Self>) {
+        loop {
+            tokio::time::sleep(RESIGN_INTERVAL).await;
+            for (zone, _) in &self.dnssec_keys {
+                let result = self.update_store(|store, updated| {
+                    updated.increment_serial(zone);
+                    // Sans trace au journal, le numéro de série reculerait au
+                    // redémarrage et les secondaires ignoreraient le primaire
+                    if let (Some(journal), Some(soa)) = (&self.journal, updated.soa(zone)) {
+                        update::journal_soa(journal, zone, soa)?;
+                    }
+                    self.sign_zone(updated, zone);
+                    updated.record_change(zone, store);
+                    Ok::<_, std::io::Error>(())
+                });
+                match result {
+                    Ok(()) => {
+                        println!("Zone {}. signée de nouveau", zone);
+                        self.notify_secondaries(zone);
+                    }
+                    Err(e) => println!("Zone {}. non signée de nouveau, écriture du journal impossible: {}", zone, e),
+                }
+            }
+        }
+    }
+
+    /// Applique `change` à une copie des zones, qu'il reçoit avec les zones
+    /// actuelles, puis met la copie en place s'il réussit. Les modifications
+    /// passent une à une ; la copie, la signature et le journal se font sous
+    /// le seul verrou de lecture, et les requêtes n'attendent que l'échange.
+    fn update_store<T, E>(&self, change: impl FnOnce(&RecordStore, &mut RecordStore) -> Result<T, E>) -> Result<T, E> {
+        let _writer = self.store_writer.lock().unwrap_or_else(|e| e.into_inner());
+        let store = self.store.read().unwrap_or_else(|e| e.into_inner());
+        let mut updated = store.clone();
+        let result = change(&store, &mut updated)?;
+        drop(store);
+        *self.store.write().unwrap_or_else(|e| e.into_inner()) = updated;
+        Ok(result)
+    }
+
+    /// Signe `zone` dans `store` avec ses clés, s'il en a ; en cas d'échec,
+    /// les anciennes signatures restent
+    fn sign_zone(&self, store: &mut RecordStore, zone: &str) {
+        let Some((_, keys)) = self.dnssec_keys.iter().find(|(origin, _)| origin.eq_ignore_ascii_case(zone)) else {
+            return;
+        };
+        let mut signed = store.clone();
+        match dnssec::sign_zone(&mut signed, zone, keys, dnssec::now()) {
+            Ok(_) => *store = signed,
+            Err(e) => println!("  Signature de {}. impossible: {}", zone, e),
+        }
+    }
+
+    /// Page de métriques, avec les compteurs du cache, de la limitation et
+    /// du blocage
+    fn metrics_page(&self) -> String {
+        let (hits, misses) = self.cache.stats();
+        let (dropped, slipped) = self.rate_limiter.as_ref().map_or((0, 0), RateLimiter::stats);
+        let blocked = self.blocklist.as_ref().map_or(0, Blocklist::blocked);
+        self.metrics.render(&[
+            ("tp7_cache_hits_total", "Succès du cache", hits),
+            ("tp7_cache_misses_total", "Échecs du cache", misses),
+            ("tp7_rate_limit_dropped_total", "Réponses supprimées par la limitation du débit", dropped),
+            ("tp7_rate_limit_slipped_total", "Réponses tronquées par la limitation du débit", slipped),
+            ("tp7_blocked_total", "Requêtes portant sur un nom bloqué", blocked),
+        ])
+    }
+
+    async fn handle_query(
+        &self,
+        socket: &UdpSocket,
+        data: &[u8],
+        client_addr: SocketAddr,
+        server_addr: SocketAddr,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.capture(Transport::Udp, client_addr, server_addr, data);
+        
+        // La limitation passe avant tout traitement : un client limité ne
+        // déclenche pas de requête vers les serveurs amont
+        let verdict = self.rate_limiter.as_ref().map_or(Verdict::Allow, |limiter| limiter.check(client_addr.ip()));
+        match verdict {
+            Verdict::Allow => {}
+            Verdict::Drop => return Ok(()),
+            Verdict::Slip => {
+                // Réponse vide avec le bit TC : un vrai client repassera en TCP
+                let query = DnsMessage::from_bytes(data).ok().filter(|query| !query.header.flags.qr);
+                if let Some(query) = query {
+                    let response = DnsMessage::new_response(&query, vec![]).truncated().to_bytes();
+                    socket.send_to(&response, client_addr).await?;
+                    self.capture(Transport::Udp, server_addr, client_addr, &response);
+                }
+                return Ok(());
+            }
+        }
+        
+        let response = self.process(data, client_addr, Transport::Udp).await?;
+        let (response, max_size) = match response {
+            Some(response) => response,
+            None => return Ok(()),
+        };
+        
+        // Une réponse trop grande pour le client est tronquée : il la
+        // redemandera en TCP
+        let mut response_bytes = response.to_bytes();
+        if response_bytes.len() > max_size {
+            println!("  Réponse de {} octets tronquée (TC)", response_bytes.len());
+            response_bytes = response.truncated().to_bytes();
+        }
+
+        // Envoyer la réponse
+        socket.send_to(&response_bytes, client_addr).await?;
+        self.capture(Transport::Udp, server_addr, client_addr, &response_bytes);
+        println!("  Réponse {} envoyée à {}", response.rcode(), client_addr);
         
+        Ok(())
+    }
+
+    /// Sert les requêtes successives d'une connexion TCP, éventuellement
+    /// chiffrée par TLS, jusqu'à sa fermeture
+    async fn handle_tcp_connection<S: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        mut stream: S,
+        client_addr: SocketAddr,
+        server_addr: SocketAddr,
+        transport: Transport,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        loop {
+            let data = match timeout(TCP_IDLE_TIMEOUT, tcp::read_message(&mut stream)).await {
+                Ok(Ok(Some(data))) => data,
+                Ok(Ok(None)) | Err(_) => return Ok(()),
+                Ok(Err(e)) => return Err(e.into()),
+            };
+            // Un jeton par requête, rendu une fois la réponse écrite
+            let _permit = self.in_flight.acquire().await.expect("sémaphore jamais fermé");
+            self.capture(transport, client_addr, server_addr, &data);
+            
+            // Les transferts de zone peuvent occuper plusieurs messages
+            let started = Instant::now();
+            if let Some(messages) = self.answer_transfer(&data, client_addr) {
+                if let Some(first) = messages.first() {
+                    self.record_query(QueryEvent {
+                        client: client_addr,
+                        transport,
+                        response: first,
+                        latency: started.elapsed(),
+                        cache_hit: false,
+                    });
+                }
+                for message in &messages {
+                    let bytes = message.to_bytes();
+                    tcp::write_message(&mut stream, &bytes).await?;
+                    self.capture(transport, server_addr, client_addr, &bytes);
+                }
+                println!("  Transfert envoyé à {} ({} message(s))", client_addr, messages.len());
+                continue;
+            }
+            
+            let response = self.process(&data, client_addr, transport).await?;
+            if let Some((response, _)) = response {
+                let bytes = response.to_bytes();
+                tcp::write_message(&mut stream, &bytes).await?;
+                self.capture(transport, server_addr, client_addr, &bytes);
+                println!("  Réponse {} envoyée à {} ({})", response.rcode(), client_addr, transport);
+            }
+        }
+    }
+
+    /// Sert les requêtes HTTP successives d'une connexion DoH : le message
+    /// DNS arrive dans l'URL (GET) ou dans le corps (POST), et la réponse
+    /// peut être mise en cache jusqu'à l'expiration de son plus petit TTL
+    async fn handle_doh_connection<S: AsyncRead + AsyncWrite + Unpin>(
+        &self,
+        mut stream: S,
+        client_addr: SocketAddr,
+        server_addr: SocketAddr,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut buffer = Vec::new();
+        loop {
+            let request = match timeout(TCP_IDLE_TIMEOUT, doh::read_message(&mut stream, &mut buffer)).await {
+                Ok(Ok(Some(request))) => request,
+                Ok(Ok(None)) | Err(_) => return Ok(()),
+                Ok(Err(e)) => {
+                    let _ = doh::write_response(&mut stream, "400 Bad Request", &[], None).await;
+                    return Err(e.into());
+                }
+            };
+            
+            let _permit = self.in_flight.acquire().await.expect("sémaphore jamais fermé");
+            let response = match doh::query_from_request(&request, doh::DEFAULT_PATH) {
+                Ok(data) => {
+                    self.capture(Transport::Https, client_addr, server_addr, &data);
+                    self.process(&data, client_addr, Transport::Https).await?
+                }
+                Err(status) => {
+                    println!("Requête HTTP refusée de {}: {} ({})", client_addr, status, request.start_line);
+                    doh::write_response(&mut stream, status, &[], None).await?;
+                    None
+                }
+            };
+            if let Some((response, _)) = response {
+                let max_age = response.answers.iter().chain(&response.authorities).map(|record| record.ttl).min();
+                let bytes = response.to_bytes();
+                doh::write_response(&mut stream, "200 OK", &bytes, Some(max_age.unwrap_or(0))).await?;
+                self.capture(Transport::Https, server_addr, client_addr, &bytes);
+                println!("  Réponse {} envoyée à {} (https)", response.rcode(), client_addr);
+            }
+            if request.closes_connection() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Analyse une requête et prépare sa réponse, quel que soit le transport,
+    /// avec la taille de réponse UDP acceptée par le client ; la requête est
+    /// comptée et journalisée.
+    ///
+    /// Renvoie `None` pour les messages auxquels il ne faut pas répondre.
+    async fn process(&self, data: &[u8], client_addr: SocketAddr, transport: Transport) -> Result<Option<(DnsMessage, usize)>, Box<dyn std::error::Error>> {
+        let started = Instant::now();
+        
+        // Parser la requête DNS (l'erreur est convertie en texte pour pouvoir
+        // être conservée à travers les `await`)
+        let query = match DnsMessage::from_bytes(data).map_err(|e| e.to_string()) {
+            Ok(query) => query,
+            Err(e) => {
+                // Sans en-tête lisible, on ne peut même pas répondre
+                let header = DnsHeader::from_bytes(data)?;
+                if header.flags.qr {
+                    return Err(e.into());
+                }
+                println!("Requête invalide de {} (ID: {}): {}", client_addr, header.id, e);
+                let response = DnsMessage::new_error(&header, ResponseCode::FormErr);
+                self.record_query(QueryEvent { client: client_addr, transport, response: &response, latency: started.elapsed(), cache_hit: false });
+                return Ok(Some((response, MAX_UDP_PAYLOAD)));
+            }
+        };
         println!("Requête reçue de {} (ID: {})", client_addr, query.header.id);
         
-        if query.questions.is_empty() {
-            return Err("Aucune question dans la requête".into());
+        // Ne jamais répondre à une réponse
+        if query.header.flags.qr {
+            return Err("Message reçu avec le bit QR".into());
+        }
+        
+        let (mut response, cache_hit) = match &query.edns {
+            Some(edns) if edns.version > 0 => {
+                println!("  Version EDNS non supportée: {}", edns.version);
+                (DnsMessage::new_error(&query.header, ResponseCode::BadVers), false)
+            }
+            _ => self.answer(&query, client_addr).await,
+        };
+        
+        // Un client EDNS reçoit toujours notre propre OPT, qui reprend son
+        // bit DO (RFC 3225 §3)
+        let mut max_size = MAX_UDP_PAYLOAD;
+        if let Some(edns) = &query.edns {
+            let rcode = response.rcode();
+            response.edns = Some(Edns { dnssec_ok: edns.dnssec_ok, ..Edns::new(MAX_EDNS_PAYLOAD) });
+            response.set_rcode(rcode);
+            max_size = edns.payload_size.clamp(MAX_UDP_PAYLOAD as u16, MAX_EDNS_PAYLOAD) as usize;
+        }
+        
+        self.record_query(QueryEvent { client: client_addr, transport, response: &response, latency: started.elapsed(), cache_hit });
+        Ok(Some((response, max_size)))
+    }
+
+    /// Compte la requête et l'ajoute au journal des requêtes s'il y en a un
+    fn record_query(&self, event: QueryEvent) {
+        self.metrics.record(&event);
+        if let Some(log) = &self.query_log
+            && let Err(e) = log.write(&event)
+        {
+            eprintln!("Erreur d'écriture du journal des requêtes: {}", e);
+        }
+    }
+
+    /// Recopie un message dans la capture s'il y en a une ; comme pour le
+    /// journal, une erreur d'écriture n'interrompt pas le service
+    fn capture(&self, transport: Transport, source: SocketAddr, destination: SocketAddr, message: &[u8]) {
+        if let Some(capture) = &self.capture
+            && let Err(e) = capture.write_message(transport, source, destination, message)
+        {
+            eprintln!("Erreur d'écriture de la capture: {}", e);
+        }
+    }
+
+    /// Termine dans la capture une connexion TCP, TLS ou HTTPS fermée
+    fn capture_closed(&self, client_addr: SocketAddr, server_addr: SocketAddr) {
+        if let Some(capture) = &self.capture {
+            capture.close_connection(client_addr, server_addr);
+        }
+    }
+
+    /// Construit la réponse à une requête déjà analysée ; le booléen indique
+    /// qu'elle vient du cache
+    async fn answer(&self, query: &DnsMessage, client_addr: SocketAddr) -> (DnsMessage, bool) {
+        match query.header.flags.opcode {
+            Opcode::Query => {}
+            Opcode::Update => return (self.answer_update(query, client_addr), false),
+            Opcode::Notify => return (self.answer_notify(query, client_addr), false),
+            opcode => {
+                println!("  Opcode non supporté: {}", opcode);
+                return (DnsMessage::new_error(&query.header, ResponseCode::NotImp), false);
+            }
+        }
+        
+        if query.questions.len() != 1 {
+            println!("  Nombre de questions invalide: {}", query.questions.len());
+            return (DnsMessage::new_error(&query.header, ResponseCode::FormErr), false);
         }
         
         let question = &query.questions[0];
-        println!("  Question: {} (type: {})", question.name, question.qtype);
+        println!("  Question: {} (type: {})", question.name, RecordType::from(question.qtype));
+        
+        // Les listes de blocage passent avant nos zones et les serveurs amont
+        if let Some(blocklist) = &self.blocklist && let Some(rule) = blocklist.find(&question.name) {
+            println!("  Bloqué par la règle {}", rule);
+            let mut response = blocklist.answer(query);
+            response.header.flags.ra = !self.forwarders.is_empty();
+            return (response, false);
+        }
         
-        // Chercher l'enregistrement
-        let mut answers = Vec::new();
+        // Le verrou est relâché avant de consulter les serveurs amont
+        let local = {
+            let store = self.store.read().unwrap_or_else(|e| e.into_inner());
+            let dnssec_ok = query.edns.as_ref().is_some_and(|edns| edns.dnssec_ok);
+            self.synthesize_ptr(query, &store).or_else(|| {
+                Self::authoritative_zone(&store, question).map(|origin| Self::answer_from_zone(query, &store, origin, dnssec_ok))
+            })
+        };
         
-        if question.qtype == 1 { // A record
-            if let Some(ip) = self.records.get(&question.name) {
-                let answer = DnsAnswer::new(question.name.clone(), *ip);
-                answers.push(answer);
-                println!("  Réponse: {}.{}.{}.{}", ip[0], ip[1], ip[2], ip[3]);
-            } else {
-                println!("  Domaine non trouvé: {}", question.name);
+        let mut cache_hit = false;
+        let mut response = match local {
+            Some(response) => response,
+            // Zone secondaire pas encore transférée, ou expirée
+            None if self.secondaries.iter().any(|secondary| is_subdomain(&question.name, &secondary.origin)) => {
+                println!("  Zone secondaire indisponible pour {}", question.name);
+                let mut response = DnsMessage::new_response(query, vec![]);
+                response.header.flags.rcode = ResponseCode::ServFail;
+                response
             }
+            None if !self.forwarders.is_empty() && query.header.flags.rd => {
+                let (response, from_cache) = self.answer_recursive(query).await;
+                cache_hit = from_cache;
+                response
+            }
+            None => {
+                println!("  Hors de nos zones: {}", question.name);
+                let mut response = DnsMessage::new_response(query, vec![]);
+                response.header.flags.rcode = ResponseCode::Refused;
+                response
+            }
+        };
+        response.header.flags.ra = !self.forwarders.is_empty();
+        (response, cache_hit)
+    }
+
+    /// PTR synthétisés à partir des enregistrements A et AAAA portant
+    /// l'adresse demandée.
+    ///
+    /// Des PTR explicites pour ce nom (zone inverse chargée ou mise à jour
+    /// dynamique) ont priorité, et les zones listées par `--no-reverse` ne
+    /// sont pas consultées ; `None` laisse la requête suivre son cours.
+    fn synthesize_ptr(&self, query: &DnsMessage, store: &RecordStore) -> Option<DnsMessage> {
+        let question = &query.questions[0];
+        if RecordType::from(question.qtype) != RecordType::PTR {
+            return None;
+        }
+        let addr = reverse::parse_reverse_name(&question.name)?;
+        if !store.rrset(&question.name, RecordType::PTR).is_empty() {
+            return None;
+        }
+        
+        let answers: Vec<DnsAnswer> = store
+            .records()
+            .filter(|record| match record.rdata {
+                RecordData::A(ip) => IpAddr::V4(ip) == addr,
+                RecordData::AAAA(ip) => IpAddr::V6(ip) == addr,
+                _ => false,
+            })
+            .filter(|record| {
+                let zone = store.find_zone(&record.name).unwrap_or_default();
+                !self.no_reverse.iter().any(|disabled| disabled.eq_ignore_ascii_case(zone))
+            })
+            .map(|record| DnsAnswer::with_ttl(question.name.clone(), record.ttl, RecordData::PTR(record.name.clone())))
+            .collect();
+        if answers.is_empty() {
+            return None;
         }
+        
+        for answer in &answers {
+            println!("  Réponse synthétisée: PTR {}", answer.rdata);
+        }
+        let mut response = DnsMessage::new_response(query, answers);
+        response.header.flags.aa = true;
+        Some(response)
+    }
 
-        // Créer la réponse
-        let response = DnsMessage::new_response(&query, answers);
-        let response_bytes = response.to_bytes();
+    /// Zone qui répond à la question : la plus spécifique, sauf pour le DS
+    /// d'un sommet, qui appartient à la zone parente quand on la sert aussi
+    /// (RFC 4035 §3.1.4.1)
+    fn authoritative_zone<'a>(store: &'a RecordStore, question: &DnsQuestion) -> Option<&'a str> {
+        let origin = store.find_zone(&question.name)?;
+        if question.qtype == RecordType::DS.to_u16() && origin.eq_ignore_ascii_case(&question.name) && !origin.is_empty() {
+            let parent = origin.split_once('.').map_or("", |(_, parent)| parent);
+            if let Some(parent_origin) = store.find_zone(parent) {
+                return Some(parent_origin);
+            }
+        }
+        Some(origin)
+    }
 
-        // Envoyer la réponse
-        self.socket.send_to(&response_bytes, client_addr).await?;
-        println!("  Réponse envoyée à {}", client_addr);
+    /// Preuve de non-existence de `name` jointe en section autorité quand le
+    /// client a mis le bit DO : signature du SOA et NSEC
+    fn add_denial(response: &mut DnsMessage, store: &RecordStore, origin: &str, name: &str) {
+        response.authorities.extend(dnssec::signatures(store, origin, store.get(origin), RecordType::SOA));
+        response.authorities.extend(dnssec::denial(store, origin, name));
+    }
+
+    /// Réponse faisant autorité à partir des enregistrements de la zone
+    /// `origin` ; avec `dnssec_ok`, les RRSIG et les preuves NSEC sont jointes
+    fn answer_from_zone(query: &DnsMessage, store: &RecordStore, origin: &str, dnssec_ok: bool) -> DnsMessage {
+        let mut response = DnsMessage::new_response(query, vec![]);
+        response.header.flags.aa = true;
+        let question = &query.questions[0];
+        let qtype = RecordType::from(question.qtype);
         
-        Ok(())
+        // Les transferts passent par TCP (voir `answer_transfer`) ; en UDP,
+        // un IXFR reçoit le SOA seul, qui invite le client à passer en TCP
+        // (RFC 1995 §2), et un AXFR est refusé
+        match qtype {
+            RecordType::AXFR => {
+                response.header.flags.rcode = ResponseCode::FormErr;
+                return response;
+            }
+            RecordType::IXFR => {
+                response.answers.extend(store.soa(origin).cloned());
+                return response;
+            }
+            _ => {}
+        }
+        
+        // Suivre les CNAME tant que leur cible est dans nos zones ; le code
+        // de réponse et le SOA négatif portent sur le dernier nom de la chaîne
+        let mut name = question.name.clone();
+        let mut origin = origin.to_string();
+        let mut visited = vec![name.to_ascii_lowercase()];
+        loop {
+            let Some(records) = store.find_node(&name, &origin) else {
+                println!("  Domaine non trouvé: {}", name);
+                response.header.flags.rcode = ResponseCode::NXDomain;
+                response.authorities.extend(store.negative_soa(&origin));
+                if dnssec_ok {
+                    Self::add_denial(&mut response, store, &origin, &name);
+                }
+                break;
+            };
+            // Au sommet d'une zone fille, le nom porte aussi des données de
+            // la parente (DS, NSEC, RRSIG) : seules celles de `origin` comptent
+            let records: Vec<DnsAnswer> = records
+                .into_iter()
+                .filter(|record| store.zone_of(record).is_some_and(|zone| zone.eq_ignore_ascii_case(&origin)))
+                .collect();
+            // Réponse synthétisée par un joker : le nom demandé n'existe pas
+            let synthesized = !store.name_exists(&name);
+
+            let matching: Vec<&DnsAnswer> = records
+                .iter()
+                .filter(|record| match qtype {
+                    RecordType::ANY => dnssec_ok || !matches!(record.rdata.record_type(), RecordType::RRSIG | RecordType::NSEC),
+                    qtype => record.rdata.record_type() == qtype,
+                })
+                .collect();
+            if !matching.is_empty() {
+                for record in matching {
+                    println!("  Réponse: {} {}", record.rdata.record_type(), record.rdata);
+                    response.answers.push(record.clone());
+                }
+                if dnssec_ok && qtype != RecordType::ANY {
+                    response.answers.extend(dnssec::signatures(store, &origin, &records, qtype));
+                }
+                if dnssec_ok && synthesized {
+                    response.authorities.extend(dnssec::denial(store, &origin, &name));
+                }
+                break;
+            }
+
+            let cname = records.iter().find(|record| record.rdata.record_type() == RecordType::CNAME);
+            let Some(cname @ DnsAnswer { rdata: RecordData::CNAME(target), .. }) = cname else {
+                println!("  Aucun enregistrement {} pour {}", qtype, name);
+                response.authorities.extend(store.negative_soa(&origin));
+                if dnssec_ok {
+                    Self::add_denial(&mut response, store, &origin, &name);
+                }
+                break;
+            };
+            println!("  Réponse: CNAME {}", target);
+            response.answers.push(cname.clone());
+            if dnssec_ok {
+                response.answers.extend(dnssec::signatures(store, &origin, &records, RecordType::CNAME));
+                if synthesized {
+                    response.authorities.extend(dnssec::denial(store, &origin, &name));
+                }
+            }
+
+            // Boucle ou chaîne trop longue : on s'arrête sur ce qu'on a
+            let target = target.trim_end_matches('.').to_string();
+            if visited.contains(&target.to_ascii_lowercase()) || visited.len() >= MAX_CNAME_CHAIN {
+                println!("  Chaîne de CNAME interrompue à {}", target);
+                break;
+            }
+            // Cible hors de nos zones : le client la résoudra lui-même
+            let Some(target_origin) = store.find_zone(&target) else {
+                break;
+            };
+            origin = target_origin.to_string();
+            visited.push(target.to_ascii_lowercase());
+            name = target;
+        }
+        
+        response
+    }
+
+    /// Mise à jour dynamique (RFC 2136) : les prérequis sont vérifiés et les
+    /// modifications appliquées sur une copie de la zone, qui ne remplace
+    /// l'originale qu'une fois la mise à jour inscrite au journal
+    fn answer_update(&self, query: &DnsMessage, client_addr: SocketAddr) -> DnsMessage {
+        let mut response = DnsMessage::new_response(query, vec![]);
+        
+        // Section zone : exactement une entrée, de type SOA
+        let zone = match query.questions.as_slice() {
+            [zone] if zone.qtype == RecordType::SOA.to_u16() => zone.name.as_str(),
+            _ => {
+                println!("  Section zone invalide");
+                response.header.flags.rcode = ResponseCode::FormErr;
+                return response;
+            }
+        };
+        println!("  Mise à jour de {}. depuis {}", zone, client_addr);
+        
+        let allowed = self.update_acl.iter().any(|(origin, range)| {
+            origin.eq_ignore_ascii_case(zone) && range.contains(client_addr.ip())
+        });
+        
+        // Une mise à jour sans effet remet en place une copie identique
+        let result = self.update_store(|store, updated| {
+            if !store.is_origin(zone) {
+                println!("  Zone non servie: {}", zone);
+                return Err(ResponseCode::NotAuth);
+            }
+            if !allowed {
+                println!("  Mise à jour refusée pour {}", client_addr);
+                return Err(ResponseCode::Refused);
+            }
+            
+            let result = update::check_prerequisites(updated, zone, &query.answers)
+                .and_then(|_| update::apply_updates(updated, zone, &query.authorities));
+            match result {
+                Ok(true) => {
+                    if let Some(journal) = &self.journal
+                        && let Err(e) = update::append_to_journal(journal, &query.to_bytes())
+                    {
+                        println!("  Écriture du journal impossible: {}", e);
+                        return Err(ResponseCode::ServFail);
+                    }
+                    self.sign_zone(updated, zone);
+                    updated.record_change(zone, store);
+                    Ok(true)
+                }
+                Ok(false) => Ok(false),
+                Err(rcode) => {
+                    println!("  Mise à jour rejetée: {}", rcode);
+                    Err(rcode)
+                }
+            }
+        });
+        
+        match result {
+            Ok(false) => println!("  Mise à jour sans effet"),
+            Ok(true) => {
+                println!("  Mise à jour appliquée ({} modification(s))", query.authorities.len());
+                self.notify_secondaries(zone);
+            }
+            Err(rcode) => response.header.flags.rcode = rcode,
+        }
+        
+        response
+    }
+
+    /// Réponse à un transfert de zone (AXFR ou IXFR) reçu en TCP, découpée
+    /// en messages ; `None` pour toute autre requête
+    fn answer_transfer(&self, data: &[u8], client_addr: SocketAddr) -> Option<Vec<DnsMessage>> {
+        let query = DnsMessage::from_bytes(data).ok()?;
+        if query.header.flags.qr || query.header.flags.opcode != Opcode::Query {
+            return None;
+        }
+        let question = match query.questions.as_slice() {
+            [question] => question,
+            _ => return None,
+        };
+        let qtype = RecordType::from(question.qtype);
+        if qtype != RecordType::AXFR && qtype != RecordType::IXFR {
+            return None;
+        }
+        
+        println!("Transfert {} de {}. demandé par {}", qtype, question.name, client_addr);
+        let mut error = DnsMessage::new_response(&query, vec![]);
+        
+        let store = self.store.read().unwrap_or_else(|e| e.into_inner());
+        if !store.is_origin(&question.name) {
+            println!("  Zone non servie: {}", question.name);
+            error.header.flags.rcode = ResponseCode::NotAuth;
+            return Some(vec![error]);
+        }
+        // Contrôle d'accès des transferts (RFC 5936 §6)
+        let allowed = self.transfer_acl.iter().any(|(origin, range)| {
+            origin.eq_ignore_ascii_case(&question.name) && range.contains(client_addr.ip())
+        });
+        if !allowed {
+            println!("  Transfert refusé pour {}", client_addr);
+            error.header.flags.rcode = ResponseCode::Refused;
+            return Some(vec![error]);
+        }
+        
+        let records = match qtype {
+            RecordType::AXFR => transfer::axfr_records(&store, &question.name),
+            // La version du client est dans le SOA de la section autorité
+            _ => match query.authorities.iter().find_map(soa_serial) {
+                Some(serial) => transfer::ixfr_records(&store, &question.name, serial),
+                None => {
+                    error.header.flags.rcode = ResponseCode::FormErr;
+                    return Some(vec![error]);
+                }
+            },
+        };
+        
+        match records {
+            Some(records) => {
+                println!("  {} enregistrement(s) transférés", records.len());
+                Some(transfer::split_messages(&query, records))
+            }
+            None => {
+                error.header.flags.rcode = ResponseCode::ServFail;
+                Some(vec![error])
+            }
+        }
     }
+
+    /// NOTIFY (RFC 1996) : le primaire d'une de nos zones secondaires
+    /// annonce un changement, la zone est rafraîchie sans attendre
+    fn answer_notify(&self, query: &DnsMessage, client_addr: SocketAddr) -> DnsMessage {
+        let mut response = DnsMessage::new_response(query, vec![]);
+        response.header.flags.aa = true;
+        
+        let zone = match query.questions.as_slice() {
+            [zone] if zone.qtype == RecordType::SOA.to_u16() => zone.name.as_str(),
+            _ => {
+                response.header.flags.rcode = ResponseCode::FormErr;
+                return response;
+            }
+        };
+        println!("  NOTIFY pour {}. depuis {}", zone, client_addr);
+        
+        match self.secondaries.iter().find(|secondary| secondary.origin.eq_ignore_ascii_case(zone)) {
+            None => response.header.flags.rcode = ResponseCode::NotAuth,
+            // Seul le primaire peut déclencher un rafraîchissement
+            Some(secondary) if secondary.primary.ip() != client_addr.ip() => {
+                response.header.flags.rcode = ResponseCode::Refused;
+            }
+            Some(secondary) => secondary.refresh_now.notify_one(),
+        }
+        response
+    }
+
+    /// Prévient les serveurs secondaires configurés que `zone` a changé
+    fn notify_secondaries(&self, zone: &str) {
+        for &secondary in &self.notify {
+            let zone = zone.to_string();
+            tokio::spawn(async move {
+                let result = match DnsClient::new(secondary).await {
+                    Ok(client) => client.notify(&zone).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    println!("NOTIFY de {}. vers {} sans réponse: {}", zone, secondary, e);
+                }
+            });
+        }
+    }
+
+    /// Boucle de rafraîchissement d'une zone secondaire, rythmée par les
+    /// champs REFRESH, RETRY et EXPIRE de son SOA (RFC 1034 §4.3.5)
+    async fn run_secondary(self: Arc<Self>, index: usize) {
+        let secondary = &self.secondaries[index];
+        let origin = secondary.origin.as_str();
+        let mut last_refresh: Option<Instant> = None;
+        
+        loop {
+            let result = self.refresh_zone(secondary).await;
+            let timers = self.store.read().unwrap_or_else(|e| e.into_inner()).soa(origin).and_then(|soa| match soa.rdata {
+                RecordData::SOA { refresh, retry, expire, .. } => Some((refresh, retry, expire)),
+                _ => None,
+            });
+            
+            let delay = match (result, timers) {
+                (Ok(changed), Some((refresh, _, _))) => {
+                    last_refresh = Some(Instant::now());
+                    if changed {
+                        self.notify_secondaries(origin);
+                    }
+                    Duration::from_secs(refresh as u64)
+                }
+                (Ok(_), None) => SECONDARY_INITIAL_RETRY,
+                (Err(e), Some((_, retry, expire))) => {
+                    println!("Zone secondaire {}.: échec du rafraîchissement depuis {}: {}", origin, secondary.primary, e);
+                    // Sans nouvelles du primaire depuis EXPIRE, la zone n'est plus servie
+                    if last_refresh.is_some_and(|last| last.elapsed() >= Duration::from_secs(expire as u64)) {
+                        println!("Zone secondaire {}.: expirée", origin);
+                        let _ = self.update_store(|_, updated| {
+                            updated.remove_zone(origin);
+                            Ok::<_, ()>(())
+                        });
+                        last_refresh = None;
+                        SECONDARY_INITIAL_RETRY
+                    } else {
+                        Duration::from_secs(retry as u64)
+                    }
+                }
+                (Err(e), None) => {
+                    println!("Zone secondaire {}.: échec du transfert depuis {}: {}", origin, secondary.primary, e);
+                    SECONDARY_INITIAL_RETRY
+                }
+            };
+            
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = secondary.refresh_now.notified() => println!("Zone secondaire {}.: rafraîchissement sur NOTIFY", origin),
+            }
+        }
+    }
+
+    /// Compare le numéro de série du primaire au nôtre et transfère la zone
+    /// s'il est plus récent ; renvoie vrai si la zone a changé
+    async fn refresh_zone(&self, secondary: &SecondaryZone) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let origin = secondary.origin.as_str();
+        let mut client = DnsClient::new(secondary.primary).await?;
+        client.set_timeout(FORWARD_TIMEOUT);
+        client.set_retries(1);
+        
+        let current = self.store.read().unwrap_or_else(|e| e.into_inner()).soa(origin).cloned();
+        if let Some(current) = &current {
+            let reply = client.query(origin, RecordType::SOA).await?;
+            let serial = reply.answers.iter().find_map(soa_serial).ok_or("pas de SOA dans la réponse du primaire")?;
+            if !soa_serial(current).is_some_and(|ours| serial_newer(serial, ours)) {
+                return Ok(false);
+            }
+        }
+        
+        let transfer = client.transfer(origin, current.as_ref()).await?;
+        
+        self.update_store(|store, updated| {
+            match transfer {
+                Transfer::UpToDate => return Ok(false),
+                Transfer::Full(records) => {
+                    if let Some(outside) = records.iter().find(|record| !is_subdomain(&record.name, origin)) {
+                        return Err(format!("{} est en dehors de la zone {}", outside.name, origin).into());
+                    }
+                    println!("Zone secondaire {}.: transfert complet ({} enregistrements)", origin, records.len());
+                    updated.load_zone(origin, records);
+                }
+                Transfer::Incremental(diffs) => {
+                    let ours = current.as_ref().and_then(soa_serial);
+                    if diffs.first().and_then(|diff| soa_serial(&diff.old_soa)) != ours {
+                        return Err("le transfert incrémental ne part pas de notre version".into());
+                    }
+                    println!("Zone secondaire {}.: transfert incrémental ({} version(s))", origin, diffs.len());
+                    updated.apply_diffs(origin, &diffs);
+                }
+            }
+            updated.record_change(origin, store);
+            Ok(true)
+        })
+    }
+
+    /// Réponse obtenue du cache ou, à défaut, des serveurs amont ; le booléen
+    /// indique qu'elle vient du cache
+    async fn answer_recursive(&self, query: &DnsMessage) -> (DnsMessage, bool) {
+        let mut response = DnsMessage::new_response(query, vec![]);
+        let question = &query.questions[0];
+        
+        let cached = self.cache.get(&question.name, question.qtype);
+        let cache_hit = cached.is_some();
+        let cached = match cached {
+            Some(cached) => {
+                println!("  Réponse trouvée dans le cache");
+                cached
+            }
+            None => match self.forward(question).await {
+                Ok(upstream) => {
+                    let cached = CachedResponse {
+                        rcode: upstream.rcode(),
+                        answers: upstream.answers,
+                        authorities: upstream.authorities,
+                    };
+                    self.cache.insert(&question.name, question.qtype, cached.clone());
+                    cached
+                }
+                Err(e) => {
+                    println!("  Échec auprès des serveurs amont: {}", e);
+                    response.header.flags.rcode = ResponseCode::ServFail;
+                    return (response, false);
+                }
+            },
+        };
+        
+        let (hits, misses) = self.cache.stats();
+        println!("  Cache: {} succès, {} échecs", hits, misses);
+        
+        response.set_rcode(cached.rcode);
+        response.answers = cached.answers;
+        response.authorities = cached.authorities;
+        (response, cache_hit)
+    }
+
+    /// Transmet la question aux serveurs amont, dans l'ordre, jusqu'à obtenir
+    /// une réponse exploitable
+    async fn forward(&self, question: &DnsQuestion) -> Result<DnsMessage, Box<dyn std::error::Error + Send + Sync>> {
+        let mut last_error: Box<dyn std::error::Error + Send + Sync> = "Aucun serveur amont".into();
+        
+        for upstream in &self.forwarders {
+            println!("  Requête transmise à {}", upstream);
+            let mut client = DnsClient::new(*upstream).await?;
+            client.set_timeout(FORWARD_TIMEOUT);
+            client.set_retries(1);
+            
+            let reply = match client.query(&question.name, RecordType::from(question.qtype)).await {
+                Ok(reply) => reply,
+                Err(e) => {
+                    last_error = e.into();
+                    continue;
+                }
+            };
+            
+            // Un serveur qui refuse ou échoue ne tranche pas : essayer le suivant
+            match reply.rcode() {
+                ResponseCode::NoError | ResponseCode::NXDomain => return Ok(reply),
+                rcode => last_error = format!("{} a répondu {}", upstream, rcode).into(),
+            }
+        }
+        
+        Err(last_error)
+    }
+}
+
+/// Socket UDP d'écoute ; en IPv6, limité à l'IPv6 pour que la même adresse
+/// puisse aussi être écoutée en IPv4, quel que soit le réglage du système
+fn bind_udp(addr: SocketAddr) -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    UdpSocket::from_std(socket.into())
 }
 
-pub async fn test_server() -> Result<(), Box<dyn std::error::Error>> {
-    let bind_addr = "127.0.0.1:5353".parse()?;
-    let server = DnsServer::new(bind_addr).await?;
+/// Écoute TCP, avec les mêmes règles que `bind_udp`
+fn bind_tcp(addr: SocketAddr) -> std::io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(TCP_BACKLOG)?;
+    TcpListener::from_std(socket.into())
+}
+
+/// Démarre le serveur décrit par les options de la ligne de commande, et
+/// le sert jusqu'à son arrêt
+pub async fn run_from_args(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let config = ServerConfig::from_args(args)?;
+    let server = Arc::new(DnsServer::new(config).await?);
     
     println!("=== Serveur DNS en écoute ===");
     println!("Utilisez Ctrl+C pour arrêter");
     
     server.run().await
-}
\ No newline at end of file
+}
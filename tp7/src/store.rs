@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use crate::dns_message::{DnsAnswer, RecordData, RecordType};
+use crate::zone::{Zone, is_subdomain};
+
+/// Nombre de versions conservées par zone pour les transferts incrémentaux
+const MAX_HISTORY: usize = 32;
+
+/// Différence entre deux versions successives d'une zone (RFC 1995)
+#[derive(Debug, Clone)]
+pub struct ZoneDiff {
+    pub old_soa: DnsAnswer,
+    pub new_soa: DnsAnswer,
+    /// Enregistrements retirés et ajoutés, SOA exclus
+    pub deleted: Vec<DnsAnswer>,
+    pub added: Vec<DnsAnswer>,
+}
+
+/// Enregistrements des zones servies, indexés par nom.
+///
+/// Le serveur le garde derrière un verrou : les mises à jour dynamiques
+/// travaillent sur une copie, qui remplace l'original d'un seul coup.
+#[derive(Debug, Clone, Default)]
+pub struct RecordStore {
+    /// Sommets des zones, sans point final
+    origins: Vec<String>,
+    /// Enregistrements par nom en minuscules (la casse ne compte pas dans
+    /// les comparaisons de noms, RFC 4343), tous types confondus
+    records: HashMap<String, Vec<DnsAnswer>>,
+    /// Nombre de noms porteurs d'enregistrements égaux à chaque nom ou sous
+    /// lui, en minuscules : un nom existe tant qu'il y figure
+    names: HashMap<String, usize>,
+    /// Dernières modifications de chaque zone, de la plus ancienne à la
+    /// plus récente
+    history: HashMap<String, Vec<ZoneDiff>>,
+}
+
+impl RecordStore {
+    pub fn new(zones: Vec<Zone>) -> Self {
+        let mut store = Self::default();
+        for zone in zones {
+            store.origins.push(zone.origin);
+            for record in zone.records {
+                store.add(record);
+            }
+        }
+        store
+    }
+
+    /// Sommet de la zone la plus spécifique contenant `name`
+    pub fn find_zone(&self, name: &str) -> Option<&str> {
+        self.origins
+            .iter()
+            .filter(|origin| is_subdomain(name, origin))
+            .max_by_key(|origin| origin.len())
+            .map(String::as_str)
+    }
+
+    /// Zone à laquelle appartient un enregistrement : celle de son nom, sauf
+    /// pour les données de la zone parente placées au sommet d'une zone fille
+    /// servie elle aussi (DS, et NSEC ou RRSIG de la parente, RFC 4035 §2.4)
+    pub fn zone_of(&self, record: &DnsAnswer) -> Option<&str> {
+        let zone = self.find_zone(&record.name)?;
+        let parent_side = match &record.rdata {
+            RecordData::DS { .. } => true,
+            // Le NSEC d'un sommet, et lui seul, annonce un SOA
+            RecordData::NSEC { types, .. } => !types.contains(&RecordType::SOA.to_u16()),
+            RecordData::RRSIG { signer, .. } => !signer.eq_ignore_ascii_case(zone),
+            _ => false,
+        };
+        if !parent_side || !zone.eq_ignore_ascii_case(&record.name) || zone.is_empty() {
+            return Some(zone);
+        }
+        let parent = zone.split_once('.').map_or("", |(_, parent)| parent);
+        self.find_zone(parent)
+    }
+
+    /// Sommets des zones servies
+    pub fn origins(&self) -> &[String] {
+        &self.origins
+    }
+
+    /// Indique si `name` est le sommet d'une zone servie
+    pub fn is_origin(&self, name: &str) -> bool {
+        self.origins.iter().any(|origin| origin.eq_ignore_ascii_case(name))
+    }
+
+    /// Tous les enregistrements de `name`
+    pub fn get(&self, name: &str) -> &[DnsAnswer] {
+        self.records.get(&name.to_ascii_lowercase()).map_or(&[], Vec::as_slice)
+    }
+
+    /// Enregistrements qui répondent pour `name` dans la zone `origin` :
+    /// ceux du nom lui-même s'il existe (éventuellement aucun pour un nœud
+    /// intermédiaire vide), sinon ceux du joker `*.<plus proche ancêtre>`
+    /// renommés en `name` (RFC 4592 §3.3). `None` si le nom n'existe pas.
+    pub fn find_node(&self, name: &str, origin: &str) -> Option<Vec<DnsAnswer>> {
+        if self.name_exists(name) {
+            return Some(self.get(name).to_vec());
+        }
+
+        let records = self.get(&self.source_of_synthesis(name, origin));
+        if records.is_empty() {
+            return None;
+        }
+        Some(
+            records
+                .iter()
+                .map(|record| DnsAnswer { name: name.to_string(), ..record.clone() })
+                .collect(),
+        )
+    }
+
+    /// Joker qui répondrait pour `name` s'il n'existe pas : `*.<plus proche
+    /// ancêtre existant>`, sans remonter au-dessus de la zone `origin`
+    pub fn source_of_synthesis(&self, name: &str, origin: &str) -> String {
+        let mut encloser = name;
+        loop {
+            encloser = match encloser.split_once('.') {
+                Some((_, parent)) => parent,
+                None => "",
+            };
+            if self.name_exists(encloser) || encloser.eq_ignore_ascii_case(origin) || encloser.is_empty() {
+                break;
+            }
+        }
+        if encloser.is_empty() { "*".to_string() } else { format!("*.{}", encloser) }
+    }
+
+    /// Enregistrements de `name` du type `rtype`
+    pub fn rrset(&self, name: &str, rtype: RecordType) -> Vec<&DnsAnswer> {
+        self.get(name).iter().filter(|record| record.rdata.record_type() == rtype).collect()
+    }
+
+    /// Un nom existe s'il porte des enregistrements ou s'il en a sous lui
+    /// (nœud intermédiaire vide, RFC 8020)
+    pub fn name_exists(&self, name: &str) -> bool {
+        self.names.contains_key(&name.to_ascii_lowercase())
+    }
+
+    /// Enregistrement SOA du sommet de la zone
+    pub fn soa(&self, origin: &str) -> Option<&DnsAnswer> {
+        self.rrset(origin, RecordType::SOA).first().copied()
+    }
+
+    /// SOA à placer en section autorité d'une réponse négative, avec le TTL
+    /// de cache négatif de la RFC 2308 (minimum du TTL et du champ MINIMUM)
+    pub fn negative_soa(&self, origin: &str) -> Option<DnsAnswer> {
+        let mut soa = self.soa(origin)?.clone();
+        if let RecordData::SOA { minimum, .. } = soa.rdata {
+            soa.ttl = soa.ttl.min(minimum);
+        }
+        Some(soa)
+    }
+
+    /// Ajoute un enregistrement ; un enregistrement identique (mêmes type et
+    /// données) est remplacé, ce qui revient à mettre à jour son TTL
+    pub fn add(&mut self, record: DnsAnswer) {
+        let key = record.name.to_ascii_lowercase();
+        if !self.records.contains_key(&key) {
+            for name in ancestors(&key) {
+                *self.names.entry(name.to_string()).or_default() += 1;
+            }
+        }
+        let records = self.records.entry(key).or_default();
+        match records.iter_mut().find(|existing| existing.rdata == record.rdata) {
+            Some(existing) => *existing = record,
+            None => records.push(record),
+        }
+    }
+
+    /// Retire les enregistrements de `name` pour lesquels `remove` est vrai ;
+    /// renvoie vrai si au moins un a été retiré
+    pub fn remove(&mut self, name: &str, remove: impl Fn(&DnsAnswer) -> bool) -> bool {
+        let key = name.to_ascii_lowercase();
+        let Some(records) = self.records.get_mut(&key) else {
+            return false;
+        };
+        let before = records.len();
+        records.retain(|record| !remove(record));
+        let removed = records.len() != before;
+        if records.is_empty() {
+            self.records.remove(&key);
+            for name in ancestors(&key) {
+                if let Some(count) = self.names.get_mut(name) {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.names.remove(name);
+                    }
+                }
+            }
+        }
+        removed
+    }
+
+    /// Incrémente le numéro de série du SOA de la zone (arithmétique de la
+    /// RFC 1982 : le compteur reboucle)
+    pub fn increment_serial(&mut self, origin: &str) {
+        if let Some(records) = self.records.get_mut(&origin.to_ascii_lowercase()) {
+            for record in records {
+                if let RecordData::SOA { serial, .. } = &mut record.rdata {
+                    *serial = serial.wrapping_add(1);
+                }
+            }
+        }
+    }
+
+    /// Tous les enregistrements, dans un ordre quelconque
+    pub fn records(&self) -> impl Iterator<Item = &DnsAnswer> {
+        self.records.values().flatten()
+    }
+
+    /// Enregistrements de la zone `origin`, SOA en tête, sans ceux des
+    /// sous-zones servies séparément
+    pub fn zone_records(&self, origin: &str) -> Vec<DnsAnswer> {
+        let mut records: Vec<DnsAnswer> = self
+            .records()
+            .filter(|record| self.zone_of(record).is_some_and(|zone| zone.eq_ignore_ascii_case(origin)))
+            .cloned()
+            .collect();
+        records.sort_by_key(|record| (record.rdata.record_type() != RecordType::SOA, record.name.clone()));
+        records
+    }
+
+    /// Remplace le contenu de la zone `origin`, ajoutée si besoin
+    pub fn load_zone(&mut self, origin: &str, records: Vec<DnsAnswer>) {
+        self.clear_zone(origin);
+        if !self.is_origin(origin) {
+            self.origins.push(origin.to_string());
+        }
+        for record in records {
+            self.add(record);
+        }
+    }
+
+    /// Retire la zone `origin` et son historique
+    pub fn remove_zone(&mut self, origin: &str) {
+        self.clear_zone(origin);
+        self.origins.retain(|zone| !zone.eq_ignore_ascii_case(origin));
+        self.history.remove(origin);
+    }
+
+    fn clear_zone(&mut self, origin: &str) {
+        for record in self.zone_records(origin) {
+            self.remove(&record.name, |existing| existing.rdata == record.rdata);
+        }
+    }
+
+    /// Applique des différences successives à la zone `origin`
+    pub fn apply_diffs(&mut self, origin: &str, diffs: &[ZoneDiff]) {
+        for diff in diffs {
+            self.remove(origin, |record| record.rdata.record_type() == RecordType::SOA);
+            for record in &diff.deleted {
+                self.remove(&record.name, |existing| existing.rdata == record.rdata);
+            }
+            for record in diff.added.iter().chain([&diff.new_soa]) {
+                self.add(record.clone());
+            }
+        }
+    }
+
+    /// Ajoute à l'historique de `origin` ce qui a changé depuis `before`, si
+    /// le numéro de série a changé
+    pub fn record_change(&mut self, origin: &str, before: &RecordStore) {
+        let (Some(old_soa), Some(new_soa)) = (before.soa(origin), self.soa(origin)) else {
+            return;
+        };
+        if soa_serial(old_soa) == soa_serial(new_soa) {
+            return;
+        }
+
+        let not_soa = |record: &DnsAnswer| record.rdata.record_type() != RecordType::SOA;
+        let old: Vec<DnsAnswer> = before.zone_records(origin).into_iter().filter(not_soa).collect();
+        let new: Vec<DnsAnswer> = self.zone_records(origin).into_iter().filter(not_soa).collect();
+        let missing_from = |records: &[DnsAnswer], record: &DnsAnswer| {
+            !records.iter().any(|other| {
+                other.name.eq_ignore_ascii_case(&record.name) && other.ttl == record.ttl && other.rdata == record.rdata
+            })
+        };
+
+        let diff = ZoneDiff {
+            old_soa: old_soa.clone(),
+            new_soa: new_soa.clone(),
+            deleted: old.iter().filter(|record| missing_from(&new, record)).cloned().collect(),
+            added: new.iter().filter(|record| missing_from(&old, record)).cloned().collect(),
+        };
+
+        let history = self.history.entry(origin.to_string()).or_default();
+        history.push(diff);
+        if history.len() > MAX_HISTORY {
+            history.remove(0);
+        }
+    }
+
+    /// Modifications de `origin` depuis la version `serial`, si l'historique
+    /// remonte jusque-là sans trou
+    pub fn changes_since(&self, origin: &str, serial: u32) -> Option<&[ZoneDiff]> {
+        let history = self.history.get(origin)?;
+        let start = history.iter().position(|diff| soa_serial(&diff.old_soa) == Some(serial))?;
+        let changes = &history[start..];
+        let contiguous = changes.windows(2).all(|pair| pair[0].new_soa.rdata == pair[1].old_soa.rdata);
+        contiguous.then_some(changes)
+    }
+}
+
+/// Numéro de série d'un enregistrement SOA
+pub fn soa_serial(record: &DnsAnswer) -> Option<u32> {
+    match record.rdata {
+        RecordData::SOA { serial, .. } => Some(serial),
+        _ => None,
+    }
+}
+
+/// Comparaison de numéros de série (RFC 1982) : vrai si `serial` est plus
+/// récent que `current`
+pub fn serial_newer(serial: u32, current: u32) -> bool {
+    serial != current && serial.wrapping_sub(current) < 1 << 31
+}
+
+/// `name` puis chacun de ses ancêtres, jusqu'à la racine (`""`)
+fn ancestors(name: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(name), |name| {
+        (!name.is_empty()).then(|| name.split_once('.').map_or("", |(_, parent)| parent))
+    })
+}
@@ -0,0 +1,31 @@
+use std::io;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Lit un message DNS précédé de sa longueur sur deux octets (RFC 1035 §4.2.2).
+///
+/// Renvoie `None` si la connexion est fermée proprement entre deux messages.
+pub async fn read_message<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut length = [0u8; 2];
+    match reader.read_exact(&mut length).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let mut message = vec![0u8; u16::from_be_bytes(length) as usize];
+    reader.read_exact(&mut message).await?;
+    Ok(Some(message))
+}
+
+/// Écrit un message DNS précédé de sa longueur sur deux octets
+pub async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &[u8]) -> io::Result<()> {
+    let length = u16::try_from(message.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Message DNS de plus de 65535 octets"))?;
+
+    // Longueur et message en une seule écriture, pour éviter deux segments
+    let mut framed = Vec::with_capacity(message.len() + 2);
+    framed.extend_from_slice(&length.to_be_bytes());
+    framed.extend_from_slice(message);
+    writer.write_all(&framed).await?;
+    writer.flush().await
+}
@@ -0,0 +1,101 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::crypto::{self, CryptoProvider};
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore, ServerConfig};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+/// Protocole ALPN de DNS sur TLS (registre IANA, RFC 7858 ne l'impose pas)
+pub const ALPN_DOT: &[u8] = b"dot";
+
+/// Protocole ALPN de DNS sur HTTPS : le serveur ne parle que HTTP/1.1
+pub const ALPN_HTTP1: &[u8] = b"http/1.1";
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(crypto::ring::default_provider())
+}
+
+/// Certificats d'un fichier PEM, dans l'ordre du fichier
+fn read_certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>, Box<dyn std::error::Error>> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let certificates = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    if certificates.is_empty() {
+        return Err(format!("{}: aucun certificat", path.display()).into());
+    }
+    Ok(certificates)
+}
+
+/// Première clé privée d'un fichier PEM (PKCS#8, PKCS#1 ou SEC1)
+fn read_private_key(path: &Path) -> Result<PrivateKeyDer<'static>, Box<dyn std::error::Error>> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .map_err(|e| format!("{}: {}", path.display(), e))?
+        .ok_or_else(|| format!("{}: aucune clé privée", path.display()).into())
+}
+
+/// Côté serveur d'un service TLS : certificat (suivi de sa chaîne) et clé
+/// privée au format PEM, protocole ALPN annoncé
+pub fn acceptor(cert: &Path, key: &Path, alpn: &[u8]) -> Result<TlsAcceptor, Box<dyn std::error::Error>> {
+    let mut config = ServerConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(read_certificates(cert)?, read_private_key(key)?)
+        .map_err(|e| format!("{}: {}", cert.display(), e))?;
+    config.alpn_protocols = vec![alpn.to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Côté client d'une connexion TLS : le certificat du serveur doit être
+/// signé par l'une des autorités du fichier PEM `ca` (ou en faire partie,
+/// pour un certificat autosigné) et valable pour `server_name`
+#[derive(Clone)]
+pub struct TlsTarget {
+    connector: TlsConnector,
+    server_name: ServerName<'static>,
+    host: String,
+}
+
+impl TlsTarget {
+    pub fn new(ca: &Path, server_name: &str, alpn: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut roots = RootCertStore::empty();
+        for certificate in read_certificates(ca)? {
+            roots.add(certificate).map_err(|e| format!("{}: {}", ca.display(), e))?;
+        }
+        let mut config = ClientConfig::builder_with_provider(provider())
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        config.alpn_protocols = vec![alpn.to_vec()];
+
+        let name = ServerName::try_from(server_name.to_string()).map_err(|e| format!("{}: {}", server_name, e))?;
+        Ok(Self { connector: TlsConnector::from(Arc::new(config)), server_name: name, host: server_name.to_string() })
+    }
+
+    /// Nom attendu dans le certificat, repris dans l'en-tête `Host` de DoH
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Ouvre une connexion TCP vers `addr` et y négocie TLS
+    pub async fn connect(&self, addr: SocketAddr) -> io::Result<TlsStream<TcpStream>> {
+        let stream = TcpStream::connect(addr).await?;
+        self.connector.connect(self.server_name.clone(), stream).await
+    }
+}
+
+/// Écrit un certificat autosigné pour `names` (noms ou adresses IP) et sa
+/// clé privée, au format PEM ; le certificat sert aussi d'autorité aux
+/// clients
+pub fn generate_self_signed(names: &[String], cert: &Path, key: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let certified = rcgen::generate_simple_self_signed(names.to_vec())?;
+    fs::write(cert, certified.cert.pem()).map_err(|e| format!("{}: {}", cert.display(), e))?;
+    fs::write(key, certified.key_pair.serialize_pem()).map_err(|e| format!("{}: {}", key.display(), e))?;
+    Ok(())
+}
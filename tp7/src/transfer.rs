@@ -0,0 +1,137 @@
+use crate::dns_message::{DnsAnswer, DnsMessage, NameCompression, RecordType};
+use crate::store::{RecordStore, ZoneDiff, serial_newer, soa_serial};
+
+/// Taille visée pour chaque message d'un transfert ; un message TCP ne peut
+/// pas dépasser 65535 octets
+const TRANSFER_MESSAGE_SIZE: usize = 16 * 1024;
+
+/// Contenu d'une réponse de transfert de zone
+#[derive(Debug)]
+pub enum Transfer {
+    /// Le serveur n'a pas de version plus récente que la nôtre
+    UpToDate,
+    /// Zone complète, SOA en tête
+    Full(Vec<DnsAnswer>),
+    /// Modifications successives depuis notre version
+    Incremental(Vec<ZoneDiff>),
+}
+
+/// Réponse AXFR : le SOA, tous les enregistrements de la zone, puis le SOA à
+/// nouveau pour marquer la fin (RFC 5936 §2.2)
+pub fn axfr_records(store: &RecordStore, origin: &str) -> Option<Vec<DnsAnswer>> {
+    let soa = store.soa(origin)?.clone();
+    let mut records = store.zone_records(origin);
+    records.push(soa);
+    Some(records)
+}
+
+/// Réponse IXFR pour un client à la version `serial` (RFC 1995 §4) : le SOA
+/// seul s'il est à jour, les différences si l'historique le permet, et
+/// sinon la zone complète au format AXFR
+pub fn ixfr_records(store: &RecordStore, origin: &str, serial: u32) -> Option<Vec<DnsAnswer>> {
+    let soa = store.soa(origin)?.clone();
+    if !soa_serial(&soa).is_some_and(|current| serial_newer(current, serial)) {
+        return Some(vec![soa]);
+    }
+
+    let Some(changes) = store.changes_since(origin, serial) else {
+        return axfr_records(store, origin);
+    };
+
+    let mut records = vec![soa.clone()];
+    for diff in changes {
+        records.push(diff.old_soa.clone());
+        records.extend(diff.deleted.iter().cloned());
+        records.push(diff.new_soa.clone());
+        records.extend(diff.added.iter().cloned());
+    }
+    records.push(soa);
+    Some(records)
+}
+
+/// Répartit les enregistrements d'un transfert en messages de réponse à
+/// `query`, chacun avec la question
+pub fn split_messages(query: &DnsMessage, records: Vec<DnsAnswer>) -> Vec<DnsMessage> {
+    let mut messages = Vec::new();
+    let mut current = DnsMessage::new_response(query, vec![]);
+    current.header.flags.aa = true;
+    let mut size = 0;
+
+    for record in records {
+        // Taille sans compression : une borne supérieure
+        let mut encoded = Vec::new();
+        record.write_to(&mut encoded, &mut NameCompression::new());
+
+        if size + encoded.len() > TRANSFER_MESSAGE_SIZE && !current.answers.is_empty() {
+            let next = DnsMessage { answers: vec![], ..current.clone() };
+            messages.push(std::mem::replace(&mut current, next));
+            size = 0;
+        }
+        size += encoded.len();
+        current.answers.push(record);
+    }
+
+    messages.push(current);
+    messages
+}
+
+/// Interprète les enregistrements reçus jusqu'ici pour un transfert.
+///
+/// Renvoie `None` tant que le SOA final n'est pas arrivé ; `known_serial`
+/// est la version demandée par un IXFR.
+pub fn decode_transfer(records: &[DnsAnswer], known_serial: Option<u32>) -> Result<Option<Transfer>, String> {
+    let Some(first) = records.first() else {
+        return Ok(None);
+    };
+    let serial = soa_serial(first).ok_or("le transfert ne commence pas par un SOA")?;
+
+    let second = match records.get(1) {
+        Some(second) => second,
+        // Un SOA seul répond à un IXFR déjà à jour
+        None if known_serial.is_some_and(|known| !serial_newer(serial, known)) => return Ok(Some(Transfer::UpToDate)),
+        None => return Ok(None),
+    };
+
+    // Format incrémental : le deuxième enregistrement est le SOA d'une
+    // version plus ancienne
+    let incremental = soa_serial(second).is_some_and(|old| old != serial);
+    if !incremental {
+        let complete = records.last().is_some_and(|last| soa_serial(last).is_some());
+        return Ok(complete.then(|| Transfer::Full(records[..records.len() - 1].to_vec())));
+    }
+
+    let is_soa = |record: &DnsAnswer| record.rdata.record_type() == RecordType::SOA;
+    let mut diffs = Vec::new();
+    let mut index = 1;
+    loop {
+        let Some(old_soa) = records.get(index) else {
+            return Ok(None);
+        };
+        if !is_soa(old_soa) {
+            return Err("SOA attendu au début d'une différence".to_string());
+        }
+        if soa_serial(old_soa) == Some(serial) {
+            return if index == records.len() - 1 {
+                Ok(Some(Transfer::Incremental(diffs)))
+            } else {
+                Err("enregistrements après le SOA final".to_string())
+            };
+        }
+        index += 1;
+
+        let deleted: Vec<DnsAnswer> = records[index..].iter().take_while(|record| !is_soa(record)).cloned().collect();
+        index += deleted.len();
+        let Some(new_soa) = records.get(index) else {
+            return Ok(None);
+        };
+        index += 1;
+
+        let added: Vec<DnsAnswer> = records[index..].iter().take_while(|record| !is_soa(record)).cloned().collect();
+        index += added.len();
+        if index == records.len() {
+            return Ok(None);
+        }
+
+        diffs.push(ZoneDiff { old_soa: old_soa.clone(), new_soa: new_soa.clone(), deleted, added });
+    }
+}
@@ -0,0 +1,238 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use crate::dns_message::{CLASS_ANY, CLASS_IN, CLASS_NONE, DnsAnswer, DnsMessage, RecordData, RecordType, ResponseCode};
+use crate::store::{RecordStore, serial_newer};
+use crate::zone::is_subdomain;
+
+/// Vérifie les prérequis d'une mise à jour de `zone` (RFC 2136 §3.2)
+pub fn check_prerequisites(store: &RecordStore, zone: &str, prerequisites: &[DnsAnswer]) -> Result<(), ResponseCode> {
+    // Prérequis dépendant des valeurs, regroupés par RRset
+    let mut expected: Vec<(&str, RecordType, Vec<&RecordData>)> = Vec::new();
+
+    for prerequisite in prerequisites {
+        let name = prerequisite.name.as_str();
+        let rtype = RecordType::from(prerequisite.rtype);
+
+        if prerequisite.ttl != 0 {
+            return Err(ResponseCode::FormErr);
+        }
+        if !is_subdomain(name, zone) {
+            return Err(ResponseCode::NotZone);
+        }
+
+        match prerequisite.rclass {
+            CLASS_ANY | CLASS_NONE if !prerequisite.is_empty() => return Err(ResponseCode::FormErr),
+            // Le nom porte au moins un enregistrement
+            CLASS_ANY if rtype == RecordType::ANY => {
+                if store.get(name).is_empty() {
+                    return Err(ResponseCode::NXDomain);
+                }
+            }
+            // Le RRset existe, quelles que soient ses valeurs
+            CLASS_ANY => {
+                if store.rrset(name, rtype).is_empty() {
+                    return Err(ResponseCode::NXRRSet);
+                }
+            }
+            // Le nom ne porte aucun enregistrement
+            CLASS_NONE if rtype == RecordType::ANY => {
+                if !store.get(name).is_empty() {
+                    return Err(ResponseCode::YXDomain);
+                }
+            }
+            // Le RRset n'existe pas
+            CLASS_NONE => {
+                if !store.rrset(name, rtype).is_empty() {
+                    return Err(ResponseCode::YXRRSet);
+                }
+            }
+            CLASS_IN if rtype != RecordType::ANY => {
+                match expected.iter_mut().find(|(owner, kind, _)| owner.eq_ignore_ascii_case(name) && *kind == rtype) {
+                    Some((_, _, values)) => values.push(&prerequisite.rdata),
+                    None => expected.push((name, rtype, vec![&prerequisite.rdata])),
+                }
+            }
+            _ => return Err(ResponseCode::FormErr),
+        }
+    }
+
+    // Le RRset existe avec exactement ces valeurs
+    for (name, rtype, values) in expected {
+        let existing: Vec<&RecordData> = store.rrset(name, rtype).iter().map(|record| &record.rdata).collect();
+        let same = values.iter().all(|value| existing.contains(value))
+            && existing.iter().all(|value| values.contains(value));
+        if !same {
+            return Err(ResponseCode::NXRRSet);
+        }
+    }
+
+    Ok(())
+}
+
+/// Applique la section mise à jour à `zone` (RFC 2136 §3.4).
+///
+/// Toute la section est validée avant la première modification ; le numéro
+/// de série du SOA est incrémenté si la zone a changé sans que la mise à jour
+/// ne fournisse elle-même un nouveau SOA. Renvoie vrai si la zone a changé.
+pub fn apply_updates(store: &mut RecordStore, zone: &str, updates: &[DnsAnswer]) -> Result<bool, ResponseCode> {
+    for update in updates {
+        prescan(update, zone)?;
+    }
+
+    let mut changed = false;
+    let mut soa_replaced = false;
+
+    for update in updates {
+        let name = update.name.as_str();
+        let rtype = RecordType::from(update.rtype);
+        let at_apex = name.eq_ignore_ascii_case(zone);
+
+        match update.rclass {
+            CLASS_IN => match rtype {
+                // Un SOA n'est remplacé que par un numéro de série plus récent
+                RecordType::SOA => {
+                    let newer = match (store.rrset(name, RecordType::SOA).first(), &update.rdata) {
+                        (Some(current), RecordData::SOA { serial, .. }) => match current.rdata {
+                            RecordData::SOA { serial: current, .. } => serial_newer(*serial, current),
+                            _ => false,
+                        },
+                        _ => false,
+                    };
+                    if at_apex && newer {
+                        store.remove(name, |record| record.rdata.record_type() == RecordType::SOA);
+                        store.add(update.clone());
+                        changed = true;
+                        soa_replaced = true;
+                    }
+                }
+                // Un CNAME ne cohabite avec aucun autre type
+                RecordType::CNAME => {
+                    let others = store.get(name).iter().any(|record| record.rdata.record_type() != RecordType::CNAME);
+                    if !others {
+                        store.remove(name, |record| record.rdata.record_type() == RecordType::CNAME);
+                        store.add(update.clone());
+                        changed = true;
+                    }
+                }
+                _ => {
+                    if store.rrset(name, RecordType::CNAME).is_empty() {
+                        store.add(update.clone());
+                        changed = true;
+                    }
+                }
+            },
+            // Suppression de tous les RRsets du nom, sauf SOA et NS au sommet
+            CLASS_ANY if rtype == RecordType::ANY => {
+                changed |= store.remove(name, |record| {
+                    let kind = record.rdata.record_type();
+                    !(at_apex && (kind == RecordType::SOA || kind == RecordType::NS))
+                });
+            }
+            // Suppression d'un RRset
+            CLASS_ANY => {
+                if !(at_apex && (rtype == RecordType::SOA || rtype == RecordType::NS)) {
+                    changed |= store.remove(name, |record| record.rdata.record_type() == rtype);
+                }
+            }
+            // Suppression d'un enregistrement ; le SOA et le dernier NS du
+            // sommet sont conservés
+            _ => {
+                let last_ns = at_apex && rtype == RecordType::NS && store.rrset(name, RecordType::NS).len() <= 1;
+                if rtype != RecordType::SOA && !last_ns {
+                    changed |= store.remove(name, |record| record.rdata == update.rdata);
+                }
+            }
+        }
+    }
+
+    if changed && !soa_replaced {
+        store.increment_serial(zone);
+    }
+    Ok(changed)
+}
+
+/// Contrôle d'une entrée de la section mise à jour (RFC 2136 §3.4.1)
+fn prescan(update: &DnsAnswer, zone: &str) -> Result<(), ResponseCode> {
+    if !is_subdomain(&update.name, zone) {
+        return Err(ResponseCode::NotZone);
+    }
+
+    let rtype = RecordType::from(update.rtype);
+    // Types réservés aux requêtes (OPT, AXFR, ANY...)
+    let meta = rtype == RecordType::OPT || update.rtype >= 128 && update.rtype <= 255;
+
+    let valid = match update.rclass {
+        CLASS_IN => !meta && !update.is_empty(),
+        CLASS_ANY => update.ttl == 0 && update.is_empty() && (!meta || rtype == RecordType::ANY),
+        CLASS_NONE => update.ttl == 0 && !meta,
+        _ => false,
+    };
+
+    if valid { Ok(()) } else { Err(ResponseCode::FormErr) }
+}
+
+/// Ajoute une mise à jour acceptée au journal, avec le même préfixe de
+/// longueur que sur TCP, et attend qu'elle soit écrite sur le disque
+pub fn append_to_journal(path: &Path, message: &[u8]) -> io::Result<()> {
+    let length = u16::try_from(message.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Message DNS de plus de 65535 octets"))?;
+
+    let mut framed = Vec::with_capacity(message.len() + 2);
+    framed.extend_from_slice(&length.to_be_bytes());
+    framed.extend_from_slice(message);
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(&framed)?;
+    file.sync_data()
+}
+
+/// Inscrit au journal le SOA de `zone`, sous la forme d'une mise à jour qui
+/// le remplace : un numéro de série changé hors mise à jour, à une nouvelle
+/// signature, est ainsi retrouvé au redémarrage
+pub fn journal_soa(path: &Path, zone: &str, soa: &DnsAnswer) -> io::Result<()> {
+    let mut message = DnsMessage::new_update(0, zone.to_string());
+    message.authorities.push(soa.clone());
+    append_to_journal(path, &message.to_bytes())
+}
+
+/// Rejoue les mises à jour du journal, dans l'ordre, sur les zones chargées.
+///
+/// Les prérequis ont été vérifiés à l'époque : seules les mises à jour sont
+/// réappliquées. Renvoie le nombre de messages rejoués ; un journal absent
+/// est un journal vide.
+pub fn replay_journal(store: &mut RecordStore, path: &Path) -> Result<usize, Box<dyn std::error::Error>> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut offset = 0;
+    let mut count = 0;
+    while offset < bytes.len() {
+        let truncated = || format!("{}: journal tronqué à l'octet {}", path.display(), offset);
+        let length = bytes.get(offset..offset + 2).ok_or_else(truncated)?;
+        let length = u16::from_be_bytes([length[0], length[1]]) as usize;
+        let message = bytes.get(offset + 2..offset + 2 + length).ok_or_else(truncated)?;
+
+        let update = DnsMessage::from_bytes(message)
+            .map_err(|e| format!("{}: mise à jour illisible à l'octet {}: {}", path.display(), offset, e))?;
+        offset += length + 2;
+
+        let zone = match update.questions.first() {
+            Some(question) if store.is_origin(&question.name) => question.name.clone(),
+            _ => {
+                println!("Journal: mise à jour d'une zone non servie ignorée");
+                continue;
+            }
+        };
+        if let Err(rcode) = apply_updates(store, &zone, &update.authorities) {
+            println!("Journal: mise à jour de {}. rejetée ({})", zone, rcode);
+            continue;
+        }
+        count += 1;
+    }
+
+    Ok(count)
+}
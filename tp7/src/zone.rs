@@ -0,0 +1,549 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::dns_message::{DnsAnswer, RecordData, RecordType, base64_decode, labels_to_name, name_labels, parse_timestamp};
+
+/// Zone chargée depuis un fichier maître (RFC 1035 §5)
+#[derive(Debug, Clone)]
+pub struct Zone {
+    /// Sommet de la zone, sans point final
+    pub origin: String,
+    pub records: Vec<DnsAnswer>,
+}
+
+/// Erreur de chargement d'une zone, localisée dans le fichier
+#[derive(Debug)]
+pub struct ZoneError {
+    pub file: PathBuf,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ZoneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.file.display(), self.line, self.message)
+    }
+}
+
+impl std::error::Error for ZoneError {}
+
+/// Entrée logique : une ligne, ou plusieurs lignes entre parenthèses
+struct Entry {
+    line: usize,
+    /// Vrai si la ligne commence par un blanc (propriétaire omis)
+    inherits_owner: bool,
+    /// Les chaînes entre guillemets gardent leurs échappements pour être
+    /// décodées selon le type d'enregistrement
+    tokens: Vec<String>,
+}
+
+/// État du parseur partagé entre un fichier et ses `$INCLUDE`
+struct ParserState {
+    origin: Option<String>,
+    /// TTL donné par `$TTL`
+    default_ttl: Option<u32>,
+    /// Dernier TTL explicite, la valeur par défaut sans `$TTL` (RFC 1035 §5.1)
+    last_ttl: Option<u32>,
+    last_owner: Option<String>,
+    records: Vec<DnsAnswer>,
+}
+
+impl Zone {
+    /// Charge une zone depuis un fichier
+    pub fn load(path: &Path) -> Result<Self, ZoneError> {
+        let mut state = ParserState {
+            origin: None,
+            default_ttl: None,
+            last_ttl: None,
+            last_owner: None,
+            records: Vec::new(),
+        };
+        parse_file(path, &mut state, 0)?;
+        Self::from_records(path, state.records)
+    }
+
+    /// Analyse le contenu d'un fichier de zone déjà en mémoire ; `source`
+    /// sert uniquement à localiser les erreurs
+    pub fn parse(text: &str, source: &Path) -> Result<Self, ZoneError> {
+        let mut state = ParserState {
+            origin: None,
+            default_ttl: None,
+            last_ttl: None,
+            last_owner: None,
+            records: Vec::new(),
+        };
+        parse_text(text, source, &mut state, 0)?;
+        Self::from_records(source, state.records)
+    }
+
+    /// Vérifie la cohérence de la zone : un seul SOA, qui en définit le
+    /// sommet, et aucun enregistrement en dehors de ce sommet
+    fn from_records(file: &Path, records: Vec<DnsAnswer>) -> Result<Self, ZoneError> {
+        let error = |message: String| ZoneError { file: file.to_path_buf(), line: 0, message };
+
+        let soas: Vec<&DnsAnswer> = records
+            .iter()
+            .filter(|record| record.rdata.record_type() == RecordType::SOA)
+            .collect();
+
+        let origin = match soas.as_slice() {
+            [soa] => soa.name.clone(),
+            [] => return Err(error("aucun enregistrement SOA".to_string())),
+            _ => return Err(error("plusieurs enregistrements SOA".to_string())),
+        };
+
+        if let Some(outside) = records.iter().find(|record| !is_subdomain(&record.name, &origin)) {
+            return Err(error(format!("{} est en dehors de la zone {}", outside.name, origin)));
+        }
+
+        Ok(Self { origin, records })
+    }
+}
+
+/// Analyse un enregistrement isolé, écrit comme dans un fichier de zone ;
+/// les noms relatifs sont complétés par `origin`
+pub fn parse_record_line(line: &str, origin: &str) -> Result<DnsAnswer, String> {
+    let entries = split_entries(line, Path::new("<ligne>")).map_err(|e| e.message)?;
+    let entry = match entries.as_slice() {
+        [entry] => entry,
+        _ => return Err("un enregistrement attendu".to_string()),
+    };
+
+    let mut state = ParserState {
+        origin: Some(origin.to_string()),
+        default_ttl: None,
+        last_ttl: None,
+        last_owner: None,
+        records: Vec::new(),
+    };
+    parse_record(entry, &mut state)
+}
+
+/// Indique si `name` est égal à `parent` ou en est un sous-domaine
+pub fn is_subdomain(name: &str, parent: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    let parent = parent.to_ascii_lowercase();
+    parent.is_empty() || name == parent || name.ends_with(&format!(".{}", parent))
+}
+
+/// Profondeur maximale des `$INCLUDE` imbriqués
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+fn parse_file(path: &Path, state: &mut ParserState, depth: usize) -> Result<(), ZoneError> {
+    let text = fs::read_to_string(path).map_err(|e| ZoneError {
+        file: path.to_path_buf(),
+        line: 0,
+        message: format!("lecture impossible: {}", e),
+    })?;
+    parse_text(&text, path, state, depth)
+}
+
+fn parse_text(text: &str, file: &Path, state: &mut ParserState, depth: usize) -> Result<(), ZoneError> {
+    for entry in split_entries(text, file)? {
+        let error = |message: String| ZoneError { file: file.to_path_buf(), line: entry.line, message };
+        let first = match entry.tokens.first() {
+            Some(token) => token.as_str(),
+            None => continue,
+        };
+
+        match first.to_ascii_uppercase().as_str() {
+            "$ORIGIN" => {
+                let name = entry.tokens.get(1).ok_or_else(|| error("$ORIGIN sans nom".to_string()))?;
+                state.origin = Some(absolute_name(name, state.origin.as_deref()).map_err(error)?);
+            }
+            "$TTL" => {
+                let ttl = entry.tokens.get(1).ok_or_else(|| error("$TTL sans valeur".to_string()))?;
+                state.default_ttl = Some(parse_ttl(ttl).ok_or_else(|| error(format!("TTL invalide: {}", ttl)))?);
+            }
+            "$INCLUDE" => {
+                if depth >= MAX_INCLUDE_DEPTH {
+                    return Err(error("trop de $INCLUDE imbriqués".to_string()));
+                }
+                let target = entry.tokens.get(1).ok_or_else(|| error("$INCLUDE sans fichier".to_string()))?;
+                let path = file.parent().unwrap_or(Path::new(".")).join(target);
+
+                // L'origine éventuelle ne s'applique qu'au fichier inclus
+                let saved_origin = state.origin.clone();
+                if let Some(origin) = entry.tokens.get(2) {
+                    state.origin = Some(absolute_name(origin, state.origin.as_deref()).map_err(error)?);
+                }
+                parse_file(&path, state, depth + 1)?;
+                state.origin = saved_origin;
+            }
+            directive if directive.starts_with('$') => {
+                return Err(error(format!("directive inconnue: {}", first)));
+            }
+            _ => {
+                let record = parse_record(&entry, state).map_err(error)?;
+                state.records.push(record);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Découpe le texte en entrées logiques en retirant les commentaires et en
+/// regroupant les lignes entre parenthèses
+fn split_entries(text: &str, file: &Path) -> Result<Vec<Entry>, ZoneError> {
+    let mut entries = Vec::new();
+    let mut current: Option<Entry> = None;
+    let mut depth = 0usize;
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |message: &str| ZoneError { file: file.to_path_buf(), line: line_number, message: message.to_string() };
+
+        let entry = current.get_or_insert_with(|| Entry {
+            line: line_number,
+            inherits_owner: line.starts_with([' ', '\t']),
+            tokens: Vec::new(),
+        });
+
+        let mut chars = line.chars().peekable();
+        let mut word = String::new();
+        let mut in_quotes = false;
+
+        let flush = |word: &mut String, tokens: &mut Vec<String>| {
+            if !word.is_empty() {
+                tokens.push(std::mem::take(word));
+            }
+        };
+
+        while let Some(c) = chars.next() {
+            if in_quotes {
+                match c {
+                    '\\' => {
+                        word.push(c);
+                        if let Some(escaped) = chars.next() {
+                            word.push(escaped);
+                        }
+                    }
+                    '"' => {
+                        entry.tokens.push(std::mem::take(&mut word));
+                        in_quotes = false;
+                    }
+                    _ => word.push(c),
+                }
+                continue;
+            }
+
+            match c {
+                ';' => break,
+                '"' => {
+                    flush(&mut word, &mut entry.tokens);
+                    in_quotes = true;
+                }
+                '(' => {
+                    flush(&mut word, &mut entry.tokens);
+                    depth += 1;
+                }
+                ')' => {
+                    flush(&mut word, &mut entry.tokens);
+                    depth = depth.checked_sub(1).ok_or_else(|| error("parenthèse fermante inattendue"))?;
+                }
+                '\\' => {
+                    word.push(c);
+                    if let Some(escaped) = chars.next() {
+                        word.push(escaped);
+                    }
+                }
+                c if c.is_whitespace() => flush(&mut word, &mut entry.tokens),
+                _ => word.push(c),
+            }
+        }
+
+        if in_quotes {
+            return Err(error("guillemet non fermé"));
+        }
+        flush(&mut word, &mut entry.tokens);
+
+        if depth == 0 {
+            let entry = current.take().expect("entrée en cours");
+            if !entry.tokens.is_empty() {
+                entries.push(entry);
+            }
+        }
+    }
+
+    if let Some(entry) = current.filter(|_| depth > 0) {
+        return Err(ZoneError {
+            file: file.to_path_buf(),
+            line: entry.line,
+            message: "parenthèse non fermée".to_string(),
+        });
+    }
+
+    Ok(entries)
+}
+
+fn parse_record(entry: &Entry, state: &mut ParserState) -> Result<DnsAnswer, String> {
+    let mut tokens = entry.tokens.iter().peekable();
+
+    let owner = if entry.inherits_owner {
+        state.last_owner.clone().ok_or("propriétaire manquant")?
+    } else {
+        let token = tokens.next().ok_or("entrée vide")?;
+        absolute_name(token, state.origin.as_deref())?
+    };
+
+    // TTL et classe sont optionnels et peuvent apparaître dans les deux ordres
+    let mut ttl = None;
+    let mut rtype = None;
+    for token in tokens.by_ref() {
+        if token.eq_ignore_ascii_case("IN") {
+            continue;
+        }
+        if matches!(token.to_ascii_uppercase().as_str(), "CH" | "HS" | "CS") {
+            return Err(format!("classe non supportée: {}", token));
+        }
+        if ttl.is_none() && token.starts_with(|c: char| c.is_ascii_digit()) {
+            ttl = Some(parse_ttl(token).ok_or_else(|| format!("TTL invalide: {}", token))?);
+            continue;
+        }
+        rtype = Some(token.parse::<RecordType>()?);
+        break;
+    }
+
+    let rtype = rtype.ok_or("type d'enregistrement manquant")?;
+    let rdata: Vec<&String> = tokens.collect();
+    let data = parse_rdata(rtype, &rdata, state.origin.as_deref())?;
+
+    // Sans $TTL, le dernier TTL explicite sert de valeur par défaut
+    let ttl = match (ttl, state.default_ttl.or(state.last_ttl)) {
+        (Some(ttl), _) => {
+            state.last_ttl = Some(ttl);
+            ttl
+        }
+        (None, Some(ttl)) => ttl,
+        (None, None) => match &data {
+            RecordData::SOA { minimum, .. } => *minimum,
+            _ => return Err("TTL manquant (ajoutez $TTL)".to_string()),
+        },
+    };
+
+    state.last_owner = Some(owner.clone());
+    Ok(DnsAnswer::with_ttl(owner, ttl, data))
+}
+
+fn parse_rdata(rtype: RecordType, tokens: &[&String], origin: Option<&str>) -> Result<RecordData, String> {
+    let expect = |count: usize| {
+        if tokens.len() == count {
+            Ok(())
+        } else {
+            Err(format!("{} attend {} valeur(s), {} trouvée(s)", rtype, count, tokens.len()))
+        }
+    };
+    let at_least = |count: usize| {
+        if tokens.len() >= count {
+            Ok(())
+        } else {
+            Err(format!("{} attend au moins {} valeur(s), {} trouvée(s)", rtype, count, tokens.len()))
+        }
+    };
+    let joined = |start: usize| tokens[start..].iter().map(|token| token.as_str()).collect::<String>();
+    let name = |index: usize| absolute_name(tokens[index], origin);
+    let byte = |index: usize| tokens[index].parse::<u8>().map_err(|_| format!("nombre invalide: {}", tokens[index]));
+    let number = |index: usize| tokens[index].parse::<u16>().map_err(|_| format!("nombre invalide: {}", tokens[index]));
+    let duration = |index: usize| parse_ttl(tokens[index]).ok_or_else(|| format!("durée invalide: {}", tokens[index]));
+
+    let data = match rtype {
+        RecordType::A => {
+            expect(1)?;
+            RecordData::A(tokens[0].parse().map_err(|_| format!("adresse IPv4 invalide: {}", tokens[0]))?)
+        }
+        RecordType::AAAA => {
+            expect(1)?;
+            RecordData::AAAA(tokens[0].parse().map_err(|_| format!("adresse IPv6 invalide: {}", tokens[0]))?)
+        }
+        RecordType::CNAME => {
+            expect(1)?;
+            RecordData::CNAME(name(0)?)
+        }
+        RecordType::NS => {
+            expect(1)?;
+            RecordData::NS(name(0)?)
+        }
+        RecordType::PTR => {
+            expect(1)?;
+            RecordData::PTR(name(0)?)
+        }
+        RecordType::MX => {
+            expect(2)?;
+            RecordData::MX { preference: number(0)?, exchange: name(1)? }
+        }
+        RecordType::TXT => {
+            if tokens.is_empty() {
+                return Err("TXT sans chaîne".to_string());
+            }
+            let strings = tokens
+                .iter()
+                .map(|token| {
+                    let bytes = unescape(token)?;
+                    if bytes.len() > 255 {
+                        return Err("chaîne TXT de plus de 255 octets".to_string());
+                    }
+                    Ok(bytes)
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            RecordData::TXT(strings)
+        }
+        RecordType::SOA => {
+            expect(7)?;
+            RecordData::SOA {
+                mname: name(0)?,
+                rname: name(1)?,
+                serial: tokens[2].parse().map_err(|_| format!("numéro de série invalide: {}", tokens[2]))?,
+                refresh: duration(3)?,
+                retry: duration(4)?,
+                expire: duration(5)?,
+                minimum: duration(6)?,
+            }
+        }
+        RecordType::SRV => {
+            expect(4)?;
+            RecordData::SRV {
+                priority: number(0)?,
+                weight: number(1)?,
+                port: number(2)?,
+                target: name(3)?,
+            }
+        }
+        // La clé, la signature ou l'empreinte peut être coupée par des blancs
+        RecordType::DS => {
+            at_least(4)?;
+            RecordData::DS {
+                key_tag: number(0)?,
+                algorithm: byte(1)?,
+                digest_type: byte(2)?,
+                digest: parse_hex(&joined(3))?,
+            }
+        }
+        RecordType::DNSKEY => {
+            at_least(4)?;
+            RecordData::DNSKEY {
+                flags: number(0)?,
+                protocol: byte(1)?,
+                algorithm: byte(2)?,
+                public_key: base64_decode(&joined(3))?,
+            }
+        }
+        RecordType::RRSIG => {
+            at_least(9)?;
+            let date = |index: usize| parse_timestamp(tokens[index]).ok_or_else(|| format!("date invalide: {}", tokens[index]));
+            RecordData::RRSIG {
+                type_covered: tokens[0].parse::<RecordType>()?.to_u16(),
+                algorithm: byte(1)?,
+                labels: byte(2)?,
+                original_ttl: duration(3)?,
+                expiration: date(4)?,
+                inception: date(5)?,
+                key_tag: number(6)?,
+                signer: name(7)?,
+                signature: base64_decode(&joined(8))?,
+            }
+        }
+        RecordType::NSEC => {
+            at_least(1)?;
+            let mut types = tokens[1..]
+                .iter()
+                .map(|token| token.parse::<RecordType>().map(RecordType::to_u16))
+                .collect::<Result<Vec<u16>, String>>()?;
+            types.sort_unstable();
+            types.dedup();
+            RecordData::NSEC { next: name(0)?, types }
+        }
+        other => return Err(format!("type non supporté dans un fichier de zone: {}", other)),
+    };
+
+    Ok(data)
+}
+
+/// Rend un nom absolu (sans point final) par rapport à l'origine courante,
+/// sous la forme canonique des noms lus dans les messages
+pub fn absolute_name(name: &str, origin: Option<&str>) -> Result<String, String> {
+    // Un point final échappé (`\.`) fait partie du dernier label
+    let absolute = name
+        .strip_suffix('.')
+        .filter(|rest| (rest.len() - rest.trim_end_matches('\\').len()) % 2 == 0);
+
+    let name = match (name, absolute, origin) {
+        ("@", _, Some(origin)) => origin.to_string(),
+        ("@", _, None) => return Err("@ utilisé sans $ORIGIN".to_string()),
+        (_, Some(absolute), _) => absolute.to_string(),
+        (_, None, Some("")) => name.to_string(),
+        (_, None, Some(origin)) => format!("{}.{}", name, origin),
+        (_, None, None) => return Err(format!("nom relatif {} sans $ORIGIN", name)),
+    };
+    Ok(labels_to_name(&name_labels(&name)?))
+}
+
+/// Décode une suite de chiffres hexadécimaux (empreinte d'un DS)
+fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return Err(format!("hexadécimal invalide: {}", text));
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&text[index..index + 2], 16).map_err(|_| format!("hexadécimal invalide: {}", text)))
+        .collect()
+}
+
+/// Lit un TTL en secondes, avec les unités BIND facultatives (1h30m, 2d...)
+fn parse_ttl(text: &str) -> Option<u32> {
+    if let Ok(seconds) = text.parse::<u32>() {
+        return Some(seconds);
+    }
+
+    let mut total: u32 = 0;
+    let mut value: Option<u32> = None;
+    for c in text.chars() {
+        if let Some(digit) = c.to_digit(10) {
+            value = Some(value.unwrap_or(0).checked_mul(10)?.checked_add(digit)?);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 604800,
+            _ => return None,
+        };
+        total = total.checked_add(value.take()?.checked_mul(unit)?)?;
+    }
+
+    if value.is_some() {
+        return None;
+    }
+    Some(total)
+}
+
+/// Décode les échappements `\X` et `\DDD` d'une chaîne de caractères
+fn unescape(text: &str) -> Result<Vec<u8>, String> {
+    let bytes = text.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            result.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+
+        let digits = &bytes[i + 1..bytes.len().min(i + 4)];
+        if digits.len() == 3 && digits.iter().all(u8::is_ascii_digit) {
+            let value: u32 = std::str::from_utf8(digits).unwrap_or("").parse().unwrap_or(256);
+            result.push(u8::try_from(value).map_err(|_| format!("échappement invalide dans {}", text))?);
+            i += 4;
+        } else if let Some(&escaped) = bytes.get(i + 1) {
+            result.push(escaped);
+            i += 2;
+        } else {
+            return Err(format!("échappement incomplet dans {}", text));
+        }
+    }
+
+    Ok(result)
+}
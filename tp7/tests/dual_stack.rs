@@ -0,0 +1,61 @@
+use std::net::{Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use tp7::{DnsClient, DnsMessage, DnsServer, RecordType, ResponseCode, ServerConfig};
+
+/// Questions posées à la zone de test, dont une réponse par CNAME, une
+/// réponse AAAA et un nom absent
+const QUESTIONS: [(&str, RecordType); 5] = [
+    ("www.example.com", RecordType::A),
+    ("example.com", RecordType::MX),
+    ("localhost", RecordType::AAAA),
+    ("big.example.com", RecordType::TXT),
+    ("absent.example.com", RecordType::A),
+];
+
+/// Le même serveur, écouté sur 127.0.0.1 et sur ::1, donne les mêmes
+/// réponses dans les deux familles, en UDP comme en TCP
+#[tokio::test]
+async fn same_answers_over_ipv4_and_ipv6() -> Result<(), Box<dyn std::error::Error>> {
+    if std::net::UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)).is_err() {
+        eprintln!("IPv6 indisponible, test ignoré");
+        return Ok(());
+    }
+
+    let args = ["--bind", "127.0.0.1:0", "--bind", "[::1]:0"].map(String::from);
+    let server = DnsServer::new(ServerConfig::from_args(&args)?).await?;
+    let addrs = server.local_addrs()?;
+    let server = Arc::new(server);
+    tokio::spawn(async move {
+        if let Err(e) = server.run().await {
+            eprintln!("Erreur serveur: {}", e);
+        }
+    });
+
+    let (v4, v6): (Vec<SocketAddr>, Vec<SocketAddr>) = addrs.into_iter().partition(SocketAddr::is_ipv4);
+    assert_eq!((v4.len(), v6.len()), (1, 1));
+
+    for (id, (name, qtype)) in QUESTIONS.into_iter().enumerate() {
+        let query = DnsMessage::new_typed_query(id as u16, name.to_string(), qtype);
+        let mut responses = Vec::new();
+        for server in [v4[0], v6[0]] {
+            let mut client = DnsClient::new(server).await?;
+            client.set_verbose(false);
+            let udp = client.exchange_detailed(&query).await?;
+            let tcp = client.exchange_tcp(&query, server).await?;
+            assert_eq!(udp.server, server);
+            responses.push(udp.response);
+            responses.push(tcp.response);
+        }
+
+        let expected = if name.starts_with("absent") { ResponseCode::NXDomain } else { ResponseCode::NoError };
+        for response in &responses {
+            assert_eq!(response.rcode(), expected, "{} {}", name, qtype);
+            assert_eq!(response.answers, responses[0].answers, "{} {}", name, qtype);
+        }
+        if expected == ResponseCode::NoError {
+            assert!(!responses[0].answers.is_empty(), "{} {}", name, qtype);
+        }
+    }
+
+    Ok(())
+}
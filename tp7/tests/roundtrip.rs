@@ -0,0 +1,180 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+use proptest::prelude::*;
+use tp7::dns_message::{
+    CLASS_ANY, CLASS_CH, CLASS_IN, CLASS_NONE, DnsAnswer, DnsFlags, DnsHeader, DnsMessage, DnsMessageRef,
+    DnsQuestion, Edns, EdnsOption, RecordData, RecordType, labels_to_name,
+};
+
+/// Labels tirés en priorité, pour que les noms partagent des suffixes et
+/// que la compression soit exercée
+const COMMON_LABELS: [&[u8]; 6] = [b"example", b"com", b"www", b"Mail", b"_tcp", b"*"];
+
+/// Types hors de ceux que l'analyseur décode, gardés tels quels
+const UNKNOWN_TYPES: [u16; 5] = [13, 65, 99, 257, 65280];
+
+fn label() -> impl Strategy<Value = Vec<u8>> {
+    prop_oneof![
+        8 => prop::sample::select(COMMON_LABELS.to_vec()).prop_map(|label| label.to_vec()),
+        1 => prop::collection::vec(any::<u8>(), 1..=8),
+        1 => Just(vec![b'x'; 63]),
+    ]
+}
+
+/// Nom de 0 à 4 labels, raccourci s'il dépasse les 255 octets d'un nom
+fn name() -> impl Strategy<Value = String> {
+    prop::collection::vec(label(), 0..=4).prop_map(|mut labels| {
+        while labels.iter().map(|label| label.len() + 1).sum::<usize>() + 1 > 255 {
+            labels.remove(0);
+        }
+        labels_to_name(&labels)
+    })
+}
+
+fn class() -> impl Strategy<Value = u16> {
+    prop::sample::select(vec![CLASS_IN, CLASS_IN, CLASS_IN, CLASS_CH, CLASS_NONE, CLASS_ANY])
+}
+
+fn bytes(max: usize) -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(any::<u8>(), 0..=max)
+}
+
+/// Données d'un enregistrement de classe `rclass`
+fn rdata(rclass: u16) -> impl Strategy<Value = RecordData> {
+    prop_oneof![
+        any::<u32>().prop_map(|address| RecordData::A(Ipv4Addr::from(address))),
+        any::<u128>().prop_map(|address| RecordData::AAAA(Ipv6Addr::from(address))),
+        name().prop_map(RecordData::CNAME),
+        name().prop_map(RecordData::NS),
+        name().prop_map(RecordData::PTR),
+        (any::<u16>(), name()).prop_map(|(preference, exchange)| RecordData::MX { preference, exchange }),
+        // Au moins une chaîne : des données vides seraient lues comme un
+        // enregistrement vide en classe ANY ou NONE
+        prop::collection::vec(bytes(40), 1..=3).prop_map(RecordData::TXT),
+        (name(), name(), any::<[u32; 5]>()).prop_map(|(mname, rname, [serial, refresh, retry, expire, minimum])| {
+            RecordData::SOA { mname, rname, serial, refresh, retry, expire, minimum }
+        }),
+        (any::<[u16; 3]>(), name())
+            .prop_map(|([priority, weight, port], target)| RecordData::SRV { priority, weight, port, target }),
+        (any::<u16>(), any::<u8>(), any::<u8>(), bytes(32)).prop_map(|(key_tag, algorithm, digest_type, digest)| {
+            RecordData::DS { key_tag, algorithm, digest_type, digest }
+        }),
+        (any::<(u16, u8, u8, u32, u32, u32, u16)>(), name(), bytes(64)).prop_map(
+            |((type_covered, algorithm, labels, original_ttl, expiration, inception, key_tag), signer, signature)| {
+                RecordData::RRSIG {
+                    type_covered,
+                    algorithm,
+                    labels,
+                    original_ttl,
+                    expiration,
+                    inception,
+                    key_tag,
+                    signer,
+                    signature,
+                }
+            }
+        ),
+        // Types triés et sans doublon, comme après décodage de la table
+        (name(), prop::collection::btree_set(any::<u16>(), 0..=5))
+            .prop_map(|(next, types)| RecordData::NSEC { next, types: types.into_iter().collect() }),
+        (any::<u16>(), any::<u8>(), any::<u8>(), bytes(64)).prop_map(|(flags, protocol, algorithm, public_key)| {
+            RecordData::DNSKEY { flags, protocol, algorithm, public_key }
+        }),
+        // Idem : pas de données vides hors de la classe IN
+        (prop::sample::select(UNKNOWN_TYPES.to_vec()), bytes(20)).prop_map(move |(rtype, data)| {
+            let data = if data.is_empty() && rclass != CLASS_IN { vec![0] } else { data };
+            RecordData::Unknown { rtype, data }
+        }),
+    ]
+}
+
+fn record() -> impl Strategy<Value = DnsAnswer> {
+    let filled = (name(), class(), any::<u32>())
+        .prop_flat_map(|(name, rclass, ttl)| (Just(name), Just(rclass), Just(ttl), rdata(rclass)))
+        .prop_map(|(name, rclass, ttl, rdata)| DnsAnswer { name, rtype: rdata.record_type().to_u16(), rclass, ttl, rdata });
+    // Enregistrement vide des mises à jour dynamiques
+    let empty = (name(), 1..=255u16, prop::sample::select(vec![CLASS_ANY, CLASS_NONE])).prop_map(|(name, rtype, rclass)| {
+        let rtype = RecordType::from(rtype);
+        let rtype = if rtype == RecordType::OPT { RecordType::A } else { rtype };
+        DnsAnswer::empty(name, rtype, rclass)
+    });
+    prop_oneof![9 => filled, 1 => empty]
+}
+
+fn edns() -> impl Strategy<Value = Edns> {
+    let option = (any::<u16>(), bytes(16)).prop_map(|(code, data)| EdnsOption { code, data });
+    (any::<(u16, u8, u8, bool)>(), prop::collection::vec(option, 0..=2)).prop_map(
+        |((payload_size, extended_rcode, version, dnssec_ok), options)| Edns {
+            payload_size,
+            extended_rcode,
+            version,
+            dnssec_ok,
+            options,
+        },
+    )
+}
+
+fn message() -> impl Strategy<Value = DnsMessage> {
+    let question = (name(), any::<u16>(), class()).prop_map(|(name, qtype, qclass)| DnsQuestion { name, qtype, qclass });
+    (
+        any::<(u16, u16)>(),
+        prop::collection::vec(question, 0..=2),
+        prop::collection::vec(record(), 0..=4),
+        prop::collection::vec(record(), 0..=3),
+        prop::collection::vec(record(), 0..=3),
+        prop::option::of(edns()),
+    )
+        .prop_map(|((id, flags), questions, answers, authorities, additionals, edns)| {
+            // Les compteurs de l'en-tête décodé sont ceux du message
+            let header = DnsHeader {
+                id,
+                flags: DnsFlags::from_u16(flags),
+                question_count: questions.len() as u16,
+                answer_count: answers.len() as u16,
+                authority_count: authorities.len() as u16,
+                additional_count: (additionals.len() + edns.is_some() as usize) as u16,
+            };
+            DnsMessage { header, questions, answers, authorities, additionals, edns }
+        })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2000))]
+
+    #[test]
+    fn from_bytes_inverts_to_bytes(message in message()) {
+        let bytes = message.to_bytes();
+        prop_assert_eq!(DnsMessage::from_bytes(&bytes).map_err(|e| e.to_string()), Ok(message));
+    }
+
+    #[test]
+    fn view_matches_full_parse(message in message()) {
+        let bytes = message.to_bytes();
+        let view = DnsMessageRef::parse(&bytes).map_err(|e| TestCaseError::fail(e.to_string()))?;
+        let answers: Result<Vec<DnsAnswer>, _> = view.answers().map(|record| record.to_answer()).collect();
+        prop_assert_eq!(answers.map_err(|e| e.to_string()), Ok(message.answers.clone()));
+        prop_assert_eq!(view.to_message().map_err(|e| e.to_string()), Ok(message));
+    }
+
+    #[test]
+    fn accepted_input_reencodes_identically(bytes in bytes(600)) {
+        if let Ok(message) = DnsMessage::from_bytes(&bytes) {
+            prop_assert_eq!(DnsMessage::from_bytes(&message.to_bytes()).map_err(|e| e.to_string()), Ok(message));
+        }
+    }
+}
+
+/// Une chaîne TXT de plus de 255 octets est découpée, pas tronquée
+#[test]
+fn long_txt_string_is_split() {
+    let text = (0..300).map(|index| b'a' + (index % 26) as u8).collect::<Vec<u8>>();
+    let message = DnsMessage::builder()
+        .id(1)
+        .qr(true)
+        .question("long.example.com", RecordType::TXT)
+        .answer(DnsAnswer::with_ttl("long.example.com".to_string(), 60, RecordData::TXT(vec![text.clone(), vec![]])))
+        .build()
+        .expect("message valide");
+
+    let decoded = DnsMessage::from_bytes(&message.to_bytes()).expect("message relu");
+    assert_eq!(decoded.answers[0].rdata, RecordData::TXT(vec![text[..255].to_vec(), text[255..].to_vec(), vec![]]));
+}